@@ -0,0 +1,388 @@
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use phoenix::{quantities::WrapperU64, state::{Side, TraderState}};
+use solana_program::{program_error::ProgramError, program_pack::{Pack, Sealed}};
+
+use crate::{error::CodeError, pricing::MarketParams, state::Position};
+
+
+/// Free and locked base and quote lots of a seat.
+pub fn seat_lots_total(trader_state: &TraderState) -> Result<(u64, u64), ProgramError> {
+    let base_lots = trader_state.base_lots_free.as_u64()
+        .checked_add(trader_state.base_lots_locked.as_u64())
+        .ok_or(CodeError::ArithmeticOverflow)?;
+    let quote_lots = trader_state.quote_lots_free.as_u64()
+        .checked_add(trader_state.quote_lots_locked.as_u64())
+        .ok_or(CodeError::ArithmeticOverflow)?;
+    Ok((base_lots, quote_lots))
+}
+
+/// Net change of the seat balances attributed to fills between two touches of a position.
+#[derive(Debug, PartialEq, Eq)]
+pub struct NetFill {
+    pub side: Side, // bid when the position bought base, ask when it sold base
+    pub base_lots: u64,
+    pub quote_lots: u64, // quote lots paid or received for the base lots
+}
+
+/// Attributes the change of the seat balances since the position was last touched to fills, then moves the snapshot.
+/// Only the net change is visible, so a buy and a sell of the same size between two touches cancel out.
+/// Returns the net fill when base changed hands.
+pub fn apply_fills(position: &mut Position, base_lots_total: u64, quote_lots_total: u64) -> Result<Option<NetFill>, ProgramError> {
+    let base_lots_delta = base_lots_total as i128 - position.last_base_lots_total as i128;
+    let quote_lots_delta = quote_lots_total as i128 - position.last_quote_lots_total as i128;
+
+    let mut net_fill = None;
+    if base_lots_delta > 0 {
+        let base_lots_bought = base_lots_delta as u64;
+        let quote_lots_spent = to_u64((-quote_lots_delta).max(0))?;
+        position.base_lots_bought = checked_add(position.base_lots_bought, base_lots_bought)?;
+        position.quote_lots_spent = checked_add(position.quote_lots_spent, quote_lots_spent)?;
+        position.cost_basis_quote_lots = checked_add(position.cost_basis_quote_lots, quote_lots_spent)?;
+        if quote_lots_delta > 0 {
+            // quote received on top of the base, e.g. a rebate
+            add_realized_pnl(position, quote_lots_delta)?;
+        }
+        net_fill = Some(NetFill { side: Side::Bid, base_lots: base_lots_bought, quote_lots: quote_lots_spent });
+    } else if base_lots_delta < 0 {
+        let base_lots_sold = (-base_lots_delta) as u64;
+        let quote_lots_received = to_u64(quote_lots_delta.max(0))?;
+        let cost_removed = cost_of_base_lots(position, base_lots_sold)?;
+        position.base_lots_sold = checked_add(position.base_lots_sold, base_lots_sold)?;
+        position.quote_lots_received = checked_add(position.quote_lots_received, quote_lots_received)?;
+        position.cost_basis_quote_lots -= cost_removed;
+        add_realized_pnl(position, quote_lots_delta - cost_removed as i128)?;
+        net_fill = Some(NetFill { side: Side::Ask, base_lots: base_lots_sold, quote_lots: quote_lots_received });
+    } else if quote_lots_delta != 0 {
+        // quote moved without base, e.g. fees
+        add_realized_pnl(position, quote_lots_delta)?;
+    }
+
+    position.last_base_lots_total = base_lots_total;
+    position.last_quote_lots_total = quote_lots_total;
+    Ok(net_fill)
+}
+
+/// Records a deposit made by the program. `base_cost_in_quote_lots` is the value the deposited base enters the cost basis at.
+pub fn record_deposit(position: &mut Position, base_lots: u64, quote_lots: u64, base_cost_in_quote_lots: u64) -> Result<(), ProgramError> {
+    position.total_base_deposited_lots = checked_add(position.total_base_deposited_lots, base_lots)?;
+    position.total_quote_deposited_lots = checked_add(position.total_quote_deposited_lots, quote_lots)?;
+    position.cost_basis_quote_lots = checked_add(position.cost_basis_quote_lots, base_cost_in_quote_lots)?;
+    position.last_base_lots_total = checked_add(position.last_base_lots_total, base_lots)?;
+    position.last_quote_lots_total = checked_add(position.last_quote_lots_total, quote_lots)?;
+    Ok(())
+}
+
+/// Records a withdrawal made by the program. Withdrawn base leaves the cost basis at average cost, realizing nothing.
+pub fn record_withdrawal(position: &mut Position, base_lots: u64, quote_lots: u64) -> Result<(), ProgramError> {
+    let cost_removed = cost_of_base_lots(position, base_lots)?;
+    position.cost_basis_quote_lots -= cost_removed;
+    position.total_base_withdrawn_lots = checked_add(position.total_base_withdrawn_lots, base_lots)?;
+    position.total_quote_withdrawn_lots = checked_add(position.total_quote_withdrawn_lots, quote_lots)?;
+    position.last_base_lots_total = position.last_base_lots_total
+        .checked_sub(base_lots)
+        .ok_or(CodeError::ArithmeticOverflow)?;
+    position.last_quote_lots_total = position.last_quote_lots_total
+        .checked_sub(quote_lots)
+        .ok_or(CodeError::ArithmeticOverflow)?;
+    Ok(())
+}
+
+/// Average price paid for the base lots held, in ticks, rounded down. 0 when no base is held.
+pub fn average_entry_price_in_ticks(market_params: &MarketParams, position: &Position) -> Result<u64, ProgramError> {
+    price_in_ticks(market_params, position.last_base_lots_total, position.cost_basis_quote_lots)
+}
+
+/// Price of `base_lots` traded for `quote_lots`, in ticks, rounded down. 0 when no base is traded.
+pub fn price_in_ticks(market_params: &MarketParams, base_lots: u64, quote_lots: u64) -> Result<u64, ProgramError> {
+    if base_lots == 0 {
+        return Ok(0);
+    }
+    let numerator = (quote_lots as u128)
+        .checked_mul(market_params.base_lots_per_base_unit as u128)
+        .ok_or(CodeError::ArithmeticOverflow)?;
+    let denominator = (base_lots as u128)
+        .checked_mul(market_params.tick_size_in_quote_lots_per_base_unit as u128)
+        .ok_or(CodeError::ArithmeticOverflow)?;
+    if denominator == 0 {
+        return Err(CodeError::InvalidPrice.into());
+    }
+    to_u64((numerator / denominator) as i128)
+}
+
+/// Share of the cost basis carried by `base_lots` of the base held, rounded down. All of it when every lot leaves.
+fn cost_of_base_lots(position: &Position, base_lots: u64) -> Result<u64, ProgramError> {
+    if base_lots >= position.last_base_lots_total {
+        return Ok(position.cost_basis_quote_lots);
+    }
+    let cost = (position.cost_basis_quote_lots as u128)
+        .checked_mul(base_lots as u128)
+        .ok_or(CodeError::ArithmeticOverflow)?
+        / position.last_base_lots_total as u128;
+    to_u64(cost as i128)
+}
+
+fn add_realized_pnl(position: &mut Position, quote_lots: i128) -> Result<(), ProgramError> {
+    let realized_pnl = (position.realized_pnl_quote_lots as i128)
+        .checked_add(quote_lots)
+        .ok_or(CodeError::ArithmeticOverflow)?;
+    position.realized_pnl_quote_lots = i64::try_from(realized_pnl).map_err(|_| CodeError::ArithmeticOverflow)?;
+    Ok(())
+}
+
+fn checked_add(a: u64, b: u64) -> Result<u64, ProgramError> {
+    a.checked_add(b).ok_or(CodeError::ArithmeticOverflow.into())
+}
+
+fn to_u64(value: i128) -> Result<u64, ProgramError> {
+    u64::try_from(value).map_err(|_| CodeError::ArithmeticOverflow.into())
+}
+
+
+/// Position statistics returned by GetPositionStats, all amounts in lots.
+pub struct PositionStats {
+    pub base_lots: u64, // free and locked base lots of the seat
+    pub quote_lots: u64, // free and locked quote lots of the seat
+    pub total_base_deposited_lots: u64,
+    pub total_quote_deposited_lots: u64,
+    pub total_base_withdrawn_lots: u64,
+    pub total_quote_withdrawn_lots: u64,
+    pub base_lots_bought: u64,
+    pub base_lots_sold: u64,
+    pub quote_lots_spent: u64,
+    pub quote_lots_received: u64,
+    pub cost_basis_quote_lots: u64,
+    pub average_entry_price_in_ticks: u64, // 0 when no base is held
+    pub realized_pnl_quote_lots: i64,
+}
+
+impl PositionStats {
+    pub fn new(market_params: &MarketParams, position: &Position) -> Result<Self, ProgramError> {
+        Ok(PositionStats {
+            base_lots: position.last_base_lots_total,
+            quote_lots: position.last_quote_lots_total,
+            total_base_deposited_lots: position.total_base_deposited_lots,
+            total_quote_deposited_lots: position.total_quote_deposited_lots,
+            total_base_withdrawn_lots: position.total_base_withdrawn_lots,
+            total_quote_withdrawn_lots: position.total_quote_withdrawn_lots,
+            base_lots_bought: position.base_lots_bought,
+            base_lots_sold: position.base_lots_sold,
+            quote_lots_spent: position.quote_lots_spent,
+            quote_lots_received: position.quote_lots_received,
+            cost_basis_quote_lots: position.cost_basis_quote_lots,
+            average_entry_price_in_ticks: average_entry_price_in_ticks(market_params, position)?,
+            realized_pnl_quote_lots: position.realized_pnl_quote_lots,
+        })
+    }
+}
+
+impl Sealed for PositionStats {}
+
+impl Pack for PositionStats {
+    const LEN: usize = 8 * 13;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, PositionStats::LEN];
+        let (
+            base_lots,
+            quote_lots,
+            total_base_deposited_lots,
+            total_quote_deposited_lots,
+            total_base_withdrawn_lots,
+            total_quote_withdrawn_lots,
+            base_lots_bought,
+            base_lots_sold,
+            quote_lots_spent,
+            quote_lots_received,
+            cost_basis_quote_lots,
+            average_entry_price_in_ticks,
+            realized_pnl_quote_lots,
+        ) = array_refs![src, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8];
+        Ok(PositionStats {
+            base_lots: u64::from_le_bytes(*base_lots),
+            quote_lots: u64::from_le_bytes(*quote_lots),
+            total_base_deposited_lots: u64::from_le_bytes(*total_base_deposited_lots),
+            total_quote_deposited_lots: u64::from_le_bytes(*total_quote_deposited_lots),
+            total_base_withdrawn_lots: u64::from_le_bytes(*total_base_withdrawn_lots),
+            total_quote_withdrawn_lots: u64::from_le_bytes(*total_quote_withdrawn_lots),
+            base_lots_bought: u64::from_le_bytes(*base_lots_bought),
+            base_lots_sold: u64::from_le_bytes(*base_lots_sold),
+            quote_lots_spent: u64::from_le_bytes(*quote_lots_spent),
+            quote_lots_received: u64::from_le_bytes(*quote_lots_received),
+            cost_basis_quote_lots: u64::from_le_bytes(*cost_basis_quote_lots),
+            average_entry_price_in_ticks: u64::from_le_bytes(*average_entry_price_in_ticks),
+            realized_pnl_quote_lots: i64::from_le_bytes(*realized_pnl_quote_lots),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, PositionStats::LEN];
+        let (
+            base_lots,
+            quote_lots,
+            total_base_deposited_lots,
+            total_quote_deposited_lots,
+            total_base_withdrawn_lots,
+            total_quote_withdrawn_lots,
+            base_lots_bought,
+            base_lots_sold,
+            quote_lots_spent,
+            quote_lots_received,
+            cost_basis_quote_lots,
+            average_entry_price_in_ticks,
+            realized_pnl_quote_lots,
+        ) = mut_array_refs![dst, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8];
+        *base_lots = self.base_lots.to_le_bytes();
+        *quote_lots = self.quote_lots.to_le_bytes();
+        *total_base_deposited_lots = self.total_base_deposited_lots.to_le_bytes();
+        *total_quote_deposited_lots = self.total_quote_deposited_lots.to_le_bytes();
+        *total_base_withdrawn_lots = self.total_base_withdrawn_lots.to_le_bytes();
+        *total_quote_withdrawn_lots = self.total_quote_withdrawn_lots.to_le_bytes();
+        *base_lots_bought = self.base_lots_bought.to_le_bytes();
+        *base_lots_sold = self.base_lots_sold.to_le_bytes();
+        *quote_lots_spent = self.quote_lots_spent.to_le_bytes();
+        *quote_lots_received = self.quote_lots_received.to_le_bytes();
+        *cost_basis_quote_lots = self.cost_basis_quote_lots.to_le_bytes();
+        *average_entry_price_in_ticks = self.average_entry_price_in_ticks.to_le_bytes();
+        *realized_pnl_quote_lots = self.realized_pnl_quote_lots.to_le_bytes();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::LegacyPosition;
+
+    fn market_params() -> MarketParams {
+        MarketParams {
+            base_lots_per_base_unit: 1_000,
+            tick_size_in_quote_lots_per_base_unit: 10,
+            base_atoms_per_base_lot: 1_000_000,
+            quote_atoms_per_quote_lot: 1,
+            tick_size_in_quote_atoms_per_base_unit: 10,
+            quote_decimals: 6,
+            raw_base_units_per_base_unit: 1,
+        }
+    }
+
+    fn position() -> Position {
+        Position::from(LegacyPosition { is_initialized: true, spread_margin: 1 })
+    }
+
+    fn bought(base_lots: u64, quote_lots: u64) -> Option<NetFill> {
+        Some(NetFill { side: Side::Bid, base_lots, quote_lots })
+    }
+
+    fn sold(base_lots: u64, quote_lots: u64) -> Option<NetFill> {
+        Some(NetFill { side: Side::Ask, base_lots, quote_lots })
+    }
+
+    #[test]
+    fn buy_then_sell_at_a_profit() {
+        let mut position = position();
+        record_deposit(&mut position, 0, 10_000, 0).unwrap();
+
+        assert_eq!(apply_fills(&mut position, 100, 9_000), Ok(bought(100, 1_000)));
+        assert_eq!(position.cost_basis_quote_lots, 1_000);
+        assert_eq!(average_entry_price_in_ticks(&market_params(), &position), Ok(1_000));
+
+        assert_eq!(apply_fills(&mut position, 0, 10_200), Ok(sold(100, 1_200)));
+        assert_eq!(position.cost_basis_quote_lots, 0);
+        assert_eq!(position.realized_pnl_quote_lots, 200);
+        assert_eq!(average_entry_price_in_ticks(&market_params(), &position), Ok(0));
+        assert_eq!(
+            (position.base_lots_bought, position.base_lots_sold, position.quote_lots_spent, position.quote_lots_received),
+            (100, 100, 1_000, 1_200),
+        );
+    }
+
+    #[test]
+    fn selling_at_a_loss_realizes_a_negative_pnl() {
+        let mut position = position();
+        record_deposit(&mut position, 0, 10_000, 0).unwrap();
+        apply_fills(&mut position, 100, 9_000).unwrap();
+
+        assert_eq!(apply_fills(&mut position, 0, 9_900), Ok(sold(100, 900)));
+        assert_eq!(position.realized_pnl_quote_lots, -100);
+    }
+
+    #[test]
+    fn partial_close_removes_average_cost() {
+        let mut position = position();
+        record_deposit(&mut position, 0, 10_000, 0).unwrap();
+        apply_fills(&mut position, 100, 9_000).unwrap();
+
+        assert_eq!(apply_fills(&mut position, 60, 9_500), Ok(sold(40, 500)));
+        assert_eq!(position.cost_basis_quote_lots, 600);
+        assert_eq!(position.realized_pnl_quote_lots, 100);
+        // the base left keeps its entry price
+        assert_eq!(average_entry_price_in_ticks(&market_params(), &position), Ok(1_000));
+    }
+
+    #[test]
+    fn partial_close_rounds_removed_cost_down() {
+        let mut position = position();
+        record_deposit(&mut position, 0, 1_000, 0).unwrap();
+        apply_fills(&mut position, 3, 900).unwrap();
+
+        // 100 * 1 / 3 = 33.33
+        assert_eq!(apply_fills(&mut position, 2, 940), Ok(sold(1, 40)));
+        assert_eq!(position.cost_basis_quote_lots, 67);
+        assert_eq!(position.realized_pnl_quote_lots, 7);
+    }
+
+    #[test]
+    fn position_flipping_from_buyer_to_seller() {
+        let mut position = position();
+        record_deposit(&mut position, 100, 2_000, 1_000).unwrap();
+
+        assert_eq!(apply_fills(&mut position, 150, 1_400), Ok(bought(50, 600)));
+        assert_eq!(position.cost_basis_quote_lots, 1_600);
+
+        // sells more than it bought, part of the deposited base goes with it
+        assert_eq!(apply_fills(&mut position, 30, 2_900), Ok(sold(120, 1_500)));
+        assert_eq!(position.cost_basis_quote_lots, 320);
+        assert_eq!(position.realized_pnl_quote_lots, 220);
+
+        assert_eq!(apply_fills(&mut position, 80, 2_300), Ok(bought(50, 600)));
+        assert_eq!(position.cost_basis_quote_lots, 920);
+        assert_eq!(position.realized_pnl_quote_lots, 220);
+    }
+
+    #[test]
+    fn quote_moving_without_base_is_realized() {
+        let mut position = position();
+        record_deposit(&mut position, 100, 1_000, 1_000).unwrap();
+
+        assert_eq!(apply_fills(&mut position, 100, 995), Ok(None));
+        assert_eq!(position.realized_pnl_quote_lots, -5);
+        assert_eq!(position.cost_basis_quote_lots, 1_000);
+        assert_eq!(apply_fills(&mut position, 100, 995), Ok(None));
+        assert_eq!(position.realized_pnl_quote_lots, -5);
+    }
+
+    #[test]
+    fn withdrawal_reduces_cost_basis_without_realizing() {
+        let mut position = position();
+        record_deposit(&mut position, 100, 500, 1_000).unwrap();
+        assert_eq!(average_entry_price_in_ticks(&market_params(), &position), Ok(1_000));
+
+        record_withdrawal(&mut position, 25, 100).unwrap();
+        assert_eq!(position.cost_basis_quote_lots, 750);
+        assert_eq!(position.realized_pnl_quote_lots, 0);
+        assert_eq!((position.last_base_lots_total, position.last_quote_lots_total), (75, 400));
+        assert_eq!((position.total_base_withdrawn_lots, position.total_quote_withdrawn_lots), (25, 100));
+        assert_eq!(average_entry_price_in_ticks(&market_params(), &position), Ok(1_000));
+
+        record_withdrawal(&mut position, 75, 0).unwrap();
+        assert_eq!(position.cost_basis_quote_lots, 0);
+    }
+
+    #[test]
+    fn withdrawing_more_than_held_is_rejected() {
+        for (base_lots, quote_lots) in [(101, 0), (0, 501)] {
+            let mut position = position();
+            record_deposit(&mut position, 100, 500, 1_000).unwrap();
+            assert_eq!(record_withdrawal(&mut position, base_lots, quote_lots), Err(CodeError::ArithmeticOverflow.into()));
+        }
+    }
+}
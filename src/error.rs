@@ -12,6 +12,57 @@ pub enum CodeError {
 
     #[error("Position is not initialized")]
     PositionNotInitialized,
+
+    #[error("Computed quote price is out of range")]
+    InvalidPrice,
+
+    #[error("Oracle account is invalid")]
+    InvalidOracle,
+
+    #[error("Oracle price is stale")]
+    StaleOraclePrice,
+
+    #[error("Oracle confidence interval is too wide")]
+    OracleConfidenceTooWide,
+
+    #[error("Order book is empty or one-sided")]
+    EmptyOrderBook,
+
+    #[error("Position doesn't hold enough funds")]
+    InsufficientFunds,
+
+    #[error("Position registry is full")]
+    PositionRegistryFull,
+
+    #[error("Operation is not allowed on a vault")]
+    NotAllowedOnVault,
+
+    #[error("Share amount is invalid")]
+    InvalidShareAmount,
+
+    #[error("Amount received is below the requested minimum")]
+    SlippageExceeded,
+
+    #[error("Position isn't open to the public crank")]
+    CrankNotEnabled,
+
+    #[error("Crank interval hasn't passed since the last crank")]
+    CrankTooEarly,
+
+    #[error("Program is paused")]
+    ProgramPaused,
+
+    #[error("Market isn't allowed by its market config")]
+    MarketNotAllowed,
+
+    #[error("Position parameters are outside the config bounds")]
+    ParamsOutOfBounds,
+
+    #[error("Market tick size is outside the market config bounds")]
+    TickSizeOutOfBounds,
+
+    #[error("Fill history is already initialized")]
+    FillHistoryIsAlreadyInitialized,
 }
 
 impl From<CodeError> for ProgramError {
@@ -0,0 +1,660 @@
+use solana_program::{log::sol_log_data, program_error::ProgramError, pubkey::Pubkey};
+
+use crate::{instruction::{Instruction, PositionParams}, pricing::LevelOrder};
+
+
+/// Events logged with `sol_log_data`, one event per data log. The first byte is the event tag,
+/// the fields follow in declaration order, integers little endian and pubkeys as 32 bytes.
+/// Tags and field layouts are stable, new events get new tags.
+/// Position parameters are logged with their own layout, preceded by its version, so that adding parameters doesn't change the layout of older logs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    PositionCreated(PositionCreated),
+    OrdersPlaced(OrdersPlaced),
+    OrdersCancelled(OrdersCancelled),
+    FundsDeposited(FundsDeposited),
+    FundsWithdrawn(FundsWithdrawn),
+    PositionClosed(PositionClosed),
+    ParamsUpdated(ParamsUpdated),
+    VaultCreated(VaultCreated),
+    VaultDeposit(VaultDeposit),
+    VaultRedeem(VaultRedeem),
+    FeesCollected(FeesCollected),
+    KeeperUpdated(KeeperUpdated),
+    Cranked(Cranked),
+    PositionPaused(PositionPaused),
+    PositionResumed(PositionResumed),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PositionCreated {
+    pub position: Pubkey,
+    pub trader: Pubkey,
+    pub market: Pubkey,
+    pub position_index: u16,
+    pub params: PositionParams,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrdersPlaced {
+    pub position: Pubkey,
+    pub client_order_id: u128,
+    pub num_orders: u8, // orders sent to phoenix, orders phoenix drops for lack of funds are counted
+    pub bid_base_lots: u64, // total size of the bids sent
+    pub ask_base_lots: u64, // total size of the asks sent
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrdersCancelled {
+    pub position: Pubkey, // every resting order of the position was cancelled
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FundsDeposited {
+    pub position: Pubkey,
+    pub base_lots: u64,
+    pub quote_lots: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FundsWithdrawn {
+    pub position: Pubkey,
+    pub base_lots: u64,
+    pub quote_lots: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PositionClosed {
+    pub position: Pubkey,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParamsUpdated {
+    pub position: Pubkey,
+    pub params: PositionParams, // parameters after the update
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VaultCreated {
+    pub position: Pubkey,
+    pub share_mint: Pubkey,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VaultDeposit {
+    pub position: Pubkey,
+    pub depositor: Pubkey,
+    pub base_lots: u64,
+    pub quote_lots: u64,
+    pub shares: u64, // shares minted to the depositor
+    pub nav_quote_lots: u64, // value of the vault before the deposit
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VaultRedeem {
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub shares: u64, // shares burned
+    pub base_lots: u64,
+    pub quote_lots: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FeesCollected {
+    pub position: Pubkey,
+    pub fee_recipient: Pubkey,
+    pub fee_shares: u64, // shares minted to the fee recipient
+    pub nav_quote_lots: u64, // value of the vault the fees were charged on
+    pub high_water_mark: u64, // nav per share mark after the accrual, scaled by fees::NAV_PER_SHARE_SCALE
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeeperUpdated {
+    pub position: Pubkey,
+    pub keeper: Pubkey, // default when the keeper was removed
+    pub permissions: u8,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cranked {
+    pub position: Pubkey,
+    pub cranker: Pubkey,
+    pub slot: u64,
+    pub tip_lamports: u64, // tip paid to the cranker
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PositionPaused {
+    pub position: Pubkey,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PositionResumed {
+    pub position: Pubkey,
+}
+
+impl OrdersPlaced {
+    /// Summarizes the non-empty levels of a ladder.
+    pub fn from_ladder(position: Pubkey, client_order_id: u128, bid_orders: &[LevelOrder], ask_orders: &[LevelOrder]) -> Self {
+        let num_orders = bid_orders.iter().chain(ask_orders).filter(|order| order.num_base_lots > 0).count();
+        OrdersPlaced {
+            position,
+            client_order_id,
+            num_orders: num_orders.min(u8::MAX as usize) as u8,
+            bid_base_lots: bid_orders.iter().fold(0u64, |total, order| total.saturating_add(order.num_base_lots)),
+            ask_base_lots: ask_orders.iter().fold(0u64, |total, order| total.saturating_add(order.num_base_lots)),
+        }
+    }
+}
+
+impl Event {
+    /// Layout version of the position parameters logged by PositionCreated and ParamsUpdated.
+    /// Version 0 is the unversioned layout of tags 0 and 6, version 1 adds the risk limits.
+    pub const POSITION_PARAMS_VERSION: u8 = 1;
+
+    pub fn emit(&self) {
+        sol_log_data(&[&self.pack()]);
+    }
+
+    pub fn pack(&self) -> Vec<u8> {
+        let mut buf = vec![];
+        match self {
+            Event::PositionCreated(event) => {
+                buf.push(15);
+                buf.extend_from_slice(event.position.as_ref());
+                buf.extend_from_slice(event.trader.as_ref());
+                buf.extend_from_slice(event.market.as_ref());
+                buf.extend_from_slice(&event.position_index.to_le_bytes());
+                Self::pack_position_params(&event.params, &mut buf);
+            }
+            Event::OrdersPlaced(event) => {
+                buf.push(1);
+                buf.extend_from_slice(event.position.as_ref());
+                buf.extend_from_slice(&event.client_order_id.to_le_bytes());
+                buf.push(event.num_orders);
+                buf.extend_from_slice(&event.bid_base_lots.to_le_bytes());
+                buf.extend_from_slice(&event.ask_base_lots.to_le_bytes());
+            }
+            Event::OrdersCancelled(event) => {
+                buf.push(2);
+                buf.extend_from_slice(event.position.as_ref());
+            }
+            Event::FundsDeposited(event) => {
+                buf.push(3);
+                buf.extend_from_slice(event.position.as_ref());
+                buf.extend_from_slice(&event.base_lots.to_le_bytes());
+                buf.extend_from_slice(&event.quote_lots.to_le_bytes());
+            }
+            Event::FundsWithdrawn(event) => {
+                buf.push(4);
+                buf.extend_from_slice(event.position.as_ref());
+                buf.extend_from_slice(&event.base_lots.to_le_bytes());
+                buf.extend_from_slice(&event.quote_lots.to_le_bytes());
+            }
+            Event::PositionClosed(event) => {
+                buf.push(5);
+                buf.extend_from_slice(event.position.as_ref());
+            }
+            Event::ParamsUpdated(event) => {
+                buf.push(16);
+                buf.extend_from_slice(event.position.as_ref());
+                Self::pack_position_params(&event.params, &mut buf);
+            }
+            Event::VaultCreated(event) => {
+                buf.push(7);
+                buf.extend_from_slice(event.position.as_ref());
+                buf.extend_from_slice(event.share_mint.as_ref());
+            }
+            Event::VaultDeposit(event) => {
+                buf.push(8);
+                buf.extend_from_slice(event.position.as_ref());
+                buf.extend_from_slice(event.depositor.as_ref());
+                buf.extend_from_slice(&event.base_lots.to_le_bytes());
+                buf.extend_from_slice(&event.quote_lots.to_le_bytes());
+                buf.extend_from_slice(&event.shares.to_le_bytes());
+                buf.extend_from_slice(&event.nav_quote_lots.to_le_bytes());
+            }
+            Event::VaultRedeem(event) => {
+                buf.push(9);
+                buf.extend_from_slice(event.position.as_ref());
+                buf.extend_from_slice(event.owner.as_ref());
+                buf.extend_from_slice(&event.shares.to_le_bytes());
+                buf.extend_from_slice(&event.base_lots.to_le_bytes());
+                buf.extend_from_slice(&event.quote_lots.to_le_bytes());
+            }
+            Event::FeesCollected(event) => {
+                buf.push(10);
+                buf.extend_from_slice(event.position.as_ref());
+                buf.extend_from_slice(event.fee_recipient.as_ref());
+                buf.extend_from_slice(&event.fee_shares.to_le_bytes());
+                buf.extend_from_slice(&event.nav_quote_lots.to_le_bytes());
+                buf.extend_from_slice(&event.high_water_mark.to_le_bytes());
+            }
+            Event::KeeperUpdated(event) => {
+                buf.push(11);
+                buf.extend_from_slice(event.position.as_ref());
+                buf.extend_from_slice(event.keeper.as_ref());
+                buf.push(event.permissions);
+            }
+            Event::Cranked(event) => {
+                buf.push(12);
+                buf.extend_from_slice(event.position.as_ref());
+                buf.extend_from_slice(event.cranker.as_ref());
+                buf.extend_from_slice(&event.slot.to_le_bytes());
+                buf.extend_from_slice(&event.tip_lamports.to_le_bytes());
+            }
+            Event::PositionPaused(event) => {
+                buf.push(13);
+                buf.extend_from_slice(event.position.as_ref());
+            }
+            Event::PositionResumed(event) => {
+                buf.push(14);
+                buf.extend_from_slice(event.position.as_ref());
+            }
+        }
+        buf
+    }
+
+    /// Decodes an event from the data of a `Program data:` log line, after base64 decoding.
+    /// Tags 0 and 6 are the PositionCreated and ParamsUpdated logs written before the parameters were versioned.
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        let (tag, rest) = input.split_first().ok_or(ProgramError::InvalidInstructionData)?;
+
+        Ok(match tag {
+            0 | 15 => {
+                let (position, rest) = Instruction::unpack_pubkey(rest)?;
+                let (trader, rest) = Instruction::unpack_pubkey(rest)?;
+                let (market, rest) = Instruction::unpack_pubkey(rest)?;
+                let (position_index, rest) = Instruction::unpack_u16(rest)?;
+                let (params, _rest) = Self::unpack_position_params(*tag == 15, rest)?;
+
+                Event::PositionCreated(PositionCreated {
+                    position,
+                    trader,
+                    market,
+                    position_index,
+                    params,
+                })
+            }
+            1 => {
+                let (position, rest) = Instruction::unpack_pubkey(rest)?;
+                let (client_order_id, rest) = Instruction::unpack_u128(rest)?;
+                let (num_orders, rest) = Instruction::unpack_u8(rest)?;
+                let (bid_base_lots, rest) = Instruction::unpack_u64(rest)?;
+                let (ask_base_lots, _rest) = Instruction::unpack_u64(rest)?;
+
+                Event::OrdersPlaced(OrdersPlaced {
+                    position,
+                    client_order_id,
+                    num_orders,
+                    bid_base_lots,
+                    ask_base_lots,
+                })
+            }
+            2 => {
+                let (position, _rest) = Instruction::unpack_pubkey(rest)?;
+
+                Event::OrdersCancelled(OrdersCancelled {
+                    position,
+                })
+            }
+            3 => {
+                let (position, rest) = Instruction::unpack_pubkey(rest)?;
+                let (base_lots, rest) = Instruction::unpack_u64(rest)?;
+                let (quote_lots, _rest) = Instruction::unpack_u64(rest)?;
+
+                Event::FundsDeposited(FundsDeposited {
+                    position,
+                    base_lots,
+                    quote_lots,
+                })
+            }
+            4 => {
+                let (position, rest) = Instruction::unpack_pubkey(rest)?;
+                let (base_lots, rest) = Instruction::unpack_u64(rest)?;
+                let (quote_lots, _rest) = Instruction::unpack_u64(rest)?;
+
+                Event::FundsWithdrawn(FundsWithdrawn {
+                    position,
+                    base_lots,
+                    quote_lots,
+                })
+            }
+            5 => {
+                let (position, _rest) = Instruction::unpack_pubkey(rest)?;
+
+                Event::PositionClosed(PositionClosed {
+                    position,
+                })
+            }
+            6 | 16 => {
+                let (position, rest) = Instruction::unpack_pubkey(rest)?;
+                let (params, _rest) = Self::unpack_position_params(*tag == 16, rest)?;
+
+                Event::ParamsUpdated(ParamsUpdated {
+                    position,
+                    params,
+                })
+            }
+            7 => {
+                let (position, rest) = Instruction::unpack_pubkey(rest)?;
+                let (share_mint, _rest) = Instruction::unpack_pubkey(rest)?;
+
+                Event::VaultCreated(VaultCreated {
+                    position,
+                    share_mint,
+                })
+            }
+            8 => {
+                let (position, rest) = Instruction::unpack_pubkey(rest)?;
+                let (depositor, rest) = Instruction::unpack_pubkey(rest)?;
+                let (base_lots, rest) = Instruction::unpack_u64(rest)?;
+                let (quote_lots, rest) = Instruction::unpack_u64(rest)?;
+                let (shares, rest) = Instruction::unpack_u64(rest)?;
+                let (nav_quote_lots, _rest) = Instruction::unpack_u64(rest)?;
+
+                Event::VaultDeposit(VaultDeposit {
+                    position,
+                    depositor,
+                    base_lots,
+                    quote_lots,
+                    shares,
+                    nav_quote_lots,
+                })
+            }
+            9 => {
+                let (position, rest) = Instruction::unpack_pubkey(rest)?;
+                let (owner, rest) = Instruction::unpack_pubkey(rest)?;
+                let (shares, rest) = Instruction::unpack_u64(rest)?;
+                let (base_lots, rest) = Instruction::unpack_u64(rest)?;
+                let (quote_lots, _rest) = Instruction::unpack_u64(rest)?;
+
+                Event::VaultRedeem(VaultRedeem {
+                    position,
+                    owner,
+                    shares,
+                    base_lots,
+                    quote_lots,
+                })
+            }
+            10 => {
+                let (position, rest) = Instruction::unpack_pubkey(rest)?;
+                let (fee_recipient, rest) = Instruction::unpack_pubkey(rest)?;
+                let (fee_shares, rest) = Instruction::unpack_u64(rest)?;
+                let (nav_quote_lots, rest) = Instruction::unpack_u64(rest)?;
+                let (high_water_mark, _rest) = Instruction::unpack_u64(rest)?;
+
+                Event::FeesCollected(FeesCollected {
+                    position,
+                    fee_recipient,
+                    fee_shares,
+                    nav_quote_lots,
+                    high_water_mark,
+                })
+            }
+            11 => {
+                let (position, rest) = Instruction::unpack_pubkey(rest)?;
+                let (keeper, rest) = Instruction::unpack_pubkey(rest)?;
+                let (permissions, _rest) = Instruction::unpack_u8(rest)?;
+
+                Event::KeeperUpdated(KeeperUpdated {
+                    position,
+                    keeper,
+                    permissions,
+                })
+            }
+            12 => {
+                let (position, rest) = Instruction::unpack_pubkey(rest)?;
+                let (cranker, rest) = Instruction::unpack_pubkey(rest)?;
+                let (slot, rest) = Instruction::unpack_u64(rest)?;
+                let (tip_lamports, _rest) = Instruction::unpack_u64(rest)?;
+
+                Event::Cranked(Cranked {
+                    position,
+                    cranker,
+                    slot,
+                    tip_lamports,
+                })
+            }
+            13 => {
+                let (position, _rest) = Instruction::unpack_pubkey(rest)?;
+
+                Event::PositionPaused(PositionPaused {
+                    position,
+                })
+            }
+            14 => {
+                let (position, _rest) = Instruction::unpack_pubkey(rest)?;
+
+                Event::PositionResumed(PositionResumed {
+                    position,
+                })
+            }
+            _ => return Err(ProgramError::InvalidInstructionData),
+        })
+    }
+
+    /// Writes the position parameters in the latest layout, preceded by its version.
+    fn pack_position_params(params: &PositionParams, buf: &mut Vec<u8>) {
+        buf.push(Self::POSITION_PARAMS_VERSION);
+        buf.push(params.spread_mode);
+        buf.extend_from_slice(&params.spread_margin.to_le_bytes());
+        buf.push(params.num_levels);
+        buf.push(params.level_spacing_mode);
+        buf.extend_from_slice(&params.level_spacing.to_le_bytes());
+        buf.push(params.size_profile);
+        buf.extend_from_slice(&params.size_profile_param.to_le_bytes());
+        buf.extend_from_slice(&params.target_base_ratio_bps.to_le_bytes());
+        buf.extend_from_slice(&params.skew_bps.to_le_bytes());
+        buf.push(params.price_source);
+        buf.extend_from_slice(params.oracle.as_ref());
+        buf.extend_from_slice(&params.max_confidence_bps.to_le_bytes());
+        buf.extend_from_slice(&params.max_staleness_slots.to_le_bytes());
+        buf.push(params.mid_model);
+        buf.extend_from_slice(&params.vwap_depth_base_lots.to_le_bytes());
+        buf.push(params.empty_book_policy);
+        buf.push(params.fallback_price_source);
+        buf.extend_from_slice(&params.max_base_inventory_lots.to_le_bytes());
+        buf.extend_from_slice(&params.max_quote_inventory_lots.to_le_bytes());
+        buf.extend_from_slice(&params.max_notional_quote_lots.to_le_bytes());
+    }
+
+    /// Reads position parameters logged in any layout version, parameters the version doesn't have are 0.
+    /// Unversioned logs are read as version 0.
+    fn unpack_position_params(versioned: bool, input: &[u8]) -> Result<(PositionParams, &[u8]), ProgramError> {
+        let (version, rest) = match versioned {
+            true => Instruction::unpack_u8(input)?,
+            false => (0, input),
+        };
+        if version > Self::POSITION_PARAMS_VERSION {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let (spread_mode, rest) = Instruction::unpack_u8(rest)?;
+        let (spread_margin, rest) = Instruction::unpack_u64(rest)?;
+        let (num_levels, rest) = Instruction::unpack_u8(rest)?;
+        let (level_spacing_mode, rest) = Instruction::unpack_u8(rest)?;
+        let (level_spacing, rest) = Instruction::unpack_u64(rest)?;
+        let (size_profile, rest) = Instruction::unpack_u8(rest)?;
+        let (size_profile_param, rest) = Instruction::unpack_u64(rest)?;
+        let (target_base_ratio_bps, rest) = Instruction::unpack_u64(rest)?;
+        let (skew_bps, rest) = Instruction::unpack_u64(rest)?;
+        let (price_source, rest) = Instruction::unpack_u8(rest)?;
+        let (oracle, rest) = Instruction::unpack_pubkey(rest)?;
+        let (max_confidence_bps, rest) = Instruction::unpack_u64(rest)?;
+        let (max_staleness_slots, rest) = Instruction::unpack_u64(rest)?;
+        let (mid_model, rest) = Instruction::unpack_u8(rest)?;
+        let (vwap_depth_base_lots, rest) = Instruction::unpack_u64(rest)?;
+        let (empty_book_policy, rest) = Instruction::unpack_u8(rest)?;
+        let (fallback_price_source, rest) = Instruction::unpack_u8(rest)?;
+        let (max_base_inventory_lots, max_quote_inventory_lots, max_notional_quote_lots, rest) = match version {
+            0 => (0, 0, 0, rest),
+            _ => {
+                let (max_base_inventory_lots, rest) = Instruction::unpack_u64(rest)?;
+                let (max_quote_inventory_lots, rest) = Instruction::unpack_u64(rest)?;
+                let (max_notional_quote_lots, rest) = Instruction::unpack_u64(rest)?;
+                (max_base_inventory_lots, max_quote_inventory_lots, max_notional_quote_lots, rest)
+            }
+        };
+
+        Ok((PositionParams {
+            spread_mode,
+            spread_margin,
+            num_levels,
+            level_spacing_mode,
+            level_spacing,
+            size_profile,
+            size_profile_param,
+            target_base_ratio_bps,
+            skew_bps,
+            price_source,
+            oracle,
+            max_confidence_bps,
+            max_staleness_slots,
+            mid_model,
+            vwap_depth_base_lots,
+            empty_book_policy,
+            fallback_price_source,
+            max_base_inventory_lots,
+            max_quote_inventory_lots,
+            max_notional_quote_lots,
+        }, rest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> PositionParams {
+        PositionParams {
+            spread_mode: 1,
+            spread_margin: 25,
+            num_levels: 3,
+            level_spacing_mode: 2,
+            level_spacing: 4,
+            size_profile: 2,
+            size_profile_param: 5_000,
+            target_base_ratio_bps: 5_000,
+            skew_bps: 30,
+            price_source: 1,
+            oracle: Pubkey::new_unique(),
+            max_confidence_bps: 100,
+            max_staleness_slots: 50,
+            mid_model: 3,
+            vwap_depth_base_lots: 1_000,
+            empty_book_policy: 3,
+            fallback_price_source: 2,
+            max_base_inventory_lots: 7,
+            max_quote_inventory_lots: 8,
+            max_notional_quote_lots: 9,
+        }
+    }
+
+    /// Position parameters in the layout logged before the parameters were versioned.
+    fn pack_unversioned_params(params: &PositionParams, buf: &mut Vec<u8>) {
+        buf.push(params.spread_mode);
+        buf.extend_from_slice(&params.spread_margin.to_le_bytes());
+        buf.push(params.num_levels);
+        buf.push(params.level_spacing_mode);
+        buf.extend_from_slice(&params.level_spacing.to_le_bytes());
+        buf.push(params.size_profile);
+        buf.extend_from_slice(&params.size_profile_param.to_le_bytes());
+        buf.extend_from_slice(&params.target_base_ratio_bps.to_le_bytes());
+        buf.extend_from_slice(&params.skew_bps.to_le_bytes());
+        buf.push(params.price_source);
+        buf.extend_from_slice(params.oracle.as_ref());
+        buf.extend_from_slice(&params.max_confidence_bps.to_le_bytes());
+        buf.extend_from_slice(&params.max_staleness_slots.to_le_bytes());
+        buf.push(params.mid_model);
+        buf.extend_from_slice(&params.vwap_depth_base_lots.to_le_bytes());
+        buf.push(params.empty_book_policy);
+        buf.push(params.fallback_price_source);
+    }
+
+    fn without_versioned_params(params: PositionParams) -> PositionParams {
+        PositionParams {
+            max_base_inventory_lots: 0,
+            max_quote_inventory_lots: 0,
+            max_notional_quote_lots: 0,
+            ..params
+        }
+    }
+
+    #[test]
+    fn every_event_round_trips() {
+        let position = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let events = [
+            Event::PositionCreated(PositionCreated { position, trader: other, market: Pubkey::new_unique(), position_index: 3, params: params() }),
+            Event::OrdersPlaced(OrdersPlaced { position, client_order_id: u128::MAX - 1, num_orders: 6, bid_base_lots: 10, ask_base_lots: 20 }),
+            Event::OrdersCancelled(OrdersCancelled { position }),
+            Event::FundsDeposited(FundsDeposited { position, base_lots: 1, quote_lots: 2 }),
+            Event::FundsWithdrawn(FundsWithdrawn { position, base_lots: 3, quote_lots: 4 }),
+            Event::PositionClosed(PositionClosed { position }),
+            Event::ParamsUpdated(ParamsUpdated { position, params: params() }),
+            Event::VaultCreated(VaultCreated { position, share_mint: other }),
+            Event::VaultDeposit(VaultDeposit { position, depositor: other, base_lots: 1, quote_lots: 2, shares: 3, nav_quote_lots: 4 }),
+            Event::VaultRedeem(VaultRedeem { position, owner: other, shares: 5, base_lots: 6, quote_lots: 7 }),
+            Event::FeesCollected(FeesCollected { position, fee_recipient: other, fee_shares: 8, nav_quote_lots: 9, high_water_mark: 10 }),
+            Event::KeeperUpdated(KeeperUpdated { position, keeper: other, permissions: 3 }),
+            Event::Cranked(Cranked { position, cranker: other, slot: 11, tip_lamports: 12 }),
+            Event::PositionPaused(PositionPaused { position }),
+            Event::PositionResumed(PositionResumed { position }),
+        ];
+        let mut tags = vec![];
+        for event in events {
+            let packed = event.pack();
+            tags.push(packed[0]);
+            assert_eq!(Event::unpack(&packed).unwrap(), event);
+        }
+        assert_eq!(tags, [15, 1, 2, 3, 4, 5, 16, 7, 8, 9, 10, 11, 12, 13, 14]);
+    }
+
+    #[test]
+    fn unversioned_position_created_decodes() {
+        let (position, trader, market) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut buf = vec![0];
+        buf.extend_from_slice(position.as_ref());
+        buf.extend_from_slice(trader.as_ref());
+        buf.extend_from_slice(market.as_ref());
+        buf.extend_from_slice(&2u16.to_le_bytes());
+        let params = params();
+        pack_unversioned_params(&params, &mut buf);
+
+        assert_eq!(
+            Event::unpack(&buf).unwrap(),
+            Event::PositionCreated(PositionCreated { position, trader, market, position_index: 2, params: without_versioned_params(params) }),
+        );
+    }
+
+    #[test]
+    fn unversioned_params_updated_decodes() {
+        let position = Pubkey::new_unique();
+        let mut buf = vec![6];
+        buf.extend_from_slice(position.as_ref());
+        let params = params();
+        pack_unversioned_params(&params, &mut buf);
+
+        assert_eq!(
+            Event::unpack(&buf).unwrap(),
+            Event::ParamsUpdated(ParamsUpdated { position, params: without_versioned_params(params) }),
+        );
+    }
+
+    #[test]
+    fn unknown_params_version_and_tag_are_rejected() {
+        let mut packed = Event::ParamsUpdated(ParamsUpdated { position: Pubkey::new_unique(), params: params() }).pack();
+        packed[1 + 32] = Event::POSITION_PARAMS_VERSION + 1;
+        assert!(Event::unpack(&packed).is_err());
+
+        assert!(Event::unpack(&[17]).is_err());
+        assert!(Event::unpack(&[]).is_err());
+    }
+
+    #[test]
+    fn truncated_events_are_rejected() {
+        let packed = Event::Cranked(Cranked { position: Pubkey::new_unique(), cranker: Pubkey::new_unique(), slot: 1, tip_lamports: 2 }).pack();
+        assert!(Event::unpack(&packed[..packed.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,216 @@
+use solana_program::program_error::ProgramError;
+
+use crate::{error::CodeError, pricing::BPS_DENOMINATOR, state::Position};
+
+
+pub const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
+pub const NAV_PER_SHARE_SCALE: u64 = 1_000_000_000; // nav per share is stored in quote lots per share times this scale
+pub const MAX_MANAGEMENT_FEE_BPS: u64 = 1_000;
+pub const MAX_PERFORMANCE_FEE_BPS: u64 = 5_000;
+
+pub fn validate_fees(management_fee_bps: u64, performance_fee_bps: u64) -> Result<(), ProgramError> {
+    if management_fee_bps > MAX_MANAGEMENT_FEE_BPS || performance_fee_bps > MAX_PERFORMANCE_FEE_BPS {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    Ok(())
+}
+
+/// Fee shares to mint and the high-water mark after accruing fees on a vault.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeAccrual {
+    pub fee_shares: u64,
+    pub high_water_mark: u64,
+}
+
+/// Accrues the management fee for `elapsed_seconds` and the performance fee on the nav per share above `high_water_mark`.
+/// Both fees are valued on `nav` before fees and paid by minting shares, diluting the other holders by the fee value.
+/// An empty vault accrues nothing and its high-water mark goes back to the initial share price.
+pub fn accrue_fees(
+    nav: u64,
+    share_supply: u64,
+    high_water_mark: u64,
+    management_fee_bps: u64,
+    performance_fee_bps: u64,
+    elapsed_seconds: u64,
+) -> Result<FeeAccrual, ProgramError> {
+    if share_supply == 0 {
+        return Ok(FeeAccrual { fee_shares: 0, high_water_mark: NAV_PER_SHARE_SCALE });
+    }
+
+    let management_fee = management_fee(nav, management_fee_bps, elapsed_seconds)?;
+    let performance_fee = performance_fee(nav, share_supply, high_water_mark, performance_fee_bps)?;
+    // fees never take the whole vault
+    let fee_value = management_fee
+        .checked_add(performance_fee)
+        .ok_or(CodeError::ArithmeticOverflow)?
+        .min(nav.saturating_sub(1));
+    let fee_shares = fee_shares(fee_value, nav, share_supply)?;
+
+    // the mark moves to the share price after fees so the same gain isn't charged twice
+    let nav_after_fees = nav.checked_sub(fee_value).ok_or(CodeError::ArithmeticOverflow)?;
+    let high_water_mark = high_water_mark.max(nav_per_share(nav_after_fees, share_supply)?);
+
+    Ok(FeeAccrual { fee_shares, high_water_mark })
+}
+
+/// Accrues the vault's fees since its last accrual at `now` and moves its high-water mark and fee timestamp.
+/// Returns the fee shares to mint to the fee recipient.
+pub fn accrue_vault_fees(position: &mut Position, nav: u64, share_supply: u64, now: i64) -> Result<u64, ProgramError> {
+    let elapsed_seconds = u64::try_from(now.saturating_sub(position.last_fee_timestamp)).unwrap_or(0);
+    let accrual = accrue_fees(
+        nav,
+        share_supply,
+        position.high_water_mark,
+        position.management_fee_bps,
+        position.performance_fee_bps,
+        elapsed_seconds,
+    )?;
+    position.high_water_mark = accrual.high_water_mark;
+    position.last_fee_timestamp = now;
+    Ok(accrual.fee_shares)
+}
+
+/// Management fee on `nav` for `elapsed_seconds` at `fee_bps` a year, in quote lots, rounded down. Capped at `nav`.
+/// The product only overflows for fees far above `nav`, so it saturates instead of failing.
+pub fn management_fee(nav: u64, fee_bps: u64, elapsed_seconds: u64) -> Result<u64, ProgramError> {
+    let fee = (nav as u128)
+        .saturating_mul(fee_bps as u128)
+        .saturating_mul(elapsed_seconds as u128)
+        / (BPS_DENOMINATOR as u128 * SECONDS_PER_YEAR as u128);
+    to_u64(fee.min(nav as u128))
+}
+
+/// Performance fee on the gain of the nav per share above `high_water_mark`, in quote lots, rounded down.
+pub fn performance_fee(nav: u64, share_supply: u64, high_water_mark: u64, fee_bps: u64) -> Result<u64, ProgramError> {
+    let nav_per_share = nav_per_share(nav, share_supply)?;
+    if nav_per_share <= high_water_mark {
+        return Ok(0);
+    }
+    let fee = ((nav_per_share - high_water_mark) as u128)
+        .checked_mul(share_supply as u128)
+        .and_then(|fee| fee.checked_mul(fee_bps as u128))
+        .ok_or(CodeError::ArithmeticOverflow)?
+        / (NAV_PER_SHARE_SCALE as u128 * BPS_DENOMINATOR as u128);
+    to_u64(fee)
+}
+
+/// Nav per share scaled by `NAV_PER_SHARE_SCALE`, rounded down.
+/// Saturates at `u64::MAX`, over about 1.8e10 quote lots per share. The high-water mark stops there,
+/// and so does the performance fee, rather than failing every deposit and redemption of the vault.
+pub fn nav_per_share(nav: u64, share_supply: u64) -> Result<u64, ProgramError> {
+    if share_supply == 0 {
+        return Ok(NAV_PER_SHARE_SCALE);
+    }
+    // nav is at most u64::MAX, times the scale fits in a u128
+    let nav_per_share = nav as u128 * NAV_PER_SHARE_SCALE as u128 / share_supply as u128;
+    Ok(nav_per_share.min(u64::MAX as u128) as u64)
+}
+
+/// Shares that are worth `fee_value` once minted on top of `share_supply` in a vault worth `nav`, rounded down.
+pub fn fee_shares(fee_value: u64, nav: u64, share_supply: u64) -> Result<u64, ProgramError> {
+    if fee_value == 0 {
+        return Ok(0);
+    }
+    if fee_value >= nav {
+        return Err(CodeError::ArithmeticOverflow.into());
+    }
+    let shares = (fee_value as u128)
+        .checked_mul(share_supply as u128)
+        .ok_or(CodeError::ArithmeticOverflow)?
+        / (nav - fee_value) as u128;
+    to_u64(shares)
+}
+
+fn to_u64(value: u128) -> Result<u64, ProgramError> {
+    u64::try_from(value).map_err(|_| CodeError::ArithmeticOverflow.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::LegacyPosition;
+
+    #[test]
+    fn management_fee_accrues_with_time() {
+        assert_eq!(management_fee(1_000_000, 100, SECONDS_PER_YEAR).unwrap(), 10_000);
+        assert_eq!(management_fee(1_000_000, 100, SECONDS_PER_YEAR / 2).unwrap(), 5_000);
+        assert_eq!(management_fee(1_000_000, 100, 0).unwrap(), 0);
+        // rounded down, a second on a small vault charges nothing
+        assert_eq!(management_fee(1_000_000, 100, 1).unwrap(), 0);
+    }
+
+    #[test]
+    fn management_fee_is_capped_at_nav() {
+        assert_eq!(management_fee(1_000, MAX_MANAGEMENT_FEE_BPS, 100 * SECONDS_PER_YEAR).unwrap(), 1_000);
+        // the product overflows a u128, the fee saturates at the nav instead of failing
+        assert_eq!(management_fee(u64::MAX, MAX_MANAGEMENT_FEE_BPS, u64::MAX).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn performance_fee_only_charges_gains_above_high_water_mark() {
+        // nav per share of 1.1 against a mark of 1, 20% of the 100_000 gain
+        assert_eq!(performance_fee(1_100_000, 1_000_000, NAV_PER_SHARE_SCALE, 2_000).unwrap(), 20_000);
+        assert_eq!(performance_fee(1_000_000, 1_000_000, NAV_PER_SHARE_SCALE, 2_000).unwrap(), 0);
+        assert_eq!(performance_fee(900_000, 1_000_000, NAV_PER_SHARE_SCALE, 2_000).unwrap(), 0);
+    }
+
+    #[test]
+    fn accrual_mints_fee_shares_and_moves_the_mark_to_the_price_after_fees() {
+        let accrual = accrue_fees(1_100_000, 1_000_000, NAV_PER_SHARE_SCALE, 0, 2_000, 0).unwrap();
+        // 20_000 of value out of the 1_080_000 left to the other holders
+        assert_eq!(accrual.fee_shares, 18_518);
+        assert_eq!(accrual.high_water_mark, 1_080_000_000);
+
+        // at or below the mark the mark doesn't move
+        let accrual = accrue_fees(900_000, 1_000_000, NAV_PER_SHARE_SCALE, 0, 2_000, 0).unwrap();
+        assert_eq!(accrual, FeeAccrual { fee_shares: 0, high_water_mark: NAV_PER_SHARE_SCALE });
+    }
+
+    #[test]
+    fn empty_vault_resets_high_water_mark() {
+        let accrual = accrue_fees(0, 0, 3 * NAV_PER_SHARE_SCALE, MAX_MANAGEMENT_FEE_BPS, MAX_PERFORMANCE_FEE_BPS, SECONDS_PER_YEAR).unwrap();
+        assert_eq!(accrual, FeeAccrual { fee_shares: 0, high_water_mark: NAV_PER_SHARE_SCALE });
+    }
+
+    #[test]
+    fn fees_never_take_the_whole_vault() {
+        // a century of the maximum management fee is worth the whole nav, one quote lot is left to the holders
+        let accrual = accrue_fees(1_000, 1_000, NAV_PER_SHARE_SCALE, MAX_MANAGEMENT_FEE_BPS, 0, 100 * SECONDS_PER_YEAR).unwrap();
+        assert_eq!(accrual.fee_shares, 999 * 1_000);
+        assert_eq!(accrual.high_water_mark, NAV_PER_SHARE_SCALE);
+    }
+
+    #[test]
+    fn nav_per_share_saturates() {
+        assert_eq!(nav_per_share(1_000, 0).unwrap(), NAV_PER_SHARE_SCALE);
+        assert_eq!(nav_per_share(3, 2).unwrap(), 1_500_000_000);
+        assert_eq!(nav_per_share(u64::MAX, 1).unwrap(), u64::MAX);
+        // a saturated mark charges no further performance fee
+        assert_eq!(performance_fee(u64::MAX, 1, u64::MAX, MAX_PERFORMANCE_FEE_BPS).unwrap(), 0);
+        let accrual = accrue_fees(u64::MAX, 1, NAV_PER_SHARE_SCALE, 0, MAX_PERFORMANCE_FEE_BPS, 0).unwrap();
+        assert_eq!(accrual.high_water_mark, u64::MAX);
+    }
+
+    #[test]
+    fn fee_shares_overflow_is_an_error() {
+        assert_eq!(fee_shares(0, 1_000, 1_000).unwrap(), 0);
+        assert!(fee_shares(1_000, 1_000, 1_000).is_err());
+        // fee shares worth all but one quote lot of a large supply don't fit a u64
+        assert!(fee_shares(u64::MAX - 1, u64::MAX, u64::MAX).is_err());
+    }
+
+    #[test]
+    fn vault_accrual_moves_the_fee_timestamp() {
+        let mut position = Position::from(LegacyPosition { is_initialized: true, spread_margin: 1 });
+        position.management_fee_bps = 100;
+        position.high_water_mark = NAV_PER_SHARE_SCALE;
+        position.last_fee_timestamp = 1_000;
+
+        assert_eq!(accrue_vault_fees(&mut position, 1_000_000, 1_000_000, 1_000 + SECONDS_PER_YEAR as i64).unwrap(), 10_101);
+        assert_eq!(position.last_fee_timestamp, 1_000 + SECONDS_PER_YEAR as i64);
+
+        // a clock going backwards accrues nothing
+        assert_eq!(accrue_vault_fees(&mut position, 1_000_000, 1_000_000, 0).unwrap(), 0);
+        assert_eq!(position.last_fee_timestamp, 0);
+    }
+}
@@ -1,27 +1,284 @@
-use solana_program::program_error::ProgramError;
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
 
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PositionParams {
+    pub spread_mode: u8, // 1 for basis points, 2 for ticks
+    pub spread_margin: u64, // distance of limit orders from market price, in basis points or ticks depending on spread_mode
+    pub num_levels: u8, // number of limit orders per side
+    pub level_spacing_mode: u8, // 1 for basis points, 2 for ticks
+    pub level_spacing: u64, // distance between consecutive levels, in basis points or ticks depending on level_spacing_mode
+    pub size_profile: u8, // 1 for flat, 2 for linear, 3 for geometric
+    pub size_profile_param: u64, // size step (linear) or ratio (geometric) between levels, in basis points
+    pub target_base_ratio_bps: u64, // share of the inventory value the position aims to hold in base, in basis points
+    pub skew_bps: u64, // shift of the quotes' reference price when inventory is fully one-sided, in basis points of the mid. 0 disables skewing
+    pub price_source: u8, // 1 for order book, 2 for pyth, 3 for mock oracle
+    pub oracle: Pubkey, // oracle account, unused when price_source is order book
+    pub max_confidence_bps: u64, // widest accepted oracle confidence interval, in basis points of the price
+    pub max_staleness_slots: u64, // oldest accepted oracle price, in slots
+    pub mid_model: u8, // 1 for top of book, 2 for microprice, 3 for vwap. Used when price_source is order book
+    pub vwap_depth_base_lots: u64, // depth per side averaged by the vwap mid model
+    pub empty_book_policy: u8, // 1 to fail, 2 to use the last mid, 3 to use the oracle, 4 to quote only the populated side
+    pub fallback_price_source: u8, // 2 for pyth, 3 for mock oracle. Read by the oracle empty book policy when price_source is order book
+    pub max_base_inventory_lots: u64, // base lots held from which bids are no longer posted, 0 for no limit
+    pub max_quote_inventory_lots: u64, // quote lots held from which asks are no longer posted, 0 for no limit
+    pub max_notional_quote_lots: u64, // value of the base held at mid from which bids are no longer posted, 0 for no limit
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConfigParams {
+    pub min_spread_margin_bps: u64, // narrowest spread margin accepted in basis points spread mode
+    pub max_spread_margin_bps: u64, // widest spread margin accepted in basis points spread mode
+    pub max_num_levels: u8, // most ladder levels per side
+    pub max_skew_bps: u64, // largest inventory skew
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MarketConfigParams {
+    pub min_spread_in_ticks: u64, // narrowest distance of the top levels from the reference price
+    pub max_order_base_lots: u64, // largest order per ladder level, 0 for no limit
+    pub min_tick_size_in_quote_lots_per_base_unit: u64, // smallest tick size the market may report
+    pub max_tick_size_in_quote_lots_per_base_unit: u64, // largest tick size the market may report
+}
+
 pub struct CreatePosition {
-    pub side: u8, // 1 for bid, 2 for ask
-    pub spread_margin: u64, // percentage of spread to put limit orders at from market price
-    pub num_base_lots: u64,
+    pub position_index: u16, // index of the position among the trader's positions on the market
+    pub params: PositionParams,
+    pub bid_num_base_lots: u64, // total size of the bids across all levels, 0 to not quote bids
+    pub ask_num_base_lots: u64, // total size of the asks across all levels, 0 to not quote asks
     pub client_order_id: u128,
 }
 
+pub struct UpdatePosition {
+    pub position_index: u16, // index of the position among the trader's positions on the market
+    pub params: PositionParams,
+    pub requote: bool, // cancel and repost the ladder with the new parameters
+    pub client_order_id: u128,
+}
+
+pub struct CancelPosition {
+    pub position_index: u16, // index of the position among the trader's positions on the market
+}
+
 pub struct PlaceLimitOrdersWithFreeFunds {
+    pub position_index: u16, // index of the position among the trader's positions on the market
+    pub client_order_id: u128,
+}
+
+pub struct Requote {
+    pub position_index: u16, // index of the position among the trader's positions on the market
+    pub client_order_id: u128,
+}
+
+pub struct SetMockOracle {
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+}
+
+pub struct AddFunds {
+    pub position_index: u16, // index of the position among the trader's positions on the market
+    pub base_lots_to_add: u64, // base lots to deposit into the position's seat, 0 to add only quote
+    pub quote_lots_to_add: u64, // quote lots to deposit into the position's seat, 0 to add only base
+    pub requote: bool, // cancel and repost the ladder with all free funds after depositing
+    pub client_order_id: u128,
+}
+
+pub struct WithdrawFunds {
+    pub position_index: u16, // index of the position among the trader's positions on the market
+    pub base_lots_to_withdraw: u64, // base lots to send to the trader, 0 to withdraw only quote
+    pub quote_lots_to_withdraw: u64, // quote lots to send to the trader, 0 to withdraw only base
+}
+
+pub struct MigratePosition {
+    pub position_index: u16, // index of the position among the trader's positions on the market
+}
+
+pub struct GetPositionStats {
+    pub position_index: u16, // index of the position among the trader's positions on the market
+}
+
+pub struct CreateFillHistory {
+    pub position_index: u16, // index of the position among the trader's positions on the market
+}
+
+pub struct CreateVault {
+    pub position_index: u16, // index of the position among the trader's positions on the market
+    pub management_fee_bps: u64, // yearly fee on the vault's value, in basis points
+    pub performance_fee_bps: u64, // fee on gains above the high-water mark, in basis points
+    pub fee_recipient: Pubkey, // owner of the share token account fees are minted to
+}
+
+pub struct DepositToVault {
+    pub base_lots_to_deposit: u64, // base lots to move from the depositor into the vault's seat
+    pub quote_lots_to_deposit: u64, // quote lots to move from the depositor into the vault's seat
+    pub min_shares_out: u64, // fails if fewer shares would be minted
+}
+
+pub struct RedeemFromVault {
+    pub shares: u64, // shares to burn
+    pub min_base_lots_out: u64, // fails if fewer base lots would be received
+    pub min_quote_lots_out: u64, // fails if fewer quote lots would be received
+}
+
+pub struct SetKeeper {
+    pub position_index: u16, // index of the position among the trader's positions on the market
+    pub keeper: Pubkey, // account allowed to manage quotes, default to remove the keeper
+    pub permissions: u8, // bitmask of Position::KEEPER_* actions the keeper may take
+}
+
+pub struct CancelAllOrders {
+    pub position_index: u16, // index of the position among the trader's positions on the market
+}
+
+pub struct ConfigureCrank {
+    pub position_index: u16, // index of the position among the trader's positions on the market
+    pub interval_slots: u64, // slots between two public cranks, 0 to close the position to the crank
+    pub tip_lamports: u64, // lamports paid to the caller of each crank
+    pub tip_budget_lamports_to_add: u64, // lamports moved from the trader to the position to pay tips
+}
+
+pub struct Crank {
+    pub client_order_id: u128,
+}
+
+pub struct PausePosition {
+    pub position_index: u16, // index of the position among the trader's positions on the market
+}
+
+pub struct ResumePosition {
+    pub position_index: u16, // index of the position among the trader's positions on the market
     pub client_order_id: u128,
 }
 
+pub struct InitializeConfig {
+    pub params: ConfigParams,
+}
+
+pub struct UpdateConfig {
+    pub admin: Pubkey, // new admin, the current admin to keep it
+    pub params: ConfigParams,
+}
+
+pub struct SetProgramPause {
+    pub is_paused: bool,
+}
+
+pub struct SetMarketAllowed {
+    pub market: Pubkey,
+    pub allowed: bool, // false stops positions from placing orders on the market
+}
+
+pub struct CreateMarketConfig {
+    pub params: MarketConfigParams,
+}
+
+pub struct UpdateMarketConfig {
+    pub params: MarketConfigParams,
+}
+
 pub enum Instruction {
     /// Creates a position.
-    /// Creates position and token accounts. Transfers tokens from user to position and then places a limit order on phoenix.
+    /// Creates position and token accounts. Transfers base and quote tokens from user to position and then places a ladder of bids and asks on phoenix around the market price.
+    /// Adds the position index to the trader's position registry, creating the registry on first use.
     /// 
     /// 0. `[]`  Phoenix program.
     /// 1. `[]`  Phoenix log authority.
     /// 2. `[writable]`  Phoenix Market state account.
     /// 3. `[signer, writable]`  Trader account.
     /// 4. `[]`  Position's seat account.
-    /// 5. `[writable]`  Position state account. Seeds = [b"position", trader_address, market_address].
+    /// 5. `[writable]`  Position state account. Seeds = [b"position", trader_address, market_address, position_index].
+    /// 6. `[writable]`  Position registry account. Seeds = [b"registry", trader_address, market_address].
+    /// 7. `[writable]`  Base token account of position. Seeds = [b"base", position_address, base_mint_address].
+    /// 8. `[writable]`  Quote token account of position. Seeds = [b"quote", position_address, quote_mint_address].
+    /// 9. `[writable]`  Phoenix Base vault account. Seeds = [b"vault", market_address, base_mint_address] (phoenix program id).
+    /// 10. `[writable]`  Phoenix Quote vault account. Seeds = [b"vault", market_address, quote_mint_address] (phoenix program id).
+    /// 11. `[]`  Base mint.
+    /// 12. `[]`  Quote mint.
+    /// 13. `[writable]`  Base token account of trader.
+    /// 14. `[writable]`  Quote token account of trader.
+    /// 15. `[]`  Token program.
+    /// 16. `[]`  System program.
+    /// 17. `[]`  Config account. Seeds = [b"config"].
+    /// 18. `[]`  Market config account. Seeds = [b"market_config", market_address].
+    /// 19. `[]`  Oracle account (optional). Required when the price source or the empty book policy is the oracle.
+    CreatePosition(CreatePosition),
+
+    /// Cancels a position.
+    /// Cancels limit orders on phoenix, withdraws funds, transfers them to user, closes position and token accounts.
+    /// Removes the position index from the trader's position registry and closes the registry once it's empty.
+    /// Not allowed on vaults.
+    /// 
+    /// 0. `[]`  Phoenix program.
+    /// 1. `[]`  Phoenix log authority.
+    /// 2. `[writable]`  Phoenix Market state account.
+    /// 3. `[signer, writable]`  Trader account.
+    /// 4. `[writable]`  Position state account. Seeds = [b"position", trader_address, market_address, position_index].
+    /// 5. `[writable]`  Position registry account. Seeds = [b"registry", trader_address, market_address].
+    /// 6. `[writable]`  Base token account of position. Seeds = [b"base", position_address, base_mint_address].
+    /// 7. `[writable]`  Quote token account of position. Seeds = [b"quote", position_address, quote_mint_address].
+    /// 8. `[writable]`  Phoenix Base vault account. Seeds = [b"vault", market_address, base_mint_address] (phoenix program id).
+    /// 9. `[writable]`  Phoenix Quote vault account. Seeds = [b"vault", market_address, quote_mint_address] (phoenix program id).
+    /// 10. `[writable]`  Base token account of trader.
+    /// 11. `[writable]`  Quote token account of trader.
+    /// 12. `[]`  Base mint.
+    /// 13. `[]`  Quote mint.
+    /// 14. `[]`  Token program.
+    /// 15. `[]`  System program.
+    /// 16. `[writable]`  Fill history account (optional). Seeds = [b"fills", position_address]. Required once the position's fill history exists, see CreateFillHistory. Receives the fills observed since the position was last touched.
+    CancelPosition(CancelPosition),
+
+    /// Places new limit orders using free funds.
+    /// Splits free funds across the position's ladder levels.
+    /// 
+    /// 0. `[]`  Phoenix program.
+    /// 1. `[]`  Phoenix log authority.
+    /// 2. `[]`  Phoenix Market state account.
+    /// 3. `[signer]`  Trader account, or the position's keeper with the requote permission.
+    /// 4. `[]`  Position's seat account.
+    /// 5. `[writable]`  Position state account. Seeds = [b"position", trader_address, market_address, position_index].
+    /// 6. `[]`  System program.
+    /// 7. `[]`  Config account. Seeds = [b"config"].
+    /// 8. `[]`  Market config account. Seeds = [b"market_config", market_address].
+    /// 9. `[]`  Oracle account (optional). Required when the price source or the empty book policy is the oracle.
+    /// 10. `[writable]`  Fill history account (optional). Seeds = [b"fills", position_address]. Required once the position's fill history exists, see CreateFillHistory. Pass any account as the oracle when only the fill history is needed.
+    PlaceLimitOrdersWithFreeFunds(PlaceLimitOrdersWithFreeFunds),
+
+    /// Requotes a position.
+    /// Cancels all resting orders on phoenix and places a new ladder around the current market price using the freed funds.
+    /// Funds stay deposited in phoenix, position token accounts are not touched.
+    /// 
+    /// 0. `[]`  Phoenix program.
+    /// 1. `[]`  Phoenix log authority.
+    /// 2. `[writable]`  Phoenix Market state account.
+    /// 3. `[signer]`  Trader account, or the position's keeper with the requote permission.
+    /// 4. `[]`  Position's seat account.
+    /// 5. `[writable]`  Position state account. Seeds = [b"position", trader_address, market_address, position_index].
+    /// 6. `[]`  Config account. Seeds = [b"config"].
+    /// 7. `[]`  Market config account. Seeds = [b"market_config", market_address].
+    /// 8. `[]`  Oracle account (optional). Required when the price source or the empty book policy is the oracle.
+    /// 9. `[writable]`  Fill history account (optional). Seeds = [b"fills", position_address]. Required once the position's fill history exists, see CreateFillHistory. Pass any account as the oracle when only the fill history is needed.
+    Requote(Requote),
+
+    /// Sets the price of a mock oracle.
+    /// Creates the mock oracle account on first use. Publish slot is set to the current slot.
+    /// 
+    /// 0. `[signer, writable]`  Authority account.
+    /// 1. `[writable]`  Mock oracle account. Seeds = [b"mock_oracle", authority_address].
+    /// 2. `[]`  System program.
+    SetMockOracle(SetMockOracle),
+
+    /// Adds funds to a position.
+    /// Transfers base and quote tokens from user to position token accounts and deposits them into phoenix for the position's seat.
+    /// Optionally requotes the position with all free funds.
+    /// Not allowed on vaults.
+    /// 
+    /// 0. `[]`  Phoenix program.
+    /// 1. `[]`  Phoenix log authority.
+    /// 2. `[writable]`  Phoenix Market state account.
+    /// 3. `[signer]`  Trader account.
+    /// 4. `[]`  Position's seat account.
+    /// 5. `[writable]`  Position state account. Seeds = [b"position", trader_address, market_address, position_index].
     /// 6. `[writable]`  Base token account of position. Seeds = [b"base", position_address, base_mint_address].
     /// 7. `[writable]`  Quote token account of position. Seeds = [b"quote", position_address, quote_mint_address].
     /// 8. `[writable]`  Phoenix Base vault account. Seeds = [b"vault", market_address, base_mint_address] (phoenix program id).
@@ -31,40 +288,264 @@ pub enum Instruction {
     /// 12. `[writable]`  Base token account of trader.
     /// 13. `[writable]`  Quote token account of trader.
     /// 14. `[]`  Token program.
-    /// 15. `[]`  System program.
-    CreatePosition(CreatePosition),
+    /// 15. `[]`  Config account. Seeds = [b"config"].
+    /// 16. `[]`  Market config account. Seeds = [b"market_config", market_address].
+    /// 17. `[]`  Oracle account (optional). Required when requoting and the price source or the empty book policy is the oracle.
+    /// 18. `[writable]`  Fill history account (optional). Seeds = [b"fills", position_address]. Required once the position's fill history exists, see CreateFillHistory. Pass any account as the oracle when only the fill history is needed.
+    AddFunds(AddFunds),
 
-    /// Cancels a position.
-    /// Cancels limit orders on phoenix, withdraws funds, transfers them to user, closes position and token accounts.
+    /// Withdraws funds from a position.
+    /// Reduces just enough resting orders to free the requested amounts, withdraws them from phoenix and transfers them to user.
+    /// The position stays open.
+    /// Not allowed on vaults.
     /// 
     /// 0. `[]`  Phoenix program.
     /// 1. `[]`  Phoenix log authority.
     /// 2. `[writable]`  Phoenix Market state account.
-    /// 3. `[signer, writable]`  Trader account.
-    /// 4. `[writable]`  Position state account. Seeds = [b"position", trader_address, market_address].
+    /// 3. `[signer]`  Trader account.
+    /// 4. `[writable]`  Position state account. Seeds = [b"position", trader_address, market_address, position_index].
     /// 5. `[writable]`  Base token account of position. Seeds = [b"base", position_address, base_mint_address].
     /// 6. `[writable]`  Quote token account of position. Seeds = [b"quote", position_address, quote_mint_address].
     /// 7. `[writable]`  Phoenix Base vault account. Seeds = [b"vault", market_address, base_mint_address] (phoenix program id).
     /// 8. `[writable]`  Phoenix Quote vault account. Seeds = [b"vault", market_address, quote_mint_address] (phoenix program id).
-    /// 9. `[writable]`  Base token account of trader.
-    /// 10. `[writable]`  Quote token account of trader.
-    /// 11. `[]`  Base mint.
-    /// 12. `[]`  Quote mint.
+    /// 9. `[]`  Base mint.
+    /// 10. `[]`  Quote mint.
+    /// 11. `[writable]`  Base token account of trader.
+    /// 12. `[writable]`  Quote token account of trader.
     /// 13. `[]`  Token program.
-    /// 14. `[]`  System program.
-    CancelPosition,
+    /// 14. `[writable]`  Fill history account (optional). Seeds = [b"fills", position_address]. Required once the position's fill history exists, see CreateFillHistory.
+    WithdrawFunds(WithdrawFunds),
 
-    /// Places new limit orders using free funds.
+    /// Updates a position's strategy parameters.
+    /// Overwrites spread, ladder, skew, pricing and oracle parameters. Optionally requotes so the new parameters take effect right away.
+    /// A vault keeps the price source, fallback price source and oracle it was created with.
     /// 
     /// 0. `[]`  Phoenix program.
     /// 1. `[]`  Phoenix log authority.
-    /// 2. `[]`  Phoenix Market state account.
-    /// 3. `[]`  Trader account.
+    /// 2. `[writable]`  Phoenix Market state account.
+    /// 3. `[signer]`  Trader account.
     /// 4. `[]`  Position's seat account.
-    /// 5. `[writable]`  Position state account. Seeds = [b"position", trader_address, market_address].
+    /// 5. `[writable]`  Position state account. Seeds = [b"position", trader_address, market_address, position_index].
+    /// 6. `[]`  Config account. Seeds = [b"config"].
+    /// 7. `[]`  Market config account. Seeds = [b"market_config", market_address].
+    /// 8. `[]`  Oracle account (optional). Required when requoting and the price source or the empty book policy is the oracle.
+    /// 9. `[writable]`  Fill history account (optional). Seeds = [b"fills", position_address]. Required once the position's fill history exists, see CreateFillHistory. Pass any account as the oracle when only the fill history is needed.
+    UpdatePosition(UpdatePosition),
+
+    /// Migrates a position account to the latest layout.
+    /// Reallocs accounts created by the first release, fills fields that later versions can't default and bumps the layout version. The trader pays the extra rent.
+    /// First release positions keep their address and take the reserved position index `u16::MAX`, which every later instruction is called with.
+    /// 
+    /// 0. `[signer, writable]`  Trader account.
+    /// 1. `[]`  Phoenix Market state account.
+    /// 2. `[writable]`  Position state account. Seeds = [b"position", trader_address, market_address, position_index], or [b"position", trader_address, market_address] for first release positions.
+    /// 3. `[]`  System program.
+    MigratePosition(MigratePosition),
+
+    /// Returns a position's PnL and cost basis statistics.
+    /// Read-only. Applies fills since the position was last touched to a copy of the position and returns the packed `PositionStats` via return data.
+    /// 
+    /// 0. `[]`  Phoenix Market state account.
+    /// 1. `[]`  Trader account.
+    /// 2. `[]`  Position state account. Seeds = [b"position", trader_address, market_address, position_index].
+    GetPositionStats(GetPositionStats),
+
+    /// Creates a position's fill history.
+    /// Ring buffer of the net fills observed on the position, appended by every instruction that syncs fills. Survives the position being cancelled.
+    /// Once created, those instructions require the fill history so that no fill goes unrecorded.
+    /// 
+    /// 0. `[signer, writable]`  Trader account.
+    /// 1. `[]`  Phoenix Market state account.
+    /// 2. `[writable]`  Position state account. Seeds = [b"position", trader_address, market_address, position_index].
+    /// 3. `[writable]`  Fill history account. Seeds = [b"fills", position_address].
+    /// 4. `[]`  System program.
+    /// 5. `[]`  Config account. Seeds = [b"config"].
+    CreateFillHistory(CreateFillHistory),
+
+    /// Turns a position into a vault.
+    /// Creates the vault's share mint, with the position as mint authority. The position should hold no funds, they can be added back through DepositToVault.
+    /// Once a vault, the position's funds only move through deposits and redemptions, the trader keeps managing quotes and parameters.
+    /// Sets the vault's management and performance fees, paid to the fee recipient in shares on every deposit and redemption.
+    /// The position should be priced by a pyth oracle. Its price source and oracle are fixed from then on, shares are valued at that price.
+    /// 
+    /// 0. `[signer, writable]`  Trader account.
+    /// 1. `[]`  Phoenix Market state account.
+    /// 2. `[writable]`  Position state account. Seeds = [b"position", trader_address, market_address, position_index].
+    /// 3. `[]`  Base token account of position. Seeds = [b"base", position_address, base_mint_address].
+    /// 4. `[]`  Quote token account of position. Seeds = [b"quote", position_address, quote_mint_address].
+    /// 5. `[writable]`  Share mint. Seeds = [b"shares", position_address].
     /// 6. `[]`  Token program.
     /// 7. `[]`  System program.
-    PlaceLimitOrdersWithFreeFunds(PlaceLimitOrdersWithFreeFunds),
+    /// 8. `[]`  Config account. Seeds = [b"config"].
+    CreateVault(CreateVault),
+
+    /// Deposits into a vault.
+    /// Moves base and quote from the depositor into the vault's seat and mints shares priced at the vault's net asset value.
+    /// The value counts the seat's free and locked funds and the position's token accounts, base valued at the vault's pyth price.
+    /// Fees accrued since the last deposit or redemption are minted to the fee recipient before pricing the shares.
+    /// 
+    /// 0. `[]`  Phoenix program.
+    /// 1. `[]`  Phoenix log authority.
+    /// 2. `[writable]`  Phoenix Market state account.
+    /// 3. `[signer]`  Depositor account.
+    /// 4. `[]`  Position's seat account.
+    /// 5. `[writable]`  Position state account.
+    /// 6. `[writable]`  Base token account of position. Seeds = [b"base", position_address, base_mint_address].
+    /// 7. `[writable]`  Quote token account of position. Seeds = [b"quote", position_address, quote_mint_address].
+    /// 8. `[writable]`  Phoenix Base vault account. Seeds = [b"vault", market_address, base_mint_address] (phoenix program id).
+    /// 9. `[writable]`  Phoenix Quote vault account. Seeds = [b"vault", market_address, quote_mint_address] (phoenix program id).
+    /// 10. `[]`  Base mint.
+    /// 11. `[]`  Quote mint.
+    /// 12. `[writable]`  Base token account of depositor.
+    /// 13. `[writable]`  Quote token account of depositor.
+    /// 14. `[writable]`  Share mint. Seeds = [b"shares", position_address].
+    /// 15. `[writable]`  Share token account of depositor.
+    /// 16. `[writable]`  Share token account of the vault's fee recipient.
+    /// 17. `[]`  Token program.
+    /// 18. `[]`  Config account. Seeds = [b"config"].
+    /// 19. `[]`  Oracle account. The vault's pyth price account, shares are never priced from the order book.
+    /// 20. `[writable]`  Fill history account (optional). Seeds = [b"fills", position_address]. Required once the position's fill history exists, see CreateFillHistory. Pass any account as the oracle when only the fill history is needed.
+    DepositToVault(DepositToVault),
+
+    /// Redeems vault shares.
+    /// Burns shares and sends their pro-rata part of the vault's base and quote to the owner. Resting orders are reduced when free funds aren't enough.
+    /// Fees accrued since the last deposit or redemption are minted to the fee recipient first.
+    /// 
+    /// 0. `[]`  Phoenix program.
+    /// 1. `[]`  Phoenix log authority.
+    /// 2. `[writable]`  Phoenix Market state account.
+    /// 3. `[signer]`  Share owner account.
+    /// 4. `[writable]`  Position state account.
+    /// 5. `[writable]`  Base token account of position. Seeds = [b"base", position_address, base_mint_address].
+    /// 6. `[writable]`  Quote token account of position. Seeds = [b"quote", position_address, quote_mint_address].
+    /// 7. `[writable]`  Phoenix Base vault account. Seeds = [b"vault", market_address, base_mint_address] (phoenix program id).
+    /// 8. `[writable]`  Phoenix Quote vault account. Seeds = [b"vault", market_address, quote_mint_address] (phoenix program id).
+    /// 9. `[]`  Base mint.
+    /// 10. `[]`  Quote mint.
+    /// 11. `[writable]`  Base token account of owner.
+    /// 12. `[writable]`  Quote token account of owner.
+    /// 13. `[writable]`  Share mint. Seeds = [b"shares", position_address].
+    /// 14. `[writable]`  Share token account of owner.
+    /// 15. `[writable]`  Share token account of the vault's fee recipient.
+    /// 16. `[]`  Token program.
+    /// 17. `[]`  Oracle account (optional). The vault's pyth price account, required when the vault charges fees.
+    /// 18. `[writable]`  Fill history account (optional). Seeds = [b"fills", position_address]. Required once the position's fill history exists, see CreateFillHistory. Pass any account as the oracle when only the fill history is needed.
+    RedeemFromVault(RedeemFromVault),
+
+    /// Sets the position's keeper.
+    /// The keeper can take the actions in its permissions on behalf of the trader: requoting and cancelling resting orders.
+    /// Funds never move on a keeper's signature. Setting the default pubkey removes the keeper.
+    /// 
+    /// 0. `[signer]`  Trader account.
+    /// 1. `[]`  Phoenix Market state account.
+    /// 2. `[writable]`  Position state account. Seeds = [b"position", trader_address, market_address, position_index].
+    /// 3. `[]`  Config account. Seeds = [b"config"].
+    SetKeeper(SetKeeper),
+
+    /// Cancels all resting orders of a position.
+    /// Released funds stay in the position's seat as free funds.
+    /// 
+    /// 0. `[]`  Phoenix program.
+    /// 1. `[]`  Phoenix log authority.
+    /// 2. `[writable]`  Phoenix Market state account.
+    /// 3. `[signer]`  Trader account, or the position's keeper with the cancel orders permission.
+    /// 4. `[]`  Position state account. Seeds = [b"position", trader_address, market_address, position_index].
+    CancelAllOrders(CancelAllOrders),
+
+    /// Opens a position to the public crank, or closes it with a zero interval.
+    /// Tops up the tip budget with lamports from the trader. The budget stays on the position account and what's left goes back to the trader on cancel.
+    /// 
+    /// 0. `[signer, writable]`  Trader account.
+    /// 1. `[]`  Phoenix Market state account.
+    /// 2. `[writable]`  Position state account. Seeds = [b"position", trader_address, market_address, position_index].
+    /// 3. `[]`  System program.
+    /// 4. `[]`  Config account. Seeds = [b"config"].
+    ConfigureCrank(ConfigureCrank),
+
+    /// Requotes a position open to the public crank. Anyone can call it once the position's crank interval has passed since the last crank.
+    /// The caller is paid the position's crank tip while the tip budget lasts.
+    /// 
+    /// 0. `[]`  Phoenix program.
+    /// 1. `[]`  Phoenix log authority.
+    /// 2. `[writable]`  Phoenix Market state account.
+    /// 3. `[signer, writable]`  Cranker account.
+    /// 4. `[]`  Position's seat account.
+    /// 5. `[writable]`  Position state account.
+    /// 6. `[]`  Config account. Seeds = [b"config"].
+    /// 7. `[]`  Market config account. Seeds = [b"market_config", market_address].
+    /// 8. `[]`  Oracle account (optional). Required when the price source or the empty book policy is the oracle.
+    /// 9. `[writable]`  Fill history account (optional). Seeds = [b"fills", position_address]. Required once the position's fill history exists, see CreateFillHistory. Pass any account as the oracle when only the fill history is needed.
+    Crank(Crank),
+
+    /// Pauses a position.
+    /// Cancels all resting orders on phoenix and stops quoting: requotes, cranks and placing orders with free funds do nothing until the position is resumed.
+    /// Funds stay deposited in phoenix.
+    /// 
+    /// 0. `[]`  Phoenix program.
+    /// 1. `[]`  Phoenix log authority.
+    /// 2. `[writable]`  Phoenix Market state account.
+    /// 3. `[signer]`  Trader account.
+    /// 4. `[writable]`  Position state account. Seeds = [b"position", trader_address, market_address, position_index].
+    PausePosition(PausePosition),
+
+    /// Resumes a paused position and places a new ladder from free funds.
+    /// 
+    /// 0. `[]`  Phoenix program.
+    /// 1. `[]`  Phoenix log authority.
+    /// 2. `[writable]`  Phoenix Market state account.
+    /// 3. `[signer]`  Trader account.
+    /// 4. `[]`  Position's seat account.
+    /// 5. `[writable]`  Position state account. Seeds = [b"position", trader_address, market_address, position_index].
+    /// 6. `[]`  Config account. Seeds = [b"config"].
+    /// 7. `[]`  Market config account. Seeds = [b"market_config", market_address].
+    /// 8. `[]`  Oracle account (optional). Required when the price source or the empty book policy is the oracle.
+    /// 9. `[writable]`  Fill history account (optional). Seeds = [b"fills", position_address]. Required once the position's fill history exists, see CreateFillHistory. Pass any account as the oracle when only the fill history is needed.
+    ResumePosition(ResumePosition),
+
+    /// Creates the program config with the signer as admin.
+    /// The config can only be created once, it should be created right after the program is deployed.
+    /// 
+    /// 0. `[signer, writable]`  Admin account.
+    /// 1. `[writable]`  Config account. Seeds = [b"config"].
+    /// 2. `[]`  System program.
+    InitializeConfig(InitializeConfig),
+
+    /// Updates the config admin and the bounds of the strategy parameters.
+    /// Bounds apply to positions created or updated afterwards.
+    /// 
+    /// 0. `[signer]`  Admin account.
+    /// 1. `[writable]`  Config account. Seeds = [b"config"].
+    UpdateConfig(UpdateConfig),
+
+    /// Pauses or resumes the program.
+    /// While paused, every instruction changing a position fails. Cancels, withdrawals, redemptions, pauses and migrations keep working.
+    /// 
+    /// 0. `[signer]`  Admin account.
+    /// 1. `[writable]`  Config account. Seeds = [b"config"].
+    SetProgramPause(SetProgramPause),
+
+    /// Allows or disallows a market with a market config. Positions only place orders on allowed markets.
+    /// 
+    /// 0. `[signer]`  Admin account.
+    /// 1. `[]`  Config account. Seeds = [b"config"].
+    /// 2. `[writable]`  Market config account. Seeds = [b"market_config", market_address].
+    SetMarketAllowed(SetMarketAllowed),
+
+    /// Creates the config of a phoenix market, allowing positions to place orders on it within its limits.
+    /// 
+    /// 0. `[signer, writable]`  Admin account.
+    /// 1. `[]`  Config account. Seeds = [b"config"].
+    /// 2. `[]`  Phoenix Market state account.
+    /// 3. `[writable]`  Market config account. Seeds = [b"market_config", market_address].
+    /// 4. `[]`  System program.
+    CreateMarketConfig(CreateMarketConfig),
+
+    /// Updates the limits of a market config.
+    /// 
+    /// 0. `[signer]`  Admin account.
+    /// 1. `[]`  Config account. Seeds = [b"config"].
+    /// 2. `[writable]`  Market config account. Seeds = [b"market_config", market_address].
+    UpdateMarketConfig(UpdateMarketConfig),
 }
 
 impl Instruction {
@@ -73,25 +554,331 @@ impl Instruction {
 
         Ok(match tag {
             0 => {
-                let (side, rest) = Self::unpack_u8(rest)?;
-                let (spread_margin, rest) = Self::unpack_u64(rest)?;
-                let (num_base_lots, rest) = Self::unpack_u64(rest)?;
+                let (position_index, rest) = Self::unpack_u16(rest)?;
+                let (params, rest) = Self::unpack_position_params(rest)?;
+                let (bid_num_base_lots, rest) = Self::unpack_u64(rest)?;
+                let (ask_num_base_lots, rest) = Self::unpack_u64(rest)?;
                 let (client_order_id, _rest) = Self::unpack_u128(rest)?;
 
                 Instruction::CreatePosition(CreatePosition {
-                    side,
-                    spread_margin,
-                    num_base_lots,
+                    position_index,
+                    params,
+                    bid_num_base_lots,
+                    ask_num_base_lots,
+                    client_order_id,
+                })
+            }
+            1 => {
+                let (position_index, _rest) = Self::unpack_u16(rest)?;
+
+                Instruction::CancelPosition(CancelPosition {
+                    position_index,
+                })
+            }
+            2 => {
+                let (position_index, rest) = Self::unpack_u16(rest)?;
+                let (client_order_id, _rest) = Self::unpack_u128(rest)?;
+
+                Instruction::PlaceLimitOrdersWithFreeFunds(PlaceLimitOrdersWithFreeFunds {
+                    position_index,
+                    client_order_id,
+                })
+            }
+            3 => {
+                let (position_index, rest) = Self::unpack_u16(rest)?;
+                let (client_order_id, _rest) = Self::unpack_u128(rest)?;
+
+                Instruction::Requote(Requote {
+                    position_index,
+                    client_order_id,
+                })
+            }
+            4 => {
+                let (price, rest) = Self::unpack_i64(rest)?;
+                let (conf, rest) = Self::unpack_u64(rest)?;
+                let (expo, _rest) = Self::unpack_i32(rest)?;
+
+                Instruction::SetMockOracle(SetMockOracle {
+                    price,
+                    conf,
+                    expo,
+                })
+            }
+            5 => {
+                let (position_index, rest) = Self::unpack_u16(rest)?;
+                let (base_lots_to_add, rest) = Self::unpack_u64(rest)?;
+                let (quote_lots_to_add, rest) = Self::unpack_u64(rest)?;
+                let (requote, rest) = Self::unpack_u8(rest)?;
+                let (client_order_id, _rest) = Self::unpack_u128(rest)?;
+
+                Instruction::AddFunds(AddFunds {
+                    position_index,
+                    base_lots_to_add,
+                    quote_lots_to_add,
+                    requote: requote != 0,
+                    client_order_id,
+                })
+            }
+            6 => {
+                let (position_index, rest) = Self::unpack_u16(rest)?;
+                let (base_lots_to_withdraw, rest) = Self::unpack_u64(rest)?;
+                let (quote_lots_to_withdraw, _rest) = Self::unpack_u64(rest)?;
+
+                Instruction::WithdrawFunds(WithdrawFunds {
+                    position_index,
+                    base_lots_to_withdraw,
+                    quote_lots_to_withdraw,
+                })
+            }
+            7 => {
+                let (position_index, rest) = Self::unpack_u16(rest)?;
+                let (params, rest) = Self::unpack_position_params(rest)?;
+                let (requote, rest) = Self::unpack_u8(rest)?;
+                let (client_order_id, _rest) = Self::unpack_u128(rest)?;
+
+                Instruction::UpdatePosition(UpdatePosition {
+                    position_index,
+                    params,
+                    requote: requote != 0,
                     client_order_id,
                 })
             }
-            1 => Instruction::CancelPosition,
+            8 => {
+                let (position_index, _rest) = Self::unpack_u16(rest)?;
+
+                Instruction::MigratePosition(MigratePosition {
+                    position_index,
+                })
+            }
+            9 => {
+                let (position_index, _rest) = Self::unpack_u16(rest)?;
+
+                Instruction::GetPositionStats(GetPositionStats {
+                    position_index,
+                })
+            }
+            10 => {
+                let (position_index, _rest) = Self::unpack_u16(rest)?;
+
+                Instruction::CreateFillHistory(CreateFillHistory {
+                    position_index,
+                })
+            }
+            11 => {
+                let (position_index, rest) = Self::unpack_u16(rest)?;
+                let (management_fee_bps, rest) = Self::unpack_u64(rest)?;
+                let (performance_fee_bps, rest) = Self::unpack_u64(rest)?;
+                let (fee_recipient, _rest) = Self::unpack_pubkey(rest)?;
+
+                Instruction::CreateVault(CreateVault {
+                    position_index,
+                    management_fee_bps,
+                    performance_fee_bps,
+                    fee_recipient,
+                })
+            }
+            12 => {
+                let (base_lots_to_deposit, rest) = Self::unpack_u64(rest)?;
+                let (quote_lots_to_deposit, rest) = Self::unpack_u64(rest)?;
+                let (min_shares_out, _rest) = Self::unpack_u64(rest)?;
+
+                Instruction::DepositToVault(DepositToVault {
+                    base_lots_to_deposit,
+                    quote_lots_to_deposit,
+                    min_shares_out,
+                })
+            }
+            13 => {
+                let (shares, rest) = Self::unpack_u64(rest)?;
+                let (min_base_lots_out, rest) = Self::unpack_u64(rest)?;
+                let (min_quote_lots_out, _rest) = Self::unpack_u64(rest)?;
+
+                Instruction::RedeemFromVault(RedeemFromVault {
+                    shares,
+                    min_base_lots_out,
+                    min_quote_lots_out,
+                })
+            }
+            14 => {
+                let (position_index, rest) = Self::unpack_u16(rest)?;
+                let (keeper, rest) = Self::unpack_pubkey(rest)?;
+                let (permissions, _rest) = Self::unpack_u8(rest)?;
+
+                Instruction::SetKeeper(SetKeeper {
+                    position_index,
+                    keeper,
+                    permissions,
+                })
+            }
+            15 => {
+                let (position_index, _rest) = Self::unpack_u16(rest)?;
+
+                Instruction::CancelAllOrders(CancelAllOrders {
+                    position_index,
+                })
+            }
+            16 => {
+                let (position_index, rest) = Self::unpack_u16(rest)?;
+                let (interval_slots, rest) = Self::unpack_u64(rest)?;
+                let (tip_lamports, rest) = Self::unpack_u64(rest)?;
+                let (tip_budget_lamports_to_add, _rest) = Self::unpack_u64(rest)?;
+
+                Instruction::ConfigureCrank(ConfigureCrank {
+                    position_index,
+                    interval_slots,
+                    tip_lamports,
+                    tip_budget_lamports_to_add,
+                })
+            }
+            17 => {
+                let (client_order_id, _rest) = Self::unpack_u128(rest)?;
+
+                Instruction::Crank(Crank {
+                    client_order_id,
+                })
+            }
+            18 => {
+                let (position_index, _rest) = Self::unpack_u16(rest)?;
+
+                Instruction::PausePosition(PausePosition {
+                    position_index,
+                })
+            }
+            19 => {
+                let (position_index, rest) = Self::unpack_u16(rest)?;
+                let (client_order_id, _rest) = Self::unpack_u128(rest)?;
+
+                Instruction::ResumePosition(ResumePosition {
+                    position_index,
+                    client_order_id,
+                })
+            }
+            20 => {
+                let (params, _rest) = Self::unpack_config_params(rest)?;
+
+                Instruction::InitializeConfig(InitializeConfig {
+                    params,
+                })
+            }
+            21 => {
+                let (admin, rest) = Self::unpack_pubkey(rest)?;
+                let (params, _rest) = Self::unpack_config_params(rest)?;
+
+                Instruction::UpdateConfig(UpdateConfig {
+                    admin,
+                    params,
+                })
+            }
+            22 => {
+                let (is_paused, _rest) = Self::unpack_u8(rest)?;
+
+                Instruction::SetProgramPause(SetProgramPause {
+                    is_paused: is_paused != 0,
+                })
+            }
+            23 => {
+                let (market, rest) = Self::unpack_pubkey(rest)?;
+                let (allowed, _rest) = Self::unpack_u8(rest)?;
+
+                Instruction::SetMarketAllowed(SetMarketAllowed {
+                    market,
+                    allowed: allowed != 0,
+                })
+            }
+            24 => {
+                let (params, _rest) = Self::unpack_market_config_params(rest)?;
+
+                Instruction::CreateMarketConfig(CreateMarketConfig {
+                    params,
+                })
+            }
+            25 => {
+                let (params, _rest) = Self::unpack_market_config_params(rest)?;
+
+                Instruction::UpdateMarketConfig(UpdateMarketConfig {
+                    params,
+                })
+            }
             _ => return Err(ProgramError::InvalidInstructionData),
         })
     }
 
-    fn unpack_u8(input: &[u8]) -> Result<(u8, &[u8]), ProgramError> {
-        if input.len() >= 1 {
+    fn unpack_position_params(input: &[u8]) -> Result<(PositionParams, &[u8]), ProgramError> {
+        let (spread_mode, rest) = Self::unpack_u8(input)?;
+        let (spread_margin, rest) = Self::unpack_u64(rest)?;
+        let (num_levels, rest) = Self::unpack_u8(rest)?;
+        let (level_spacing_mode, rest) = Self::unpack_u8(rest)?;
+        let (level_spacing, rest) = Self::unpack_u64(rest)?;
+        let (size_profile, rest) = Self::unpack_u8(rest)?;
+        let (size_profile_param, rest) = Self::unpack_u64(rest)?;
+        let (target_base_ratio_bps, rest) = Self::unpack_u64(rest)?;
+        let (skew_bps, rest) = Self::unpack_u64(rest)?;
+        let (price_source, rest) = Self::unpack_u8(rest)?;
+        let (oracle, rest) = Self::unpack_pubkey(rest)?;
+        let (max_confidence_bps, rest) = Self::unpack_u64(rest)?;
+        let (max_staleness_slots, rest) = Self::unpack_u64(rest)?;
+        let (mid_model, rest) = Self::unpack_u8(rest)?;
+        let (vwap_depth_base_lots, rest) = Self::unpack_u64(rest)?;
+        let (empty_book_policy, rest) = Self::unpack_u8(rest)?;
+        let (fallback_price_source, rest) = Self::unpack_u8(rest)?;
+        let (max_base_inventory_lots, rest) = Self::unpack_u64(rest)?;
+        let (max_quote_inventory_lots, rest) = Self::unpack_u64(rest)?;
+        let (max_notional_quote_lots, rest) = Self::unpack_u64(rest)?;
+
+        Ok((PositionParams {
+            spread_mode,
+            spread_margin,
+            num_levels,
+            level_spacing_mode,
+            level_spacing,
+            size_profile,
+            size_profile_param,
+            target_base_ratio_bps,
+            skew_bps,
+            price_source,
+            oracle,
+            max_confidence_bps,
+            max_staleness_slots,
+            mid_model,
+            vwap_depth_base_lots,
+            empty_book_policy,
+            fallback_price_source,
+            max_base_inventory_lots,
+            max_quote_inventory_lots,
+            max_notional_quote_lots,
+        }, rest))
+    }
+
+    fn unpack_config_params(input: &[u8]) -> Result<(ConfigParams, &[u8]), ProgramError> {
+        let (min_spread_margin_bps, rest) = Self::unpack_u64(input)?;
+        let (max_spread_margin_bps, rest) = Self::unpack_u64(rest)?;
+        let (max_num_levels, rest) = Self::unpack_u8(rest)?;
+        let (max_skew_bps, rest) = Self::unpack_u64(rest)?;
+
+        Ok((ConfigParams {
+            min_spread_margin_bps,
+            max_spread_margin_bps,
+            max_num_levels,
+            max_skew_bps,
+        }, rest))
+    }
+
+    fn unpack_market_config_params(input: &[u8]) -> Result<(MarketConfigParams, &[u8]), ProgramError> {
+        let (min_spread_in_ticks, rest) = Self::unpack_u64(input)?;
+        let (max_order_base_lots, rest) = Self::unpack_u64(rest)?;
+        let (min_tick_size_in_quote_lots_per_base_unit, rest) = Self::unpack_u64(rest)?;
+        let (max_tick_size_in_quote_lots_per_base_unit, rest) = Self::unpack_u64(rest)?;
+
+        Ok((MarketConfigParams {
+            min_spread_in_ticks,
+            max_order_base_lots,
+            min_tick_size_in_quote_lots_per_base_unit,
+            max_tick_size_in_quote_lots_per_base_unit,
+        }, rest))
+    }
+
+    pub(crate) fn unpack_u8(input: &[u8]) -> Result<(u8, &[u8]), ProgramError> {
+        if !input.is_empty() {
             let (amount, rest) = input.split_at(1);
             let amount = amount
                 .get(..1)
@@ -104,7 +891,35 @@ impl Instruction {
         }
     }
 
-    fn unpack_u64(input: &[u8]) -> Result<(u64, &[u8]), ProgramError> {
+    pub(crate) fn unpack_u16(input: &[u8]) -> Result<(u16, &[u8]), ProgramError> {
+        if input.len() >= 2 {
+            let (amount, rest) = input.split_at(2);
+            let amount = amount
+                .get(..2)
+                .and_then(|slice| slice.try_into().ok())
+                .map(u16::from_le_bytes)
+                .ok_or(ProgramError::InvalidInstructionData)?;
+            Ok((amount, rest))
+        } else {
+            Err(ProgramError::InvalidInstructionData)
+        }
+    }
+
+    fn unpack_i32(input: &[u8]) -> Result<(i32, &[u8]), ProgramError> {
+        if input.len() >= 4 {
+            let (amount, rest) = input.split_at(4);
+            let amount = amount
+                .get(..4)
+                .and_then(|slice| slice.try_into().ok())
+                .map(i32::from_le_bytes)
+                .ok_or(ProgramError::InvalidInstructionData)?;
+            Ok((amount, rest))
+        } else {
+            Err(ProgramError::InvalidInstructionData)
+        }
+    }
+
+    pub(crate) fn unpack_u64(input: &[u8]) -> Result<(u64, &[u8]), ProgramError> {
         if input.len() >= 8 {
             let (amount, rest) = input.split_at(8);
             let amount = amount
@@ -118,7 +933,21 @@ impl Instruction {
         }
     }
 
-    fn unpack_u128(input: &[u8]) -> Result<(u128, &[u8]), ProgramError> {
+    fn unpack_i64(input: &[u8]) -> Result<(i64, &[u8]), ProgramError> {
+        if input.len() >= 8 {
+            let (amount, rest) = input.split_at(8);
+            let amount = amount
+                .get(..8)
+                .and_then(|slice| slice.try_into().ok())
+                .map(i64::from_le_bytes)
+                .ok_or(ProgramError::InvalidInstructionData)?;
+            Ok((amount, rest))
+        } else {
+            Err(ProgramError::InvalidInstructionData)
+        }
+    }
+
+    pub(crate) fn unpack_u128(input: &[u8]) -> Result<(u128, &[u8]), ProgramError> {
         if input.len() >= 16 {
             let (amount, rest) = input.split_at(16);
             let amount = amount
@@ -131,4 +960,18 @@ impl Instruction {
             Err(ProgramError::InvalidInstructionData)
         }
     }
+
+    pub(crate) fn unpack_pubkey(input: &[u8]) -> Result<(Pubkey, &[u8]), ProgramError> {
+        if input.len() >= 32 {
+            let (key, rest) = input.split_at(32);
+            let key = key
+                .get(..32)
+                .and_then(|slice| slice.try_into().ok())
+                .map(Pubkey::new_from_array)
+                .ok_or(ProgramError::InvalidInstructionData)?;
+            Ok((key, rest))
+        } else {
+            Err(ProgramError::InvalidInstructionData)
+        }
+    }
 }
\ No newline at end of file
@@ -6,11 +6,23 @@ pub mod state;
 pub mod instruction;
 pub mod processor;
 pub mod error;
+pub mod pricing;
+pub mod oracle;
+pub mod accounting;
+pub mod events;
+pub mod vault;
+pub mod fees;
 
 declare_id!("7vybLSwaCimfTL7AarykdoQWpvwW59ZABTf88fcTuYFx");
 
+// the entrypoint macro checks cfgs of the solana toolchain that the host toolchain doesn't know
 #[cfg(not(feature = "no-entrypoint"))]
-solana_program::entrypoint!(process_instruction);
+#[allow(unexpected_cfgs)]
+mod entrypoint {
+    use super::process_instruction;
+    solana_program::entrypoint!(process_instruction);
+}
+
 fn process_instruction(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
@@ -19,20 +31,82 @@ fn process_instruction(
     let ixn = instruction::Instruction::unpack(instruction_data)?;
     match ixn {
         instruction::Instruction::CreatePosition(data) => {
-            processor::process_create_position(
-                program_id,
-                accounts,
-                data.spread_margin,
-                data.side,
-                data.num_base_lots,
-                data.client_order_id,
-            )
-        }
-        instruction::Instruction::CancelPosition => {
-            processor::process_cancel_position(program_id, accounts)
+            processor::process_create_position(program_id, accounts, data)
+        }
+        instruction::Instruction::CancelPosition(data) => {
+            processor::process_cancel_position(program_id, accounts, data.position_index)
         }
         instruction::Instruction::PlaceLimitOrdersWithFreeFunds(data) => {
-            processor::process_place_limit_orders_with_free_funds(program_id, accounts, data.client_order_id)
+            processor::process_place_limit_orders_with_free_funds(program_id, accounts, data.position_index, data.client_order_id)
+        }
+        instruction::Instruction::Requote(data) => {
+            processor::process_requote(program_id, accounts, data.position_index, data.client_order_id)
+        }
+        instruction::Instruction::SetMockOracle(data) => {
+            processor::process_set_mock_oracle(program_id, accounts, data.price, data.conf, data.expo)
+        }
+        instruction::Instruction::AddFunds(data) => {
+            processor::process_add_funds(program_id, accounts, data)
+        }
+        instruction::Instruction::WithdrawFunds(data) => {
+            processor::process_withdraw_funds(program_id, accounts, data)
+        }
+        instruction::Instruction::UpdatePosition(data) => {
+            processor::process_update_position(program_id, accounts, data)
+        }
+        instruction::Instruction::MigratePosition(data) => {
+            processor::process_migrate_position(program_id, accounts, data.position_index)
+        }
+        instruction::Instruction::GetPositionStats(data) => {
+            processor::process_get_position_stats(program_id, accounts, data.position_index)
+        }
+        instruction::Instruction::CreateFillHistory(data) => {
+            processor::process_create_fill_history(program_id, accounts, data.position_index)
+        }
+        instruction::Instruction::CreateVault(data) => {
+            processor::process_create_vault(program_id, accounts, data)
+        }
+        instruction::Instruction::DepositToVault(data) => {
+            processor::process_deposit_to_vault(program_id, accounts, data)
+        }
+        instruction::Instruction::RedeemFromVault(data) => {
+            processor::process_redeem_from_vault(program_id, accounts, data)
+        }
+        instruction::Instruction::SetKeeper(data) => {
+            processor::process_set_keeper(program_id, accounts, data)
+        }
+        instruction::Instruction::CancelAllOrders(data) => {
+            processor::process_cancel_all_orders(program_id, accounts, data.position_index)
+        }
+        instruction::Instruction::ConfigureCrank(data) => {
+            processor::process_configure_crank(program_id, accounts, data)
+        }
+        instruction::Instruction::Crank(data) => {
+            processor::process_crank(program_id, accounts, data.client_order_id)
+        }
+        instruction::Instruction::PausePosition(data) => {
+            processor::process_pause_position(program_id, accounts, data.position_index)
+        }
+        instruction::Instruction::ResumePosition(data) => {
+            processor::process_resume_position(program_id, accounts, data.position_index, data.client_order_id)
+        }
+        instruction::Instruction::InitializeConfig(data) => {
+            processor::process_initialize_config(program_id, accounts, data)
+        }
+        instruction::Instruction::UpdateConfig(data) => {
+            processor::process_update_config(program_id, accounts, data)
+        }
+        instruction::Instruction::SetProgramPause(data) => {
+            processor::process_set_program_pause(program_id, accounts, data.is_paused)
+        }
+        instruction::Instruction::SetMarketAllowed(data) => {
+            processor::process_set_market_allowed(program_id, accounts, data)
+        }
+        instruction::Instruction::CreateMarketConfig(data) => {
+            processor::process_create_market_config(program_id, accounts, data)
+        }
+        instruction::Instruction::UpdateMarketConfig(data) => {
+            processor::process_update_market_config(program_id, accounts, data)
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,281 @@
+use arrayref::array_ref;
+use solana_program::{account_info::AccountInfo, msg, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey};
+
+use crate::{error::CodeError, pricing::{MarketParams, BPS_DENOMINATOR}, state::MockOracle};
+
+
+const PYTH_PROGRAM_ID: &str = "FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH";
+const PYTH_MAGIC: u32 = 0xa1b2c3d4;
+const PYTH_VERSION: u32 = 2;
+const PYTH_PRICE_ACCOUNT_TYPE: u32 = 3;
+const PYTH_STATUS_TRADING: u32 = 1;
+const PYTH_PRICE_ACCOUNT_MIN_LEN: usize = 240;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PriceSource {
+    OrderBook = 1, // midpoint of the phoenix order book
+    Pyth = 2, // pyth price account
+    MockOracle = 3, // program-owned mock oracle account
+}
+
+impl TryFrom<u8> for PriceSource {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(PriceSource::OrderBook),
+            2 => Ok(PriceSource::Pyth),
+            3 => Ok(PriceSource::MockOracle),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+/// Price as published by an oracle: `price * 10^expo` quote units per base unit, with confidence `conf` in the same units.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OraclePrice {
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub publish_slot: u64,
+}
+
+impl OraclePrice {
+    /// Reads the price from a pyth price account or a mock oracle account, depending on `price_source`.
+    pub fn load(oracle: &AccountInfo, price_source: PriceSource, program_id: &Pubkey) -> Result<OraclePrice, ProgramError> {
+        match price_source {
+            PriceSource::OrderBook => Err(CodeError::InvalidOracle.into()),
+            PriceSource::Pyth => {
+                if oracle.owner.to_string() != PYTH_PROGRAM_ID {
+                    msg!("Pyth price account should be owned by the pyth program");
+                    return Err(CodeError::InvalidOracle.into());
+                }
+                Self::unpack_pyth(&oracle.data.borrow())
+            }
+            PriceSource::MockOracle => {
+                if oracle.owner != program_id {
+                    msg!("Mock oracle should be owned by the program");
+                    return Err(CodeError::InvalidOracle.into());
+                }
+                let mock_oracle = MockOracle::unpack(&oracle.data.borrow())?;
+                Ok(OraclePrice {
+                    price: mock_oracle.price,
+                    conf: mock_oracle.conf,
+                    expo: mock_oracle.expo,
+                    publish_slot: mock_oracle.publish_slot,
+                })
+            }
+        }
+    }
+
+    fn unpack_pyth(data: &[u8]) -> Result<OraclePrice, ProgramError> {
+        if data.len() < PYTH_PRICE_ACCOUNT_MIN_LEN {
+            return Err(CodeError::InvalidOracle.into());
+        }
+        let magic = u32::from_le_bytes(*array_ref![data, 0, 4]);
+        let version = u32::from_le_bytes(*array_ref![data, 4, 4]);
+        let account_type = u32::from_le_bytes(*array_ref![data, 8, 4]);
+        if magic != PYTH_MAGIC || version != PYTH_VERSION || account_type != PYTH_PRICE_ACCOUNT_TYPE {
+            return Err(CodeError::InvalidOracle.into());
+        }
+
+        let status = u32::from_le_bytes(*array_ref![data, 224, 4]);
+        if status != PYTH_STATUS_TRADING {
+            msg!("Oracle price is not trading");
+            return Err(CodeError::InvalidOracle.into());
+        }
+
+        Ok(OraclePrice {
+            price: i64::from_le_bytes(*array_ref![data, 208, 8]),
+            conf: u64::from_le_bytes(*array_ref![data, 216, 8]),
+            expo: i32::from_le_bytes(*array_ref![data, 20, 4]),
+            publish_slot: u64::from_le_bytes(*array_ref![data, 232, 8]),
+        })
+    }
+
+    /// Checks the price is positive, no older than `max_staleness_slots` and its confidence interval is within `max_confidence_bps` of the price.
+    pub fn validate(&self, current_slot: u64, max_staleness_slots: u64, max_confidence_bps: u64) -> Result<(), ProgramError> {
+        if self.price <= 0 {
+            return Err(CodeError::InvalidOracle.into());
+        }
+
+        if current_slot.saturating_sub(self.publish_slot) > max_staleness_slots {
+            msg!("Oracle price is stale");
+            return Err(CodeError::StaleOraclePrice.into());
+        }
+
+        let confidence_bps = (self.conf as u128)
+            .checked_mul(BPS_DENOMINATOR as u128)
+            .ok_or(CodeError::ArithmeticOverflow)?
+            / self.price as u128;
+        if confidence_bps > max_confidence_bps as u128 {
+            msg!("Oracle confidence interval is too wide");
+            return Err(CodeError::OracleConfidenceTooWide.into());
+        }
+
+        Ok(())
+    }
+
+    /// Converts the price to phoenix ticks, rounded down.
+    pub fn price_in_ticks(&self, market_params: &MarketParams) -> Result<u64, ProgramError> {
+        if self.price <= 0 {
+            return Err(CodeError::InvalidOracle.into());
+        }
+
+        // quote atoms per base unit = price * 10^(expo + quote_decimals) * raw base units per base unit
+        let exponent = self.expo
+            .checked_add(market_params.quote_decimals as i32)
+            .ok_or(CodeError::ArithmeticOverflow)?;
+        let mut numerator = (self.price as u128)
+            .checked_mul(market_params.raw_base_units_per_base_unit.max(1) as u128)
+            .ok_or(CodeError::ArithmeticOverflow)?;
+        let mut denominator = market_params.tick_size_in_quote_atoms_per_base_unit as u128;
+        let scale = 10u128
+            .checked_pow(exponent.unsigned_abs())
+            .ok_or(CodeError::ArithmeticOverflow)?;
+        if exponent >= 0 {
+            numerator = numerator.checked_mul(scale).ok_or(CodeError::ArithmeticOverflow)?;
+        } else {
+            denominator = denominator.checked_mul(scale).ok_or(CodeError::ArithmeticOverflow)?;
+        }
+        if denominator == 0 {
+            return Err(CodeError::InvalidPrice.into());
+        }
+
+        let price_in_ticks = u64::try_from(numerator / denominator).map_err(|_| CodeError::ArithmeticOverflow)?;
+        if price_in_ticks == 0 {
+            return Err(CodeError::InvalidPrice.into());
+        }
+        Ok(price_in_ticks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market_params() -> MarketParams {
+        MarketParams {
+            base_lots_per_base_unit: 1_000,
+            tick_size_in_quote_lots_per_base_unit: 10,
+            base_atoms_per_base_lot: 1_000_000,
+            quote_atoms_per_quote_lot: 1,
+            tick_size_in_quote_atoms_per_base_unit: 10,
+            quote_decimals: 6,
+            raw_base_units_per_base_unit: 1,
+        }
+    }
+
+    fn pyth_data(price: i64, conf: u64, expo: i32, status: u32, publish_slot: u64) -> Vec<u8> {
+        let mut data = vec![0u8; PYTH_PRICE_ACCOUNT_MIN_LEN];
+        data[0..4].copy_from_slice(&PYTH_MAGIC.to_le_bytes());
+        data[4..8].copy_from_slice(&PYTH_VERSION.to_le_bytes());
+        data[8..12].copy_from_slice(&PYTH_PRICE_ACCOUNT_TYPE.to_le_bytes());
+        data[20..24].copy_from_slice(&expo.to_le_bytes());
+        data[208..216].copy_from_slice(&price.to_le_bytes());
+        data[216..224].copy_from_slice(&conf.to_le_bytes());
+        data[224..228].copy_from_slice(&status.to_le_bytes());
+        data[232..240].copy_from_slice(&publish_slot.to_le_bytes());
+        data
+    }
+
+    fn oracle_price(price: i64, conf: u64, expo: i32, publish_slot: u64) -> OraclePrice {
+        OraclePrice { price, conf, expo, publish_slot }
+    }
+
+    #[test]
+    fn pyth_price_account_unpacks() {
+        let data = pyth_data(15_000, 20, -4, PYTH_STATUS_TRADING, 77);
+        assert_eq!(OraclePrice::unpack_pyth(&data), Ok(oracle_price(15_000, 20, -4, 77)));
+    }
+
+    #[test]
+    fn malformed_pyth_accounts_are_rejected() {
+        let data = pyth_data(15_000, 20, -4, PYTH_STATUS_TRADING, 77);
+        assert_eq!(OraclePrice::unpack_pyth(&data[..PYTH_PRICE_ACCOUNT_MIN_LEN - 1]), Err(CodeError::InvalidOracle.into()));
+
+        for offset in [0, 4, 8] {
+            let mut data = data.clone();
+            data[offset] ^= 1;
+            assert_eq!(OraclePrice::unpack_pyth(&data), Err(CodeError::InvalidOracle.into()));
+        }
+    }
+
+    #[test]
+    fn pyth_price_not_trading_is_rejected() {
+        let data = pyth_data(15_000, 20, -4, 0, 77);
+        assert_eq!(OraclePrice::unpack_pyth(&data), Err(CodeError::InvalidOracle.into()));
+    }
+
+    #[test]
+    fn pyth_price_account_should_be_owned_by_pyth() {
+        let key = Pubkey::new_unique();
+        let program_id = Pubkey::new_unique();
+        let pyth_program_id = PYTH_PROGRAM_ID.parse::<Pubkey>().unwrap();
+        let mut data = pyth_data(15_000, 20, -4, PYTH_STATUS_TRADING, 77);
+        let mut lamports = 0;
+
+        let oracle = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &pyth_program_id, false, 0);
+        assert_eq!(OraclePrice::load(&oracle, PriceSource::Pyth, &program_id), Ok(oracle_price(15_000, 20, -4, 77)));
+
+        let owners = [program_id, Pubkey::new_unique()];
+        for owner in &owners {
+            let mut oracle = oracle.clone();
+            oracle.owner = owner;
+            assert_eq!(OraclePrice::load(&oracle, PriceSource::Pyth, &program_id), Err(CodeError::InvalidOracle.into()));
+        }
+    }
+
+    #[test]
+    fn stale_prices_are_rejected() {
+        let price = oracle_price(15_000, 0, -4, 100);
+        assert_eq!(price.validate(110, 10, 100), Ok(()));
+        assert_eq!(price.validate(111, 10, 100), Err(CodeError::StaleOraclePrice.into()));
+        // a publish slot ahead of the clock isn't stale
+        assert_eq!(price.validate(90, 10, 100), Ok(()));
+    }
+
+    #[test]
+    fn wide_confidence_intervals_are_rejected() {
+        // 150 / 15000 = 100 bps
+        assert_eq!(oracle_price(15_000, 150, -4, 100).validate(100, 10, 100), Ok(()));
+        // 151 / 15000 = 100.67 bps, rounded down
+        assert_eq!(oracle_price(15_000, 151, -4, 100).validate(100, 10, 100), Ok(()));
+        assert_eq!(oracle_price(15_000, 165, -4, 100).validate(100, 10, 100), Err(CodeError::OracleConfidenceTooWide.into()));
+    }
+
+    #[test]
+    fn non_positive_prices_are_rejected() {
+        assert_eq!(oracle_price(0, 0, -4, 100).validate(100, 10, 100), Err(CodeError::InvalidOracle.into()));
+        assert_eq!(oracle_price(-1, 0, -4, 100).validate(100, 10, 100), Err(CodeError::InvalidOracle.into()));
+        assert_eq!(oracle_price(0, 0, -4, 100).price_in_ticks(&market_params()), Err(CodeError::InvalidOracle.into()));
+    }
+
+    #[test]
+    fn prices_convert_to_ticks() {
+        // 1.5 quote units per base unit = 1_500_000 quote atoms, 10 atoms per tick
+        assert_eq!(oracle_price(150, 0, -2, 0).price_in_ticks(&market_params()), Ok(150_000));
+        // positive exponents scale the price up
+        assert_eq!(oracle_price(3, 0, 1, 0).price_in_ticks(&market_params()), Ok(3_000_000));
+        // raw base units scale the price per base unit
+        let market_params = MarketParams { raw_base_units_per_base_unit: 1_000, ..market_params() };
+        assert_eq!(oracle_price(150, 0, -2, 0).price_in_ticks(&market_params), Ok(150_000_000));
+    }
+
+    #[test]
+    fn prices_in_ticks_round_down() {
+        // 1.23456789 quote units = 1_234_567.89 quote atoms = 123_456.789 ticks
+        assert_eq!(oracle_price(123_456_789, 0, -8, 0).price_in_ticks(&market_params()), Ok(123_456));
+    }
+
+    #[test]
+    fn prices_below_a_tick_are_rejected() {
+        assert_eq!(oracle_price(1, 0, -10, 0).price_in_ticks(&market_params()), Err(CodeError::InvalidPrice.into()));
+        assert_eq!(oracle_price(1, 0, 1, 0).price_in_ticks(&MarketParams { tick_size_in_quote_atoms_per_base_unit: 0, ..market_params() }), Err(CodeError::InvalidPrice.into()));
+    }
+
+    #[test]
+    fn oversized_prices_overflow() {
+        assert_eq!(oracle_price(i64::MAX, 0, 30, 0).price_in_ticks(&market_params()), Err(CodeError::ArithmeticOverflow.into()));
+    }
+}
@@ -0,0 +1,852 @@
+use phoenix::{program::MarketHeader, quantities::WrapperU64, state::{markets::{FIFOOrderId, FIFORestingOrder, LadderOrder, Market}, OrderPacket}};
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+
+use crate::error::CodeError;
+
+
+pub const BPS_DENOMINATOR: u64 = 10_000;
+pub const MAX_LADDER_LEVELS: u8 = 10;
+pub const VWAP_BOOK_LEVELS: u64 = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpreadMode {
+    Bps = 1, // spread is expressed in basis points of the mid price
+    Ticks = 2, // spread is expressed in ticks away from the mid price
+}
+
+impl TryFrom<u8> for SpreadMode {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(SpreadMode::Bps),
+            2 => Ok(SpreadMode::Ticks),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MidModel {
+    TopOfBook = 1, // midpoint of the best bid and best ask
+    Microprice = 2, // best bid and best ask weighted by the size resting on the opposite side
+    Vwap = 3, // midpoint of the volume weighted prices of the first vwap_depth_base_lots on each side
+}
+
+impl TryFrom<u8> for MidModel {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(MidModel::TopOfBook),
+            2 => Ok(MidModel::Microprice),
+            3 => Ok(MidModel::Vwap),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+impl MidModel {
+    /// Number of book levels per side the model needs from `get_ladder`.
+    pub fn book_levels(&self) -> u64 {
+        match self {
+            MidModel::TopOfBook | MidModel::Microprice => 1,
+            MidModel::Vwap => VWAP_BOOK_LEVELS,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmptyBookPolicy {
+    Fail = 1, // fail with CodeError::EmptyOrderBook
+    LastMid = 2, // quote around the last stored mid price
+    Oracle = 3, // quote around the position's oracle price
+    PopulatedSide = 4, // quote only the populated side, around its best price
+}
+
+impl TryFrom<u8> for EmptyBookPolicy {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(EmptyBookPolicy::Fail),
+            2 => Ok(EmptyBookPolicy::LastMid),
+            3 => Ok(EmptyBookPolicy::Oracle),
+            4 => Ok(EmptyBookPolicy::PopulatedSide),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+/// What to quote around when the order book is empty or one-sided.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmptyBookPrice {
+    Reference(ReferencePrice), // quote around this price
+    Oracle, // read the position's fallback oracle
+}
+
+impl EmptyBookPolicy {
+    /// Applies the policy to the best prices left in the book and the last stored mid, 0 when there is none.
+    pub fn empty_book_price(
+        &self,
+        best_bid_in_ticks: Option<u64>,
+        best_ask_in_ticks: Option<u64>,
+        last_mid_price_in_ticks: u64,
+    ) -> Result<EmptyBookPrice, ProgramError> {
+        match self {
+            EmptyBookPolicy::Fail => Err(CodeError::EmptyOrderBook.into()),
+            EmptyBookPolicy::LastMid => match last_mid_price_in_ticks {
+                0 => Err(CodeError::EmptyOrderBook.into()),
+                price_in_ticks => Ok(EmptyBookPrice::Reference(ReferencePrice::two_sided(price_in_ticks))),
+            },
+            EmptyBookPolicy::Oracle => Ok(EmptyBookPrice::Oracle),
+            EmptyBookPolicy::PopulatedSide => match (best_bid_in_ticks, best_ask_in_ticks) {
+                (Some(price_in_ticks), _) => Ok(EmptyBookPrice::Reference(ReferencePrice { price_in_ticks, quote_bids: true, quote_asks: false })),
+                (None, Some(price_in_ticks)) => Ok(EmptyBookPrice::Reference(ReferencePrice { price_in_ticks, quote_bids: false, quote_asks: true })),
+                (None, None) => Err(CodeError::EmptyOrderBook.into()),
+            },
+        }
+    }
+}
+
+/// Price a position quotes around and the sides it may quote.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReferencePrice {
+    pub price_in_ticks: u64,
+    pub quote_bids: bool,
+    pub quote_asks: bool,
+}
+
+impl ReferencePrice {
+    pub fn two_sided(price_in_ticks: u64) -> Self {
+        ReferencePrice {
+            price_in_ticks,
+            quote_bids: true,
+            quote_asks: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SizeProfile {
+    Flat = 1, // every level gets the same size
+    Linear = 2, // each level is size_profile_param bps of the first level bigger than the previous one
+    Geometric = 3, // each level is size_profile_param bps of the previous one
+}
+
+impl TryFrom<u8> for SizeProfile {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(SizeProfile::Flat),
+            2 => Ok(SizeProfile::Linear),
+            3 => Ok(SizeProfile::Geometric),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+/// A single limit order of a ladder.
+#[derive(Clone, Copy, Debug)]
+pub struct LevelOrder {
+    pub price_in_ticks: u64,
+    pub num_base_lots: u64,
+}
+
+/// Layout of the orders a position posts on each side of the book.
+#[derive(Clone, Copy, Debug)]
+pub struct LadderConfig {
+    pub num_levels: u8,
+    pub level_spacing_mode: SpreadMode,
+    pub level_spacing: u64,
+    pub size_profile: SizeProfile,
+    pub size_profile_param: u64,
+}
+
+impl LadderConfig {
+    pub fn new(num_levels: u8, level_spacing_mode: u8, level_spacing: u64, size_profile: u8, size_profile_param: u64) -> Result<Self, ProgramError> {
+        let ladder_config = LadderConfig {
+            num_levels,
+            level_spacing_mode: SpreadMode::try_from(level_spacing_mode)?,
+            level_spacing,
+            size_profile: SizeProfile::try_from(size_profile)?,
+            size_profile_param,
+        };
+        ladder_config.validate()?;
+        Ok(ladder_config)
+    }
+
+    fn validate(&self) -> Result<(), ProgramError> {
+        if self.num_levels == 0 || self.num_levels > MAX_LADDER_LEVELS {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        if self.num_levels > 1 {
+            let max_spacing = self.level_spacing
+                .checked_mul((self.num_levels - 1) as u64)
+                .ok_or(ProgramError::InvalidInstructionData)?;
+            validate_spread(self.level_spacing_mode, max_spacing)?;
+        }
+        if self.size_profile == SizeProfile::Geometric && self.size_profile_param == 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(())
+    }
+
+    /// Bid prices of every level, starting from `top_bid_in_ticks` and moving away from the mid.
+    pub fn bid_prices(&self, top_bid_in_ticks: u64) -> Result<Vec<u64>, ProgramError> {
+        (0..self.num_levels as u64)
+            .map(|level| bid_price_in_ticks(top_bid_in_ticks, self.level_spacing_mode, self.level_offset(level)?))
+            .collect()
+    }
+
+    /// Ask prices of every level, starting from `top_ask_in_ticks` and moving away from the mid.
+    pub fn ask_prices(&self, top_ask_in_ticks: u64) -> Result<Vec<u64>, ProgramError> {
+        (0..self.num_levels as u64)
+            .map(|level| ask_price_in_ticks(top_ask_in_ticks, self.level_spacing_mode, self.level_offset(level)?))
+            .collect()
+    }
+
+    /// Pairs every level price with its share of `total_num_base_lots`.
+    pub fn orders(&self, prices_in_ticks: Vec<u64>, total_num_base_lots: u64) -> Result<Vec<LevelOrder>, ProgramError> {
+        Ok(prices_in_ticks
+            .into_iter()
+            .zip(self.split_size(total_num_base_lots)?)
+            .map(|(price_in_ticks, num_base_lots)| LevelOrder { price_in_ticks, num_base_lots })
+            .collect())
+    }
+
+    /// Splits `total` across the levels according to the size profile. Rounding dust goes to the first level.
+    /// Every level gets at least one lot when `total` covers them, so a steep profile doesn't leave empty levels.
+    pub fn split_size(&self, total: u64) -> Result<Vec<u64>, ProgramError> {
+        let weights = self.level_weights()?;
+        let min_size = if total >= weights.len() as u64 { 1 } else { 0 };
+        let to_split = total - min_size * weights.len() as u64;
+        let total_weight = weights.iter().try_fold(0u128, |acc, weight| acc.checked_add(*weight as u128))
+            .ok_or(CodeError::ArithmeticOverflow)?;
+        if total_weight == 0 {
+            return Err(CodeError::ArithmeticOverflow.into());
+        }
+
+        let mut sizes = weights
+            .iter()
+            .map(|weight| {
+                let size = (to_split as u128)
+                    .checked_mul(*weight as u128)
+                    .ok_or(CodeError::ArithmeticOverflow)?;
+                to_u64(size / total_weight + min_size as u128)
+            })
+            .collect::<Result<Vec<u64>, ProgramError>>()?;
+        let allocated = sizes.iter().try_fold(0u64, |acc, size| acc.checked_add(*size))
+            .ok_or(CodeError::ArithmeticOverflow)?;
+        sizes[0] = sizes[0]
+            .checked_add(total.checked_sub(allocated).ok_or(CodeError::ArithmeticOverflow)?)
+            .ok_or(CodeError::ArithmeticOverflow)?;
+        Ok(sizes)
+    }
+
+    fn level_offset(&self, level: u64) -> Result<u64, ProgramError> {
+        self.level_spacing
+            .checked_mul(level)
+            .ok_or_else(|| CodeError::ArithmeticOverflow.into())
+    }
+
+    fn level_weights(&self) -> Result<Vec<u64>, ProgramError> {
+        let mut weights = Vec::with_capacity(self.num_levels as usize);
+        let mut weight = BPS_DENOMINATOR;
+        for level in 0..self.num_levels as u64 {
+            match self.size_profile {
+                SizeProfile::Flat => weights.push(BPS_DENOMINATOR),
+                SizeProfile::Linear => {
+                    let step = self.size_profile_param.checked_mul(level).ok_or(CodeError::ArithmeticOverflow)?;
+                    weights.push(BPS_DENOMINATOR.checked_add(step).ok_or(CodeError::ArithmeticOverflow)?);
+                }
+                SizeProfile::Geometric => {
+                    weights.push(weight);
+                    weight = mul_div_floor(weight, self.size_profile_param, BPS_DENOMINATOR)?;
+                }
+            }
+        }
+        Ok(weights)
+    }
+}
+
+/// Market constants needed to convert between prices, lots and token atoms.
+#[derive(Clone, Copy, Debug)]
+pub struct MarketParams {
+    pub base_lots_per_base_unit: u64,
+    pub tick_size_in_quote_lots_per_base_unit: u64,
+    pub base_atoms_per_base_lot: u64,
+    pub quote_atoms_per_quote_lot: u64,
+    pub tick_size_in_quote_atoms_per_base_unit: u64,
+    pub quote_decimals: u32,
+    pub raw_base_units_per_base_unit: u32,
+}
+
+impl MarketParams {
+    pub fn new(header: &MarketHeader, market: &dyn Market<Pubkey, FIFOOrderId, FIFORestingOrder, OrderPacket>) -> Self {
+        MarketParams {
+            base_lots_per_base_unit: market.get_base_lots_per_base_unit().as_u64(),
+            tick_size_in_quote_lots_per_base_unit: market.get_tick_size().as_u64(),
+            base_atoms_per_base_lot: header.get_base_lot_size().as_u64(),
+            quote_atoms_per_quote_lot: header.get_quote_lot_size().as_u64(),
+            tick_size_in_quote_atoms_per_base_unit: header.get_tick_size_in_quote_atoms_per_base_unit().as_u64(),
+            quote_decimals: header.quote_params.decimals,
+            raw_base_units_per_base_unit: header.raw_base_units_per_base_unit,
+        }
+    }
+}
+
+pub fn validate_spread(spread_mode: SpreadMode, spread: u64) -> Result<(), ProgramError> {
+    let is_valid = match spread_mode {
+        SpreadMode::Bps => spread > 0 && spread < BPS_DENOMINATOR,
+        SpreadMode::Ticks => spread > 0,
+    };
+    if !is_valid {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    Ok(())
+}
+
+/// Midpoint of the best bid and best ask, rounded down.
+pub fn mid_price_in_ticks(best_bid_in_ticks: u64, best_ask_in_ticks: u64) -> Result<u64, ProgramError> {
+    let sum = (best_bid_in_ticks as u128)
+        .checked_add(best_ask_in_ticks as u128)
+        .ok_or(CodeError::ArithmeticOverflow)?;
+    to_u64(sum / 2)
+}
+
+pub fn validate_skew(target_base_ratio_bps: u64, skew_bps: u64) -> Result<(), ProgramError> {
+    if target_base_ratio_bps > BPS_DENOMINATOR || skew_bps >= BPS_DENOMINATOR {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    Ok(())
+}
+
+/// Shifts the mid so that quotes lean towards bringing inventory back to `target_base_ratio_bps`.
+/// A position holding more base than its target gets a lower reference price, i.e. a more aggressive
+/// ask and a less aggressive bid. The shift is `skew_bps` of the mid when inventory is fully one-sided.
+pub fn skewed_mid_price_in_ticks(
+    mid_price_in_ticks: u64,
+    base_value_in_quote_lots: u64,
+    quote_lots: u64,
+    target_base_ratio_bps: u64,
+    skew_bps: u64,
+) -> Result<u64, ProgramError> {
+    let total_value = (base_value_in_quote_lots as u128)
+        .checked_add(quote_lots as u128)
+        .ok_or(CodeError::ArithmeticOverflow)?;
+    if skew_bps == 0 || total_value == 0 {
+        return Ok(mid_price_in_ticks);
+    }
+
+    let base_ratio_bps = (base_value_in_quote_lots as u128)
+        .checked_mul(BPS_DENOMINATOR as u128)
+        .ok_or(CodeError::ArithmeticOverflow)?
+        / total_value;
+    let deviation_bps = base_ratio_bps as i128 - target_base_ratio_bps as i128;
+    let shift_bps = deviation_bps
+        .checked_mul(skew_bps as i128)
+        .ok_or(CodeError::ArithmeticOverflow)?
+        / BPS_DENOMINATOR as i128;
+
+    let factor = u64::try_from(BPS_DENOMINATOR as i128 - shift_bps).map_err(|_| CodeError::InvalidPrice)?;
+    let price = mul_div_floor(mid_price_in_ticks, factor, BPS_DENOMINATOR)?;
+    if price == 0 {
+        return Err(CodeError::InvalidPrice.into());
+    }
+    Ok(price)
+}
+
+/// Mid price of an order book under `mid_model`, rounded down. Both sides should be sorted from the best price.
+/// `vwap_depth_base_lots` is only used by the VWAP model, which averages over whatever depth is available if the book is thinner.
+pub fn book_mid_price_in_ticks(
+    bids: &[LadderOrder],
+    asks: &[LadderOrder],
+    mid_model: MidModel,
+    vwap_depth_base_lots: u64,
+) -> Result<u64, ProgramError> {
+    let best_bid = bids.first().ok_or(ProgramError::InvalidAccountData)?;
+    let best_ask = asks.first().ok_or(ProgramError::InvalidAccountData)?;
+
+    match mid_model {
+        MidModel::TopOfBook => mid_price_in_ticks(best_bid.price_in_ticks, best_ask.price_in_ticks),
+        MidModel::Microprice => {
+            // the more size rests on the bid, the closer the fair price is to the ask
+            let total_size = (best_bid.size_in_base_lots as u128)
+                .checked_add(best_ask.size_in_base_lots as u128)
+                .ok_or(CodeError::ArithmeticOverflow)?;
+            if total_size == 0 {
+                return mid_price_in_ticks(best_bid.price_in_ticks, best_ask.price_in_ticks);
+            }
+            let weighted_bid = (best_bid.price_in_ticks as u128)
+                .checked_mul(best_ask.size_in_base_lots as u128)
+                .ok_or(CodeError::ArithmeticOverflow)?;
+            let weighted_ask = (best_ask.price_in_ticks as u128)
+                .checked_mul(best_bid.size_in_base_lots as u128)
+                .ok_or(CodeError::ArithmeticOverflow)?;
+            let numerator = weighted_bid.checked_add(weighted_ask).ok_or(CodeError::ArithmeticOverflow)?;
+            to_u64(numerator / total_size)
+        }
+        MidModel::Vwap => {
+            let bid_vwap = vwap_in_ticks(bids, vwap_depth_base_lots)?;
+            let ask_vwap = vwap_in_ticks(asks, vwap_depth_base_lots)?;
+            mid_price_in_ticks(bid_vwap, ask_vwap)
+        }
+    }
+}
+
+/// Volume weighted price of the first `depth_base_lots` of one side of the book, rounded down.
+fn vwap_in_ticks(orders: &[LadderOrder], depth_base_lots: u64) -> Result<u64, ProgramError> {
+    let mut remaining = depth_base_lots;
+    let mut filled = 0u128;
+    let mut notional = 0u128;
+    for order in orders {
+        if remaining == 0 {
+            break;
+        }
+        let size = order.size_in_base_lots.min(remaining);
+        remaining -= size;
+        filled = filled.checked_add(size as u128).ok_or(CodeError::ArithmeticOverflow)?;
+        notional = (order.price_in_ticks as u128)
+            .checked_mul(size as u128)
+            .and_then(|order_notional| notional.checked_add(order_notional))
+            .ok_or(CodeError::ArithmeticOverflow)?;
+    }
+    if filled == 0 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    to_u64(notional / filled)
+}
+
+/// Bid price `spread` away from `mid_price_in_ticks`. Rounds down so the bid never crosses the requested spread.
+pub fn bid_price_in_ticks(mid_price_in_ticks: u64, spread_mode: SpreadMode, spread: u64) -> Result<u64, ProgramError> {
+    let price = match spread_mode {
+        SpreadMode::Bps => {
+            let factor = BPS_DENOMINATOR.checked_sub(spread).ok_or(CodeError::InvalidPrice)?;
+            mul_div_floor(mid_price_in_ticks, factor, BPS_DENOMINATOR)?
+        }
+        SpreadMode::Ticks => mid_price_in_ticks.checked_sub(spread).ok_or(CodeError::InvalidPrice)?,
+    };
+    if price == 0 {
+        return Err(CodeError::InvalidPrice.into());
+    }
+    Ok(price)
+}
+
+/// Ask price `spread` away from `mid_price_in_ticks`. Rounds up so the ask never crosses the requested spread.
+pub fn ask_price_in_ticks(mid_price_in_ticks: u64, spread_mode: SpreadMode, spread: u64) -> Result<u64, ProgramError> {
+    let price = match spread_mode {
+        SpreadMode::Bps => {
+            let factor = BPS_DENOMINATOR.checked_add(spread).ok_or(CodeError::ArithmeticOverflow)?;
+            mul_div_ceil(mid_price_in_ticks, factor, BPS_DENOMINATOR)?
+        }
+        SpreadMode::Ticks => mid_price_in_ticks.checked_add(spread).ok_or(CodeError::ArithmeticOverflow)?,
+    };
+    if price == 0 {
+        return Err(CodeError::InvalidPrice.into());
+    }
+    Ok(price)
+}
+
+/// Top bid and ask prices of a ladder, `spread` away from `mid_price_in_ticks` and at least `min_spread_in_ticks` away.
+pub fn top_of_ladder_prices(mid_price_in_ticks: u64, spread_mode: SpreadMode, spread: u64, min_spread_in_ticks: u64) -> Result<(u64, u64), ProgramError> {
+    let bid_price = bid_price_in_ticks(mid_price_in_ticks, spread_mode, spread)?
+        .min(bid_price_in_ticks(mid_price_in_ticks, SpreadMode::Ticks, min_spread_in_ticks)?);
+    let ask_price = ask_price_in_ticks(mid_price_in_ticks, spread_mode, spread)?
+        .max(ask_price_in_ticks(mid_price_in_ticks, SpreadMode::Ticks, min_spread_in_ticks)?);
+    Ok((bid_price, ask_price))
+}
+
+/// Caps every order of a ladder at `max_order_base_lots`, 0 for no cap. The rest stays as free funds.
+pub fn cap_order_sizes(orders: &mut [LevelOrder], max_order_base_lots: u64) {
+    if max_order_base_lots == 0 {
+        return;
+    }
+    for order in orders.iter_mut() {
+        order.num_base_lots = order.num_base_lots.min(max_order_base_lots);
+    }
+}
+
+/// Quote lots needed to buy `num_base_lots` at `price_in_ticks`, rounded up.
+pub fn quote_lots_for_base_lots(market_params: &MarketParams, price_in_ticks: u64, num_base_lots: u64) -> Result<u64, ProgramError> {
+    let quote_lots_per_base_unit = (price_in_ticks as u128)
+        .checked_mul(market_params.tick_size_in_quote_lots_per_base_unit as u128)
+        .ok_or(CodeError::ArithmeticOverflow)?;
+    let numerator = quote_lots_per_base_unit
+        .checked_mul(num_base_lots as u128)
+        .ok_or(CodeError::ArithmeticOverflow)?;
+    let denominator = market_params.base_lots_per_base_unit as u128;
+    if denominator == 0 {
+        return Err(CodeError::ArithmeticOverflow.into());
+    }
+    let rounded_up = numerator
+        .checked_add(denominator - 1)
+        .ok_or(CodeError::ArithmeticOverflow)?;
+    to_u64(rounded_up / denominator)
+}
+
+/// Quote lots locked by a resting bid of `num_base_lots` at `price_in_ticks`, rounded down like phoenix does.
+pub fn quote_lots_locked_by_bid(market_params: &MarketParams, price_in_ticks: u64, num_base_lots: u64) -> Result<u64, ProgramError> {
+    let quote_lots_per_base_unit = (price_in_ticks as u128)
+        .checked_mul(market_params.tick_size_in_quote_lots_per_base_unit as u128)
+        .ok_or(CodeError::ArithmeticOverflow)?;
+    let numerator = quote_lots_per_base_unit
+        .checked_mul(num_base_lots as u128)
+        .ok_or(CodeError::ArithmeticOverflow)?;
+    let denominator = market_params.base_lots_per_base_unit as u128;
+    if denominator == 0 {
+        return Err(CodeError::ArithmeticOverflow.into());
+    }
+    to_u64(numerator / denominator)
+}
+
+/// Base lots that `quote_lots` can buy at `price_in_ticks`, rounded down.
+pub fn base_lots_for_quote_lots(market_params: &MarketParams, price_in_ticks: u64, quote_lots: u64) -> Result<u64, ProgramError> {
+    let numerator = (quote_lots as u128)
+        .checked_mul(market_params.base_lots_per_base_unit as u128)
+        .ok_or(CodeError::ArithmeticOverflow)?;
+    let denominator = (price_in_ticks as u128)
+        .checked_mul(market_params.tick_size_in_quote_lots_per_base_unit as u128)
+        .ok_or(CodeError::ArithmeticOverflow)?;
+    if denominator == 0 {
+        return Err(CodeError::InvalidPrice.into());
+    }
+    to_u64(numerator / denominator)
+}
+
+pub fn base_lots_to_atoms(market_params: &MarketParams, num_base_lots: u64) -> Result<u64, ProgramError> {
+    num_base_lots
+        .checked_mul(market_params.base_atoms_per_base_lot)
+        .ok_or_else(|| CodeError::ArithmeticOverflow.into())
+}
+
+pub fn quote_lots_to_atoms(market_params: &MarketParams, quote_lots: u64) -> Result<u64, ProgramError> {
+    quote_lots
+        .checked_mul(market_params.quote_atoms_per_quote_lot)
+        .ok_or_else(|| CodeError::ArithmeticOverflow.into())
+}
+
+fn mul_div_floor(value: u64, numerator: u64, denominator: u64) -> Result<u64, ProgramError> {
+    let product = (value as u128)
+        .checked_mul(numerator as u128)
+        .ok_or(CodeError::ArithmeticOverflow)?;
+    to_u64(product / denominator as u128)
+}
+
+fn mul_div_ceil(value: u64, numerator: u64, denominator: u64) -> Result<u64, ProgramError> {
+    let product = (value as u128)
+        .checked_mul(numerator as u128)
+        .ok_or(CodeError::ArithmeticOverflow)?;
+    // u128::div_ceil isn't available on the bpf toolchain
+    let rounded_up = product
+        .checked_add(denominator as u128 - 1)
+        .ok_or(CodeError::ArithmeticOverflow)?;
+    to_u64(rounded_up / denominator as u128)
+}
+
+fn to_u64(value: u128) -> Result<u64, ProgramError> {
+    u64::try_from(value).map_err(|_| CodeError::ArithmeticOverflow.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market_params() -> MarketParams {
+        MarketParams {
+            base_lots_per_base_unit: 1_000,
+            tick_size_in_quote_lots_per_base_unit: 10,
+            base_atoms_per_base_lot: 1_000_000,
+            quote_atoms_per_quote_lot: 1,
+            tick_size_in_quote_atoms_per_base_unit: 10,
+            quote_decimals: 6,
+            raw_base_units_per_base_unit: 1,
+        }
+    }
+
+    #[test]
+    fn bid_rounds_down_and_ask_rounds_up_in_bps() {
+        // 1001 * 0.999 = 999.999 and 1001 * 1.001 = 1002.001
+        assert_eq!(bid_price_in_ticks(1001, SpreadMode::Bps, 10), Ok(999));
+        assert_eq!(ask_price_in_ticks(1001, SpreadMode::Bps, 10), Ok(1003));
+    }
+
+    #[test]
+    fn exact_bps_prices_are_not_rounded() {
+        assert_eq!(bid_price_in_ticks(10_000, SpreadMode::Bps, 25), Ok(9_975));
+        assert_eq!(ask_price_in_ticks(10_000, SpreadMode::Bps, 25), Ok(10_025));
+    }
+
+    #[test]
+    fn tick_spreads_move_by_whole_ticks() {
+        assert_eq!(bid_price_in_ticks(100, SpreadMode::Ticks, 3), Ok(97));
+        assert_eq!(ask_price_in_ticks(100, SpreadMode::Ticks, 3), Ok(103));
+    }
+
+    #[test]
+    fn zero_prices_are_rejected() {
+        // rounding down a small mid lands on zero
+        assert_eq!(bid_price_in_ticks(1, SpreadMode::Bps, 1), Err(CodeError::InvalidPrice.into()));
+        assert_eq!(bid_price_in_ticks(5, SpreadMode::Ticks, 5), Err(CodeError::InvalidPrice.into()));
+        assert_eq!(ask_price_in_ticks(0, SpreadMode::Ticks, 0), Err(CodeError::InvalidPrice.into()));
+    }
+
+    #[test]
+    fn bids_crossing_zero_are_rejected() {
+        assert_eq!(bid_price_in_ticks(5, SpreadMode::Ticks, 6), Err(CodeError::InvalidPrice.into()));
+        assert_eq!(bid_price_in_ticks(100, SpreadMode::Bps, BPS_DENOMINATOR), Err(CodeError::InvalidPrice.into()));
+        assert_eq!(bid_price_in_ticks(100, SpreadMode::Bps, BPS_DENOMINATOR + 1), Err(CodeError::InvalidPrice.into()));
+    }
+
+    #[test]
+    fn overflowing_asks_are_rejected() {
+        assert_eq!(ask_price_in_ticks(u64::MAX, SpreadMode::Ticks, 1), Err(CodeError::ArithmeticOverflow.into()));
+        assert_eq!(ask_price_in_ticks(u64::MAX, SpreadMode::Bps, 1), Err(CodeError::ArithmeticOverflow.into()));
+    }
+
+    #[test]
+    fn spreads_stay_in_bounds() {
+        assert!(validate_spread(SpreadMode::Bps, 1).is_ok());
+        assert!(validate_spread(SpreadMode::Bps, BPS_DENOMINATOR - 1).is_ok());
+        assert!(validate_spread(SpreadMode::Bps, 0).is_err());
+        assert!(validate_spread(SpreadMode::Bps, BPS_DENOMINATOR).is_err());
+        assert!(validate_spread(SpreadMode::Ticks, 1).is_ok());
+        assert!(validate_spread(SpreadMode::Ticks, 0).is_err());
+    }
+
+    #[test]
+    fn mid_price_rounds_down() {
+        assert_eq!(mid_price_in_ticks(100, 103), Ok(101));
+        assert_eq!(mid_price_in_ticks(u64::MAX, u64::MAX), Ok(u64::MAX));
+    }
+
+    #[test]
+    fn quote_lots_round_up_to_buy_and_down_when_locked() {
+        let market_params = market_params();
+        // 7 ticks * 10 quote lots per unit * 3 lots / 1000 lots per unit = 0.21
+        assert_eq!(quote_lots_for_base_lots(&market_params, 7, 3), Ok(1));
+        assert_eq!(quote_lots_locked_by_bid(&market_params, 7, 3), Ok(0));
+        assert_eq!(quote_lots_for_base_lots(&market_params, 100, 1_000), Ok(1_000));
+        assert_eq!(quote_lots_locked_by_bid(&market_params, 100, 1_000), Ok(1_000));
+    }
+
+    #[test]
+    fn base_lots_for_quote_lots_rounds_down() {
+        let market_params = market_params();
+        assert_eq!(base_lots_for_quote_lots(&market_params, 3, 1), Ok(33));
+        assert_eq!(base_lots_for_quote_lots(&market_params, 0, 1), Err(CodeError::InvalidPrice.into()));
+    }
+
+    #[test]
+    fn lots_convert_to_atoms() {
+        let market_params = market_params();
+        assert_eq!(base_lots_to_atoms(&market_params, 3), Ok(3_000_000));
+        assert_eq!(quote_lots_to_atoms(&market_params, 3), Ok(3));
+        assert_eq!(base_lots_to_atoms(&market_params, u64::MAX), Err(CodeError::ArithmeticOverflow.into()));
+    }
+
+    fn level(price_in_ticks: u64, size_in_base_lots: u64) -> LadderOrder {
+        LadderOrder { price_in_ticks, size_in_base_lots }
+    }
+
+    #[test]
+    fn top_of_book_mid_ignores_size_and_depth() {
+        let bids = [level(99, 1), level(90, 1_000)];
+        let asks = [level(102, 500), level(110, 1_000)];
+        assert_eq!(book_mid_price_in_ticks(&bids, &asks, MidModel::TopOfBook, 0), Ok(100));
+    }
+
+    #[test]
+    fn microprice_leans_towards_the_thinner_side() {
+        // 3 lots bid against 1 lot offered: (100 * 1 + 104 * 3) / 4 = 103
+        let bids = [level(100, 3)];
+        let asks = [level(104, 1)];
+        assert_eq!(book_mid_price_in_ticks(&bids, &asks, MidModel::Microprice, 0), Ok(103));
+        // and 1 lot bid against 3 offered: (100 * 3 + 104 * 1) / 4 = 101
+        assert_eq!(book_mid_price_in_ticks(&[level(100, 1)], &[level(104, 3)], MidModel::Microprice, 0), Ok(101));
+    }
+
+    #[test]
+    fn microprice_of_equal_sizes_is_the_mid() {
+        let bids = [level(100, 5)];
+        let asks = [level(104, 5)];
+        assert_eq!(book_mid_price_in_ticks(&bids, &asks, MidModel::Microprice, 0), Ok(102));
+    }
+
+    #[test]
+    fn microprice_without_size_falls_back_to_the_mid() {
+        let bids = [level(100, 0)];
+        let asks = [level(104, 0)];
+        assert_eq!(book_mid_price_in_ticks(&bids, &asks, MidModel::Microprice, 0), Ok(102));
+    }
+
+    #[test]
+    fn vwap_stops_at_the_requested_depth() {
+        // bids: 10 lots at 100 and 10 of the 30 lots at 90 -> 95
+        // asks: 10 lots at 110 and 10 of the 30 lots at 120 -> 115
+        let bids = [level(100, 10), level(90, 30), level(50, 100)];
+        let asks = [level(110, 10), level(120, 30), level(200, 100)];
+        assert_eq!(book_mid_price_in_ticks(&bids, &asks, MidModel::Vwap, 20), Ok(105));
+        // within the first level the vwap is the top of book
+        assert_eq!(book_mid_price_in_ticks(&bids, &asks, MidModel::Vwap, 5), Ok(105));
+        assert_eq!(vwap_in_ticks(&bids, 20), Ok(95));
+        assert_eq!(vwap_in_ticks(&asks, 20), Ok(115));
+    }
+
+    #[test]
+    fn vwap_of_a_thin_book_averages_the_available_depth() {
+        let bids = [level(100, 1), level(97, 2)];
+        let asks = [level(103, 3)];
+        assert_eq!(vwap_in_ticks(&bids, 1_000), Ok(98));
+        assert_eq!(book_mid_price_in_ticks(&bids, &asks, MidModel::Vwap, 1_000), Ok(100));
+    }
+
+    #[test]
+    fn vwap_without_depth_is_rejected() {
+        let bids = [level(100, 1)];
+        let asks = [level(103, 3)];
+        assert_eq!(book_mid_price_in_ticks(&bids, &asks, MidModel::Vwap, 0), Err(ProgramError::InvalidAccountData));
+        assert_eq!(vwap_in_ticks(&[level(100, 0)], 10), Err(ProgramError::InvalidAccountData));
+    }
+
+    #[test]
+    fn one_sided_books_are_rejected_by_every_model() {
+        let orders = [level(100, 1)];
+        for mid_model in [MidModel::TopOfBook, MidModel::Microprice, MidModel::Vwap] {
+            assert_eq!(book_mid_price_in_ticks(&orders, &[], mid_model, 10), Err(ProgramError::InvalidAccountData));
+            assert_eq!(book_mid_price_in_ticks(&[], &orders, mid_model, 10), Err(ProgramError::InvalidAccountData));
+            assert_eq!(book_mid_price_in_ticks(&[], &[], mid_model, 10), Err(ProgramError::InvalidAccountData));
+        }
+    }
+
+    #[test]
+    fn fail_policy_rejects_empty_books() {
+        for (best_bid, best_ask) in [(None, None), (Some(100), None), (None, Some(100))] {
+            assert_eq!(EmptyBookPolicy::Fail.empty_book_price(best_bid, best_ask, 100), Err(CodeError::EmptyOrderBook.into()));
+        }
+    }
+
+    #[test]
+    fn last_mid_policy_quotes_both_sides_around_the_stored_mid() {
+        assert_eq!(EmptyBookPolicy::LastMid.empty_book_price(None, Some(120), 100), Ok(EmptyBookPrice::Reference(ReferencePrice::two_sided(100))));
+        assert_eq!(EmptyBookPolicy::LastMid.empty_book_price(None, None, 0), Err(CodeError::EmptyOrderBook.into()));
+    }
+
+    #[test]
+    fn oracle_policy_defers_to_the_oracle() {
+        assert_eq!(EmptyBookPolicy::Oracle.empty_book_price(None, None, 0), Ok(EmptyBookPrice::Oracle));
+        assert_eq!(EmptyBookPolicy::Oracle.empty_book_price(Some(90), None, 100), Ok(EmptyBookPrice::Oracle));
+    }
+
+    #[test]
+    fn populated_side_policy_quotes_only_the_populated_side() {
+        assert_eq!(
+            EmptyBookPolicy::PopulatedSide.empty_book_price(Some(90), None, 100),
+            Ok(EmptyBookPrice::Reference(ReferencePrice { price_in_ticks: 90, quote_bids: true, quote_asks: false })),
+        );
+        assert_eq!(
+            EmptyBookPolicy::PopulatedSide.empty_book_price(None, Some(110), 100),
+            Ok(EmptyBookPrice::Reference(ReferencePrice { price_in_ticks: 110, quote_bids: false, quote_asks: true })),
+        );
+        assert_eq!(EmptyBookPolicy::PopulatedSide.empty_book_price(None, None, 100), Err(CodeError::EmptyOrderBook.into()));
+    }
+
+    fn ladder(num_levels: u8, size_profile: SizeProfile, size_profile_param: u64) -> LadderConfig {
+        LadderConfig::new(num_levels, SpreadMode::Ticks as u8, 1, size_profile as u8, size_profile_param).unwrap()
+    }
+
+    #[test]
+    fn flat_sizes_split_evenly_with_dust_on_the_first_level() {
+        assert_eq!(ladder(3, SizeProfile::Flat, 0).split_size(9), Ok(vec![3, 3, 3]));
+        assert_eq!(ladder(3, SizeProfile::Flat, 0).split_size(11), Ok(vec![5, 3, 3]));
+        assert_eq!(ladder(1, SizeProfile::Flat, 0).split_size(11), Ok(vec![11]));
+    }
+
+    #[test]
+    fn linear_sizes_grow_away_from_the_mid() {
+        // weights 1, 1.5 and 2
+        assert_eq!(ladder(3, SizeProfile::Linear, 5_000).split_size(90), Ok(vec![21, 30, 39]));
+        assert_eq!(ladder(3, SizeProfile::Linear, 0).split_size(90), Ok(vec![30, 30, 30]));
+    }
+
+    #[test]
+    fn geometric_sizes_decay_away_from_the_mid() {
+        // weights 1, 0.5, 0.25 and 0.125
+        assert_eq!(ladder(4, SizeProfile::Geometric, 5_000).split_size(150), Ok(vec![81, 39, 20, 10]));
+        // a ratio above 1 grows them instead
+        assert_eq!(ladder(3, SizeProfile::Geometric, 20_000).split_size(70), Ok(vec![11, 20, 39]));
+    }
+
+    #[test]
+    fn sizes_sum_to_the_total() {
+        let ladders = [
+            ladder(5, SizeProfile::Flat, 0),
+            ladder(5, SizeProfile::Linear, 3_333),
+            ladder(5, SizeProfile::Geometric, 7_000),
+            ladder(MAX_LADDER_LEVELS, SizeProfile::Geometric, 1),
+        ];
+        for ladder_config in ladders {
+            for total in (0..1_000).chain([u64::MAX / 2, u64::MAX]) {
+                let sizes = ladder_config.split_size(total).unwrap();
+                assert_eq!(sizes.len(), ladder_config.num_levels as usize);
+                assert_eq!(sizes.iter().map(|size| *size as u128).sum::<u128>(), total as u128);
+            }
+        }
+    }
+
+    #[test]
+    fn steep_geometric_sizes_leave_no_level_empty() {
+        for size_profile_param in [1, 100, 2_500] {
+            let ladder_config = ladder(8, SizeProfile::Geometric, size_profile_param);
+            for total in 8..1_000 {
+                assert!(ladder_config.split_size(total).unwrap().iter().all(|size| *size > 0));
+            }
+        }
+        assert_eq!(ladder(4, SizeProfile::Geometric, 1).split_size(4), Ok(vec![1, 1, 1, 1]));
+        assert_eq!(ladder(4, SizeProfile::Geometric, 1).split_size(10), Ok(vec![7, 1, 1, 1]));
+    }
+
+    #[test]
+    fn sizes_below_one_lot_per_level_go_to_the_first_levels() {
+        assert_eq!(ladder(4, SizeProfile::Geometric, 1).split_size(2), Ok(vec![2, 0, 0, 0]));
+        assert_eq!(ladder(4, SizeProfile::Flat, 0).split_size(3), Ok(vec![3, 0, 0, 0]));
+        assert_eq!(ladder(4, SizeProfile::Flat, 0).split_size(0), Ok(vec![0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn skew_leaves_the_mid_at_the_target_ratio() {
+        assert_eq!(skewed_mid_price_in_ticks(10_000, 500, 500, 5_000, 100), Ok(10_000));
+        assert_eq!(skewed_mid_price_in_ticks(10_000, 0, 1_000, 0, 100), Ok(10_000));
+        // no skew or nothing held
+        assert_eq!(skewed_mid_price_in_ticks(10_000, 900, 100, 5_000, 0), Ok(10_000));
+        assert_eq!(skewed_mid_price_in_ticks(10_000, 0, 0, 5_000, 100), Ok(10_000));
+    }
+
+    #[test]
+    fn skew_lowers_the_mid_when_long_base() {
+        // 40% above the target moves the mid by 40% of the skew
+        assert_eq!(skewed_mid_price_in_ticks(10_000, 900, 100, 5_000, 100), Ok(9_960));
+    }
+
+    #[test]
+    fn skew_raises_the_mid_when_long_quote() {
+        assert_eq!(skewed_mid_price_in_ticks(10_000, 100, 900, 5_000, 100), Ok(10_040));
+    }
+
+    #[test]
+    fn skew_is_clamped_at_skew_bps() {
+        assert_eq!(skewed_mid_price_in_ticks(10_000, 1_000, 0, 0, 100), Ok(9_900));
+        assert_eq!(skewed_mid_price_in_ticks(10_000, u64::MAX, 0, 0, 100), Ok(9_900));
+        assert_eq!(skewed_mid_price_in_ticks(10_000, 0, u64::MAX, BPS_DENOMINATOR, 100), Ok(10_100));
+        // a target in between leaves less room to move
+        assert_eq!(skewed_mid_price_in_ticks(10_000, 1_000, 0, 5_000, 100), Ok(9_950));
+        assert_eq!(skewed_mid_price_in_ticks(10_000, 0, 1_000, 5_000, 100), Ok(10_050));
+    }
+
+    #[test]
+    fn skew_down_to_zero_is_rejected() {
+        assert_eq!(skewed_mid_price_in_ticks(10_000, 1_000, 0, 0, BPS_DENOMINATOR), Err(CodeError::InvalidPrice.into()));
+        assert_eq!(skewed_mid_price_in_ticks(1, 900, 100, 5_000, 100), Err(CodeError::InvalidPrice.into()));
+    }
+}
@@ -0,0 +1,299 @@
+use phoenix::program::get_vault_address;
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey};
+use spl_token::state::Account;
+
+use crate::{error::CodeError, state::{Config, FillHistory, MarketConfig, Position, PositionSeeds}};
+
+
+/// Loads a position and checks it is the position of `trader` on `market` at `position_index`.
+/// The address is checked with the stored bump, no bump search is needed.
+pub(crate) fn load_position(
+    program_id: &Pubkey,
+    position: &AccountInfo,
+    trader: &Pubkey,
+    market: &Pubkey,
+    position_index: u16,
+) -> Result<Position, ProgramError> {
+    if position.owner != program_id {
+        msg!("Position account should be owned by the program");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let position_data = Position::unpack(&position.data.borrow())?;
+    if position_data.needs_migration() {
+        msg!("Position should be migrated first");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if position_data.trader != *trader || position_data.market != *market || position_data.position_index != position_index {
+        msg!("Position doesn't belong to the trader and market");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let position_pubkey = Pubkey::create_program_address(
+        &PositionSeeds::new(trader, market, position_index, position_data.position_bump).signer_seeds(),
+        program_id,
+    )?;
+    if position.key != &position_pubkey {
+        msg!("Invalid position account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(position_data)
+}
+
+/// Loads a position on `market` at `position_index` for an action taken by `authority`, the trader or its keeper.
+/// The authority should sign and, when it's the keeper, hold `permission`. The address is checked with the stored trader.
+pub(crate) fn load_position_for_authority(
+    program_id: &Pubkey,
+    position: &AccountInfo,
+    authority: &AccountInfo,
+    market: &Pubkey,
+    position_index: u16,
+    permission: u8,
+) -> Result<Position, ProgramError> {
+    if position.owner != program_id {
+        msg!("Position account should be owned by the program");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let position_data = Position::unpack(&position.data.borrow())?;
+    if position_data.needs_migration() {
+        msg!("Position should be migrated first");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if position_data.market != *market || position_data.position_index != position_index {
+        msg!("Position doesn't belong to the market");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !authority.is_signer || !position_data.is_authorized(authority.key, permission) {
+        msg!("Authority should be the trader or a keeper with the permission, and should sign");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let position_pubkey = Pubkey::create_program_address(
+        &PositionSeeds::new(&position_data.trader, market, position_index, position_data.position_bump).signer_seeds(),
+        program_id,
+    )?;
+    if position.key != &position_pubkey {
+        msg!("Invalid position account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(position_data)
+}
+
+/// Checks the token accounts and mints against the ones stored on the position.
+pub(crate) fn check_position_token_accounts(
+    position_data: &Position,
+    position_base_token_account: &AccountInfo,
+    position_quote_token_account: &AccountInfo,
+    base_mint: &AccountInfo,
+    quote_mint: &AccountInfo,
+) -> ProgramResult {
+    if position_base_token_account.key != &position_data.base_token_account || position_quote_token_account.key != &position_data.quote_token_account {
+        msg!("Invalid base token account or quote token account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if base_mint.key != &position_data.base_mint || quote_mint.key != &position_data.quote_mint {
+        msg!("Invalid base or quote mint");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
+
+/// Loads the fill history of `position`. The address is checked with the stored bump.
+pub(crate) fn load_fill_history(
+    program_id: &Pubkey,
+    fill_history: &AccountInfo,
+    position: &Pubkey,
+) -> Result<FillHistory, ProgramError> {
+    if !fill_history.is_writable || fill_history.owner != program_id {
+        msg!("Fill history account should be writable and owned by the program");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let fill_history_data = FillHistory::unpack(&fill_history.data.borrow())?;
+    if fill_history_data.position != *position {
+        msg!("Fill history doesn't belong to the position");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let fill_history_pubkey = Pubkey::create_program_address(
+        &[FillHistory::SEED.as_bytes(), position.as_ref(), &[fill_history_data.bump]],
+        program_id,
+    )?;
+    if fill_history.key != &fill_history_pubkey {
+        msg!("Invalid fill history account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(fill_history_data)
+}
+
+/// Loads a vault position from its address alone, depositors don't know the managing trader.
+/// The address is checked with the stored trader, index and bump.
+pub(crate) fn load_vault(
+    program_id: &Pubkey,
+    position: &AccountInfo,
+    market: &Pubkey,
+    share_mint: &AccountInfo,
+) -> Result<Position, ProgramError> {
+    if position.owner != program_id {
+        msg!("Position account should be owned by the program");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let position_data = Position::unpack(&position.data.borrow())?;
+    if position_data.needs_migration() {
+        msg!("Position should be migrated first");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !position_data.is_vault() || position_data.market != *market || position_data.share_mint != *share_mint.key {
+        msg!("Position isn't a vault on the market with this share mint");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let position_pubkey = Pubkey::create_program_address(
+        &PositionSeeds::new(&position_data.trader, market, position_data.position_index, position_data.position_bump).signer_seeds(),
+        program_id,
+    )?;
+    if position.key != &position_pubkey {
+        msg!("Invalid position account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(position_data)
+}
+
+/// Checks the share token account vault fees are minted to belongs to the vault's fee recipient.
+pub(crate) fn check_fee_recipient_share_token_account(
+    position_data: &Position,
+    fee_recipient_share_token_account: &AccountInfo,
+) -> ProgramResult {
+    if !fee_recipient_share_token_account.is_writable {
+        msg!("Fee recipient share token account should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let fee_recipient_share_token_account_data = Account::unpack(&fee_recipient_share_token_account.data.borrow())?;
+    if fee_recipient_share_token_account_data.owner != position_data.fee_recipient || fee_recipient_share_token_account_data.mint != position_data.share_mint {
+        msg!("Invalid fee recipient share token account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
+
+/// Loads the program config. The address is checked with the stored bump.
+pub(crate) fn load_config(
+    program_id: &Pubkey,
+    config: &AccountInfo,
+) -> Result<Config, ProgramError> {
+    if config.owner != program_id {
+        msg!("Config account should be owned by the program");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let config_data = Config::unpack(&config.data.borrow())?;
+    let config_pubkey = Pubkey::create_program_address(&[Config::SEED.as_bytes(), &[config_data.bump]], program_id)?;
+    if config.key != &config_pubkey {
+        msg!("Invalid config account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(config_data)
+}
+
+/// Loads the program config for an admin action, `admin` should be the config's admin and sign.
+pub(crate) fn load_config_for_admin(
+    program_id: &Pubkey,
+    config: &AccountInfo,
+    admin: &AccountInfo,
+) -> Result<Config, ProgramError> {
+    let config_data = load_config(program_id, config)?;
+    if !admin.is_signer || config_data.admin != *admin.key {
+        msg!("Admin account should be the config admin and should sign");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(config_data)
+}
+
+/// Loads the config of `market`. The address is checked with the stored bump.
+pub(crate) fn load_market_config(
+    program_id: &Pubkey,
+    market_config: &AccountInfo,
+    market: &Pubkey,
+) -> Result<MarketConfig, ProgramError> {
+    if market_config.owner != program_id {
+        msg!("Market config account should be owned by the program");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let market_config_data = MarketConfig::unpack(&market_config.data.borrow())?;
+    if market_config_data.market != *market {
+        msg!("Market config doesn't belong to the market");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let market_config_pubkey = Pubkey::create_program_address(&[MarketConfig::SEED.as_bytes(), market.as_ref(), &[market_config_data.bump]], program_id)?;
+    if market_config.key != &market_config_pubkey {
+        msg!("Invalid market config account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(market_config_data)
+}
+
+/// Checks the program isn't paused. Every instruction changing a position checks it,
+/// except cancels, withdrawals, redemptions, pauses and migrations so that funds can always be taken out.
+pub(crate) fn check_program_not_paused(config_data: &Config) -> ProgramResult {
+    if config_data.is_paused {
+        msg!("Program is paused");
+        return Err(CodeError::ProgramPaused.into());
+    }
+
+    Ok(())
+}
+
+/// Checks the program config and the market config let positions place orders on the market.
+pub(crate) fn check_order_placement_allowed(config_data: &Config, market_config_data: &MarketConfig) -> ProgramResult {
+    check_program_not_paused(config_data)?;
+
+    if !market_config_data.is_allowed {
+        msg!("Market isn't allowed by its market config");
+        return Err(CodeError::MarketNotAllowed.into());
+    }
+
+    Ok(())
+}
+
+/// Checks the market account is owned by the phoenix program.
+pub(crate) fn check_phoenix_market(market: &AccountInfo) -> ProgramResult {
+    if market.owner.to_string() != "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY" {
+        msg!("Market account should be owned by the phoenix program");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
+
+/// Checks the base and quote vaults are the phoenix vaults of `market` for the mints.
+pub(crate) fn check_phoenix_vaults(
+    market: &AccountInfo,
+    base_mint: &AccountInfo,
+    quote_mint: &AccountInfo,
+    base_vault: &AccountInfo,
+    quote_vault: &AccountInfo,
+) -> ProgramResult {
+    let (base_vault_pubkey, _) = get_vault_address(market.key, base_mint.key);
+    let (quote_vault_pubkey, _) = get_vault_address(market.key, quote_mint.key);
+    if base_vault.key != &base_vault_pubkey || quote_vault.key != &quote_vault_pubkey {
+        msg!("Base or quote vault doesn't belong to the market");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,210 @@
+use phoenix::program::{load_with_dispatch, MarketHeader};
+use solana_program::{account_info::{AccountInfo, next_account_info}, entrypoint::ProgramResult, msg, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey};
+use core::mem::size_of;
+
+use crate::{accounting::{record_deposit, seat_lots_total}, error::CodeError, events::{Event, FundsDeposited}, instruction::AddFunds, pricing::{base_lots_to_atoms, quote_lots_for_base_lots, quote_lots_to_atoms, MarketParams}, state::{Position, PositionSeeds}};
+use super::{accounts::{check_order_placement_allowed, check_phoenix_market, check_phoenix_vaults, check_position_token_accounts, check_program_not_paused, load_config, load_market_config, load_position}, funds::{deposit_funds_to_phoenix, transfer_tokens_to_position}, quoting::{record_fills, requote_with_free_funds}};
+
+
+pub fn process_add_funds(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: AddFunds,
+) -> ProgramResult {
+    msg!("Add funds");
+
+    if data.base_lots_to_add == 0 && data.quote_lots_to_add == 0 {
+        msg!("At least one of base or quote should be non-zero");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let phoenix_program = next_account_info(account_info_iter)?;
+    let phoenix_log_authority = next_account_info(account_info_iter)?;
+    let market = next_account_info(account_info_iter)?;
+    let trader = next_account_info(account_info_iter)?;
+    let seat = next_account_info(account_info_iter)?;
+    let position = next_account_info(account_info_iter)?;
+    let position_base_token_account = next_account_info(account_info_iter)?;
+    let position_quote_token_account = next_account_info(account_info_iter)?;
+    let base_vault = next_account_info(account_info_iter)?;
+    let quote_vault = next_account_info(account_info_iter)?;
+    let base_mint = next_account_info(account_info_iter)?;
+    let quote_mint = next_account_info(account_info_iter)?;
+    let trader_base_token_account = next_account_info(account_info_iter)?;
+    let trader_quote_token_account = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let config = next_account_info(account_info_iter)?;
+    let market_config = next_account_info(account_info_iter)?;
+    let oracle = account_info_iter.next();
+    let fill_history = account_info_iter.next();
+
+    check_accounts(
+        phoenix_program,
+        trader,
+        position,
+        position_base_token_account,
+        position_quote_token_account,
+        base_vault,
+        quote_vault,
+        base_mint,
+        quote_mint,
+        trader_base_token_account,
+        trader_quote_token_account,
+        token_program,
+    )?;
+    check_phoenix_market(market)?;
+    check_phoenix_vaults(market, base_mint, quote_mint, base_vault, quote_vault)?;
+
+    let config_data = load_config(program_id, config)?;
+    let market_config_data = load_market_config(program_id, market_config, market.key)?;
+    check_program_not_paused(&config_data)?;
+    if data.requote {
+        check_order_placement_allowed(&config_data, &market_config_data)?;
+    }
+
+    let mut position_data = load_position(program_id, position, trader.key, market.key, data.position_index)?;
+    if position_data.is_vault() {
+        msg!("Vault funds only move through deposits and redemptions");
+        return Err(CodeError::NotAllowedOnVault.into());
+    }
+    check_position_token_accounts(&position_data, position_base_token_account, position_quote_token_account, base_mint, quote_mint)?;
+    let position_bump = position_data.position_bump;
+
+    let (base_tokens_to_transfer, quote_tokens_to_transfer, base_cost_in_quote_lots) = {
+        let market_account_data = market.data.borrow();
+        let (header_bytes, market_bytes) = market_account_data.split_at(size_of::<MarketHeader>());
+        let header = bytemuck::try_from_bytes::<MarketHeader>(header_bytes).map_err(|_| ProgramError::InvalidAccountData)?;
+        let market_decoded_data = load_with_dispatch(&header.market_size_params, market_bytes)?.inner;
+        let market_params = MarketParams::new(header, market_decoded_data);
+        let trader_state = market_decoded_data.get_trader_state(position.key).ok_or(ProgramError::InvalidAccountData)?;
+        let (base_lots, quote_lots) = seat_lots_total(trader_state)?;
+        record_fills(program_id, &market_params, position, &mut position_data, fill_history, base_lots, quote_lots)?;
+        (
+            base_lots_to_atoms(&market_params, data.base_lots_to_add)?,
+            quote_lots_to_atoms(&market_params, data.quote_lots_to_add)?,
+            // deposited base enters the cost basis at the last mid price
+            quote_lots_for_base_lots(&market_params, position_data.last_mid_price_in_ticks, data.base_lots_to_add)?,
+        )
+    };
+
+    transfer_tokens_to_position(
+        quote_tokens_to_transfer,
+        base_tokens_to_transfer,
+        trader.clone(),
+        position_base_token_account.clone(),
+        position_quote_token_account.clone(),
+        trader_base_token_account.clone(),
+        trader_quote_token_account.clone(),
+        token_program.clone()
+    )?;
+
+    let position_seeds = PositionSeeds::new(trader.key, market.key, data.position_index, position_bump);
+    let position_signer_seeds: &[&[u8]] = &position_seeds.signer_seeds();
+
+    deposit_funds_to_phoenix(
+        data.base_lots_to_add,
+        data.quote_lots_to_add,
+        phoenix_program,
+        phoenix_log_authority,
+        market,
+        position,
+        seat,
+        position_base_token_account,
+        position_quote_token_account,
+        base_vault,
+        quote_vault,
+        base_mint,
+        quote_mint,
+        token_program,
+        position_signer_seeds,
+    )?;
+
+    record_deposit(&mut position_data, data.base_lots_to_add, data.quote_lots_to_add, base_cost_in_quote_lots)?;
+    Position::pack(position_data, &mut position.data.borrow_mut())?;
+
+    Event::FundsDeposited(FundsDeposited {
+        position: *position.key,
+        base_lots: data.base_lots_to_add,
+        quote_lots: data.quote_lots_to_add,
+    }).emit();
+
+    if data.requote {
+        requote_with_free_funds(
+            program_id,
+            phoenix_program,
+            phoenix_log_authority,
+            market,
+            &market_config_data,
+            position,
+            seat,
+            oracle,
+            fill_history,
+            data.client_order_id,
+            position_signer_seeds,
+        )?;
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn check_accounts(
+    phoenix_program: &AccountInfo,
+    trader: &AccountInfo,
+    position: &AccountInfo,
+    position_base_token_account: &AccountInfo,
+    position_quote_token_account: &AccountInfo,
+    base_vault: &AccountInfo,
+    quote_vault: &AccountInfo,
+    base_mint: &AccountInfo,
+    quote_mint: &AccountInfo,
+    trader_base_token_account: &AccountInfo,
+    trader_quote_token_account: &AccountInfo,
+    token_program: &AccountInfo,
+) -> ProgramResult {
+    if phoenix_program.key.to_string() != "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY" {
+        msg!("Invalid phoenix program account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !trader.is_signer {
+        msg!("Trader account should be signer");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !position.is_writable || !position_base_token_account.is_writable || !position_quote_token_account.is_writable {
+        msg!("Position, base token account and quote token account should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !trader_base_token_account.is_writable || !trader_quote_token_account.is_writable {
+        msg!("Trader base and quote token accounts should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if token_program.key != &spl_token::id() {
+        msg!("Invalid token program account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let trader_base_token_account_data = spl_token::state::Account::unpack(&trader_base_token_account.data.borrow())?;
+    let trader_quote_token_account_data = spl_token::state::Account::unpack(&trader_quote_token_account.data.borrow())?;
+    if trader_base_token_account_data.owner != *trader.key || trader_base_token_account_data.mint != *base_mint.key {
+        msg!("Invalid trader base token account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if trader_quote_token_account_data.owner != *trader.key || trader_quote_token_account_data.mint != *quote_mint.key {
+        msg!("Invalid trader quote token account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let base_vault_data = spl_token::state::Account::unpack(&base_vault.data.borrow())?;
+    let quote_vault_data = spl_token::state::Account::unpack(&quote_vault.data.borrow())?;
+    if base_vault_data.mint != *base_mint.key || quote_vault_data.mint != *quote_mint.key {
+        msg!("Invalid base or quote vault account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use solana_program::{account_info::{AccountInfo, next_account_info}, entrypoint::ProgramResult, msg, program_error::ProgramError, pubkey::Pubkey};
+
+use crate::state::{Position, PositionSeeds};
+use super::{accounts::{check_phoenix_market, load_position_for_authority}, quoting::cancel_all_orders_with_free_funds};
+
+
+pub fn process_cancel_all_orders(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    position_index: u16,
+) -> ProgramResult {
+    msg!("Cancel all orders");
+
+    let account_info_iter = &mut accounts.iter();
+    let phoenix_program = next_account_info(account_info_iter)?;
+    let phoenix_log_authority = next_account_info(account_info_iter)?;
+    let market = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+    let position = next_account_info(account_info_iter)?;
+
+    check_accounts(
+        phoenix_program,
+        market,
+    )?;
+    check_phoenix_market(market)?;
+
+    let position_data = load_position_for_authority(program_id, position, authority, market.key, position_index, Position::KEEPER_CANCEL_ORDERS)?;
+
+    cancel_all_orders_with_free_funds(
+        phoenix_program,
+        phoenix_log_authority,
+        market,
+        position,
+        &PositionSeeds::new(&position_data.trader, market.key, position_index, position_data.position_bump).signer_seeds(),
+    )?;
+
+    Ok(())
+}
+
+fn check_accounts(
+    phoenix_program: &AccountInfo,
+    market: &AccountInfo,
+) -> ProgramResult {
+    if phoenix_program.key.to_string() != "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY" {
+        msg!("Invalid phoenix program account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !market.is_writable {
+        msg!("Market account should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
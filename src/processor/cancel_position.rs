@@ -1,14 +1,16 @@
-use phoenix::program::{create_cancel_all_order_with_free_funds_instruction, create_withdraw_funds_instruction_with_custom_token_accounts};
+use phoenix::program::create_withdraw_funds_instruction_with_custom_token_accounts;
 use solana_program::{account_info::{AccountInfo, next_account_info}, entrypoint::ProgramResult, msg, program::invoke_signed, program_error::ProgramError, program_pack::{IsInitialized, Pack}, pubkey::Pubkey, system_program};
 use spl_token::instruction::{close_account, transfer};
 
-use crate::{error::CodeError, state::Position};
+use crate::{error::CodeError, events::{Event, FundsWithdrawn, PositionClosed}, state::{Position, PositionRegistry, PositionSeeds}};
+use super::{accounts::{check_phoenix_market, check_phoenix_vaults, check_position_token_accounts, load_position}, quoting::{cancel_all_orders_with_free_funds, sync_position_fills}};
 
 
 
 pub fn process_cancel_position(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
+    position_index: u16,
 ) -> ProgramResult {
     msg!("Cancel position");
 
@@ -18,6 +20,7 @@ pub fn process_cancel_position(
     let market = next_account_info(account_info_iter)?;
     let trader = next_account_info(account_info_iter)?;
     let position = next_account_info(account_info_iter)?;
+    let position_registry = next_account_info(account_info_iter)?;
     let position_base_token_account = next_account_info(account_info_iter)?;
     let position_quote_token_account = next_account_info(account_info_iter)?;
     let base_vault = next_account_info(account_info_iter)?;
@@ -28,11 +31,13 @@ pub fn process_cancel_position(
     let quote_mint = next_account_info(account_info_iter)?;
     let token_program = next_account_info(account_info_iter)?;
     let system_program = next_account_info(account_info_iter)?;
+    let fill_history = account_info_iter.next();
 
     check_accounts(
         phoenix_program,
         trader,
         position,
+        position_registry,
         position_base_token_account,
         position_quote_token_account,
         base_vault,
@@ -44,12 +49,23 @@ pub fn process_cancel_position(
         token_program,
         system_program,
     )?;
+    check_phoenix_market(market)?;
+    check_phoenix_vaults(market, base_mint, quote_mint, base_vault, quote_vault)?;
 
-    let (position_pubkey, position_bump) = Pubkey::find_program_address(&[Position::SEED.as_bytes(), trader.key.as_ref(), market.key.as_ref()], program_id);
-    let (position_base_token_account_pubkey, _position_base_token_account_bump) = Pubkey::find_program_address(&[Position::BASE_TOKEN_SEED.as_bytes(), position_pubkey.as_ref(), base_mint.key.as_ref()], program_id);
-    let (position_quote_token_account_pubkey, _position_quote_token_account_bump) = Pubkey::find_program_address(&[Position::QUOTE_TOKEN_SEED.as_bytes(), position_pubkey.as_ref(), quote_mint.key.as_ref()], program_id);
-    if position.key != &position_pubkey || position_base_token_account.key != &position_base_token_account_pubkey || position_quote_token_account.key != &position_quote_token_account_pubkey {
-        msg!("Invalid position, base token account or quote token account");
+    let mut position_data = load_position(program_id, position, trader.key, market.key, position_index)?;
+    if position_data.is_vault() {
+        msg!("Vault funds only move through deposits and redemptions");
+        return Err(CodeError::NotAllowedOnVault.into());
+    }
+    check_position_token_accounts(&position_data, position_base_token_account, position_quote_token_account, base_mint, quote_mint)?;
+    let position_bump = position_data.position_bump;
+
+    // the fill history outlives the position, so it keeps the fills up to the cancel
+    sync_position_fills(program_id, market, position, &mut position_data, fill_history)?;
+
+    let (position_registry_pubkey, _position_registry_bump) = Pubkey::find_program_address(&[PositionRegistry::SEED.as_bytes(), trader.key.as_ref(), market.key.as_ref()], program_id);
+    if position_registry.key != &position_registry_pubkey {
+        msg!("Invalid position registry account");
         return Err(ProgramError::InvalidAccountData);
     }
 
@@ -65,6 +81,7 @@ pub fn process_cancel_position(
         quote_vault,
         base_mint,
         quote_mint,
+        position_index,
         position_bump
     )?;
 
@@ -77,17 +94,32 @@ pub fn process_cancel_position(
         trader_base_token_account,
         trader_quote_token_account,
         token_program,
+        position_index,
         position_bump
     )?;
 
+    // migrated first release positions were never registered
+    if position_index != Position::LEGACY_POSITION_INDEX {
+        unregister_position(trader, position_registry, position_index)?;
+    }
+
+    Event::FundsWithdrawn(FundsWithdrawn {
+        position: *position.key,
+        base_lots: position_data.last_base_lots_total,
+        quote_lots: position_data.last_quote_lots_total,
+    }).emit();
+    Event::PositionClosed(PositionClosed { position: *position.key }).emit();
+
     Ok(())
 }
 
 
+#[allow(clippy::too_many_arguments)]
 fn check_accounts(
     phoenix_program: &AccountInfo,
     trader: &AccountInfo,
     position: &AccountInfo,
+    position_registry: &AccountInfo,
     position_base_token_account: &AccountInfo,
     position_quote_token_account: &AccountInfo,
     base_vault: &AccountInfo,
@@ -109,8 +141,8 @@ fn check_accounts(
         return Err(ProgramError::InvalidAccountData);
     }
 
-    if !position.is_writable || !position_base_token_account.is_writable || !position_quote_token_account.is_writable {
-        msg!("Position, base token account and quote token account should be writable");
+    if !position.is_writable || !position_registry.is_writable || !position_base_token_account.is_writable || !position_quote_token_account.is_writable {
+        msg!("Position, position registry, base token account and quote token account should be writable");
         return Err(ProgramError::InvalidAccountData);
     }
 
@@ -150,6 +182,7 @@ fn check_accounts(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn cancel_orders_and_withdraw_funds_from_phoenix_to_position<'a>(
     phoenix_program: &AccountInfo<'a>,
     phoenix_log_authority: &AccountInfo<'a>,
@@ -162,14 +195,16 @@ fn cancel_orders_and_withdraw_funds_from_phoenix_to_position<'a>(
     quote_vault: &AccountInfo<'a>,
     base_mint: &AccountInfo<'a>,
     quote_mint: &AccountInfo<'a>,
+    position_index: u16,
     position_bump: u8
 ) -> ProgramResult {
-    let cancel_limit_order_ixn = create_cancel_all_order_with_free_funds_instruction(market.key, trader.key);
-    invoke_signed(
-        &cancel_limit_order_ixn,
-        &[phoenix_program.clone(), phoenix_log_authority.clone(), market.clone(), trader.clone(), phoenix_program.clone()],
-        &[&[Position::SEED.as_bytes(), trader.key.as_ref(), market.key.as_ref(), &[position_bump]],
-    ])?;
+    cancel_all_orders_with_free_funds(
+        phoenix_program,
+        phoenix_log_authority,
+        market,
+        position,
+        &PositionSeeds::new(trader.key, market.key, position_index, position_bump).signer_seeds(),
+    )?;
 
     let withdraw_all_funds_ixn = create_withdraw_funds_instruction_with_custom_token_accounts(
         market.key,
@@ -182,12 +217,13 @@ fn cancel_orders_and_withdraw_funds_from_phoenix_to_position<'a>(
     invoke_signed(
         &withdraw_all_funds_ixn,
         &[phoenix_program.clone(), phoenix_log_authority.clone(), market.clone(), position.clone(), position_base_token_account.clone(), position_quote_token_account.clone(), base_vault.clone(), quote_vault.clone(), phoenix_program.clone()],
-        &[&[Position::SEED.as_bytes(), trader.key.as_ref(), market.key.as_ref(), &[position_bump]],
+        &[&PositionSeeds::new(trader.key, market.key, position_index, position_bump).signer_seeds(),
     ])?;
 
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn withdraw_and_close_position_and_token_accounts<'a>(
     trader: &AccountInfo<'a>,
     market: &AccountInfo<'a>,
@@ -197,6 +233,7 @@ fn withdraw_and_close_position_and_token_accounts<'a>(
     trader_base_token_account: &AccountInfo<'a>,
     trader_quote_token_account: &AccountInfo<'a>,
     token_program: &AccountInfo<'a>,
+    position_index: u16,
     position_bump: u8
 ) -> ProgramResult {
     let position_data = Position::unpack(&position.data.borrow())?;
@@ -210,7 +247,7 @@ fn withdraw_and_close_position_and_token_accounts<'a>(
     invoke_signed(
         &transfer_base_tokens_ixn,
         &[position_base_token_account.clone(), trader_base_token_account.clone(), position.clone(), token_program.clone()],
-        &[&[Position::SEED.as_bytes(), trader.key.as_ref(), market.key.as_ref(), &[position_bump]]]
+        &[&PositionSeeds::new(trader.key, market.key, position_index, position_bump).signer_seeds()]
     )?;
 
     let position_quote_token_account_data = spl_token::state::Account::unpack(&position_quote_token_account.data.borrow())?;
@@ -218,21 +255,21 @@ fn withdraw_and_close_position_and_token_accounts<'a>(
     invoke_signed(
         &transfer_quote_tokens_ixn,
         &[position_quote_token_account.clone(), trader_quote_token_account.clone(), position.clone(), token_program.clone()],
-        &[&[Position::SEED.as_bytes(), trader.key.as_ref(), market.key.as_ref(), &[position_bump]]]
+        &[&PositionSeeds::new(trader.key, market.key, position_index, position_bump).signer_seeds()]
     )?;
 
     let close_position_base_token_account_ixn = close_account(token_program.key, position_base_token_account.key, trader.key, position.key, &[position.key])?;
     invoke_signed(
         &close_position_base_token_account_ixn,
         &[position_base_token_account.clone(), trader.clone(), position.clone(), token_program.clone()],
-        &[&[Position::SEED.as_bytes(), trader.key.as_ref(), market.key.as_ref(), &[position_bump]]]
+        &[&PositionSeeds::new(trader.key, market.key, position_index, position_bump).signer_seeds()]
     )?;
 
     let close_position_quote_token_account_ixn = close_account(token_program.key, position_quote_token_account.key, trader.key, position.key, &[position.key])?;
     invoke_signed(
         &close_position_quote_token_account_ixn,
         &[position_quote_token_account.clone(), trader.clone(), position.clone(), token_program.clone()],
-        &[&[Position::SEED.as_bytes(), trader.key.as_ref(), market.key.as_ref(), &[position_bump]]]
+        &[&PositionSeeds::new(trader.key, market.key, position_index, position_bump).signer_seeds()]
     )?;
 
     **trader.try_borrow_mut_lamports()? = trader
@@ -244,3 +281,26 @@ fn withdraw_and_close_position_and_token_accounts<'a>(
 
     Ok(())
 }
+
+fn unregister_position<'a>(
+    trader: &AccountInfo<'a>,
+    position_registry: &AccountInfo<'a>,
+    position_index: u16,
+) -> ProgramResult {
+    let mut position_registry_data = PositionRegistry::unpack(&position_registry.data.borrow())?;
+    position_registry_data.remove(position_index)?;
+
+    if position_registry_data.num_positions > 0 {
+        PositionRegistry::pack(position_registry_data, &mut position_registry.data.borrow_mut())?;
+        return Ok(());
+    }
+
+    **trader.try_borrow_mut_lamports()? = trader
+        .lamports()
+        .checked_add(position_registry.lamports())
+        .ok_or(CodeError::ArithmeticOverflow)?;
+    **position_registry.try_borrow_mut_lamports()? = 0;
+    *position_registry.try_borrow_mut_data()? = &mut [];
+
+    Ok(())
+}
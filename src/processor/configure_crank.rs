@@ -0,0 +1,71 @@
+use solana_program::{account_info::{AccountInfo, next_account_info}, entrypoint::ProgramResult, msg, program::invoke, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey, system_instruction::transfer, system_program};
+
+use crate::{error::CodeError, instruction::ConfigureCrank, state::Position};
+use super::accounts::{check_program_not_paused, load_config, load_position};
+
+
+pub fn process_configure_crank(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: ConfigureCrank,
+) -> ProgramResult {
+    msg!("Configure crank");
+
+    let account_info_iter = &mut accounts.iter();
+    let trader = next_account_info(account_info_iter)?;
+    let market = next_account_info(account_info_iter)?;
+    let position = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let config = next_account_info(account_info_iter)?;
+
+    check_accounts(
+        trader,
+        position,
+        system_program,
+    )?;
+
+    check_program_not_paused(&load_config(program_id, config)?)?;
+
+    let mut position_data = load_position(program_id, position, trader.key, market.key, data.position_index)?;
+
+    // the tip budget is held as lamports on the position account and goes back to the trader when the position is cancelled
+    if data.tip_budget_lamports_to_add > 0 {
+        let transfer_ixn = transfer(trader.key, position.key, data.tip_budget_lamports_to_add);
+        invoke(
+            &transfer_ixn,
+            &[trader.clone(), position.clone(), system_program.clone()],
+        )?;
+        position_data.crank_tip_budget_lamports = position_data.crank_tip_budget_lamports
+            .checked_add(data.tip_budget_lamports_to_add)
+            .ok_or(CodeError::ArithmeticOverflow)?;
+    }
+
+    position_data.crank_interval_slots = data.interval_slots;
+    position_data.crank_tip_lamports = data.tip_lamports;
+    Position::pack(position_data, &mut position.data.borrow_mut())?;
+
+    Ok(())
+}
+
+fn check_accounts(
+    trader: &AccountInfo,
+    position: &AccountInfo,
+    system_program: &AccountInfo,
+) -> ProgramResult {
+    if !trader.is_signer || !trader.is_writable {
+        msg!("Trader account should be signer and writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !position.is_writable {
+        msg!("Position account should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if system_program.key != &system_program::id() {
+        msg!("Invalid system program account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,155 @@
+use solana_program::{account_info::{AccountInfo, next_account_info}, clock::Clock, entrypoint::ProgramResult, msg, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey, sysvar::Sysvar};
+
+use crate::{error::CodeError, events::{Cranked, Event}, state::{Position, PositionSeeds}};
+use super::{accounts::{check_order_placement_allowed, check_phoenix_market, load_config, load_market_config}, quoting::{requote_with_free_funds, sync_position_fills}};
+
+
+pub fn process_crank(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    client_order_id: u128,
+) -> ProgramResult {
+    msg!("Crank");
+
+    let account_info_iter = &mut accounts.iter();
+    let phoenix_program = next_account_info(account_info_iter)?;
+    let phoenix_log_authority = next_account_info(account_info_iter)?;
+    let market = next_account_info(account_info_iter)?;
+    let cranker = next_account_info(account_info_iter)?;
+    let seat = next_account_info(account_info_iter)?;
+    let position = next_account_info(account_info_iter)?;
+    let config = next_account_info(account_info_iter)?;
+    let market_config = next_account_info(account_info_iter)?;
+    let oracle = account_info_iter.next();
+    let fill_history = account_info_iter.next();
+
+    check_accounts(
+        phoenix_program,
+        cranker,
+        position,
+    )?;
+    check_phoenix_market(market)?;
+
+    let config_data = load_config(program_id, config)?;
+    let market_config_data = load_market_config(program_id, market_config, market.key)?;
+    check_order_placement_allowed(&config_data, &market_config_data)?;
+
+    let mut position_data = load_cranked_position(program_id, position, market.key)?;
+
+    if position_data.is_paused {
+        msg!("Position is paused, skipping crank");
+        return Ok(());
+    }
+
+    let slot = Clock::get()?.slot;
+    if position_data.crank_interval_slots == 0 {
+        msg!("Position isn't open to the crank");
+        return Err(CodeError::CrankNotEnabled.into());
+    }
+    let next_crank_slot = position_data.last_crank_slot
+        .checked_add(position_data.crank_interval_slots)
+        .ok_or(CodeError::ArithmeticOverflow)?;
+    if slot < next_crank_slot {
+        msg!("Position can be cranked from slot {}", next_crank_slot);
+        return Err(CodeError::CrankTooEarly.into());
+    }
+
+    sync_position_fills(program_id, market, position, &mut position_data, fill_history)?;
+
+    // the tip is capped by what's left of the budget, a drained budget still lets anyone crank for free
+    let tip_lamports = position_data.crank_tip_lamports.min(position_data.crank_tip_budget_lamports);
+    position_data.crank_tip_budget_lamports -= tip_lamports;
+    position_data.last_crank_slot = slot;
+    let (trader, position_index, position_bump) = (position_data.trader, position_data.position_index, position_data.position_bump);
+    Position::pack(position_data, &mut position.data.borrow_mut())?;
+
+    requote_with_free_funds(
+        program_id,
+        phoenix_program,
+        phoenix_log_authority,
+        market,
+        &market_config_data,
+        position,
+        seat,
+        oracle,
+        fill_history,
+        client_order_id,
+        &PositionSeeds::new(&trader, market.key, position_index, position_bump).signer_seeds(),
+    )?;
+
+    if tip_lamports > 0 {
+        **position.try_borrow_mut_lamports()? = position
+            .lamports()
+            .checked_sub(tip_lamports)
+            .ok_or(CodeError::ArithmeticOverflow)?;
+        **cranker.try_borrow_mut_lamports()? = cranker
+            .lamports()
+            .checked_add(tip_lamports)
+            .ok_or(CodeError::ArithmeticOverflow)?;
+    }
+
+    Event::Cranked(Cranked {
+        position: *position.key,
+        cranker: *cranker.key,
+        slot,
+        tip_lamports,
+    }).emit();
+
+    Ok(())
+}
+
+/// Loads a position from its address alone, the cranker doesn't need to know the trader.
+fn load_cranked_position(
+    program_id: &Pubkey,
+    position: &AccountInfo,
+    market: &Pubkey,
+) -> Result<Position, ProgramError> {
+    if position.owner != program_id {
+        msg!("Position account should be owned by the program");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let position_data = Position::unpack(&position.data.borrow())?;
+    if position_data.needs_migration() {
+        msg!("Position should be migrated first");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if position_data.market != *market {
+        msg!("Position doesn't belong to the market");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let position_pubkey = Pubkey::create_program_address(
+        &PositionSeeds::new(&position_data.trader, market, position_data.position_index, position_data.position_bump).signer_seeds(),
+        program_id,
+    )?;
+    if position.key != &position_pubkey {
+        msg!("Invalid position account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(position_data)
+}
+
+fn check_accounts(
+    phoenix_program: &AccountInfo,
+    cranker: &AccountInfo,
+    position: &AccountInfo,
+) -> ProgramResult {
+    if phoenix_program.key.to_string() != "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY" {
+        msg!("Invalid phoenix program account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !cranker.is_signer || !cranker.is_writable {
+        msg!("Cranker account should be signer and writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !position.is_writable {
+        msg!("Position account should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use solana_program::{account_info::{AccountInfo, next_account_info}, entrypoint::ProgramResult, msg, program::invoke_signed, program_error::ProgramError, program_pack::{IsInitialized, Pack}, pubkey::Pubkey, rent::Rent, system_instruction::create_account, system_program, sysvar::Sysvar};
+
+use crate::{error::CodeError, state::{FillEntry, FillHistory, Position}};
+use super::accounts::{check_program_not_paused, load_config, load_position};
+
+
+pub fn process_create_fill_history(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    position_index: u16,
+) -> ProgramResult {
+    msg!("Create fill history");
+
+    let account_info_iter = &mut accounts.iter();
+    let trader = next_account_info(account_info_iter)?;
+    let market = next_account_info(account_info_iter)?;
+    let position = next_account_info(account_info_iter)?;
+    let fill_history = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let config = next_account_info(account_info_iter)?;
+
+    check_accounts(
+        trader,
+        position,
+        fill_history,
+        system_program,
+    )?;
+
+    check_program_not_paused(&load_config(program_id, config)?)?;
+
+    let mut position_data = load_position(program_id, position, trader.key, market.key, position_index)?;
+
+    let (fill_history_pubkey, fill_history_bump) = Pubkey::find_program_address(&[FillHistory::SEED.as_bytes(), position.key.as_ref()], program_id);
+    if fill_history.key != &fill_history_pubkey {
+        msg!("Invalid fill history account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let fill_history_size = FillHistory::LEN;
+    let lamports = Rent::get()?.minimum_balance(fill_history_size);
+    let create_fill_history_ixn = create_account(trader.key, fill_history.key, lamports, fill_history_size.try_into().unwrap(), program_id);
+    invoke_signed(
+        &create_fill_history_ixn,
+        &[trader.clone(), fill_history.clone(), system_program.clone()],
+        &[&[FillHistory::SEED.as_bytes(), position.key.as_ref(), &[fill_history_bump]]]
+    )?;
+
+    let mut fill_history_data_bytes = fill_history.data.borrow_mut();
+    if FillHistory::unpack_unchecked(&fill_history_data_bytes)?.is_initialized() {
+        msg!("Fill history is already initialized");
+        return Err(CodeError::FillHistoryIsAlreadyInitialized.into());
+    }
+
+    let fill_history_data = FillHistory {
+        is_initialized: true,
+        position: *position.key,
+        bump: fill_history_bump,
+        next_index: 0,
+        num_fills: 0,
+        fills: [FillEntry::default(); FillHistory::CAPACITY],
+    };
+    fill_history_data.pack_into_slice(&mut fill_history_data_bytes);
+
+    // from now on every instruction moving the position's fill snapshot needs the fill history
+    position_data.has_fill_history = true;
+    Position::pack(position_data, &mut position.data.borrow_mut())?;
+
+    Ok(())
+}
+
+fn check_accounts(
+    trader: &AccountInfo,
+    position: &AccountInfo,
+    fill_history: &AccountInfo,
+    system_program: &AccountInfo,
+) -> ProgramResult {
+    if !trader.is_signer || !trader.is_writable {
+        msg!("Trader account should be signer and writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !position.is_writable {
+        msg!("Position account should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !fill_history.is_writable {
+        msg!("Fill history account should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if system_program.key != &system_program::id() {
+        msg!("Invalid system program account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use solana_program::{account_info::{AccountInfo, next_account_info}, entrypoint::ProgramResult, msg, program::invoke_signed, program_error::ProgramError, program_pack::{IsInitialized, Pack}, pubkey::Pubkey, rent::Rent, system_instruction::create_account, system_program, sysvar::Sysvar};
+
+use crate::{error::CodeError, instruction::CreateMarketConfig, state::MarketConfig};
+use super::{accounts::{check_phoenix_market, load_config_for_admin}, params::validate_market_config_params};
+
+
+pub fn process_create_market_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: CreateMarketConfig,
+) -> ProgramResult {
+    msg!("Create market config");
+
+    validate_market_config_params(&data.params)?;
+
+    let account_info_iter = &mut accounts.iter();
+    let admin = next_account_info(account_info_iter)?;
+    let config = next_account_info(account_info_iter)?;
+    let market = next_account_info(account_info_iter)?;
+    let market_config = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    check_accounts(
+        admin,
+        market_config,
+        system_program,
+    )?;
+    check_phoenix_market(market)?;
+
+    load_config_for_admin(program_id, config, admin)?;
+
+    let (market_config_pubkey, market_config_bump) = Pubkey::find_program_address(&[MarketConfig::SEED.as_bytes(), market.key.as_ref()], program_id);
+    if market_config.key != &market_config_pubkey {
+        msg!("Invalid market config account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let lamports = Rent::get()?.minimum_balance(MarketConfig::LEN);
+    let create_market_config_ixn = create_account(admin.key, market_config.key, lamports, MarketConfig::LEN.try_into().unwrap(), program_id);
+    invoke_signed(
+        &create_market_config_ixn,
+        &[admin.clone(), market_config.clone(), system_program.clone()],
+        &[&[MarketConfig::SEED.as_bytes(), market.key.as_ref(), &[market_config_bump]]]
+    )?;
+
+    let mut market_config_data_bytes = market_config.data.borrow_mut();
+    if MarketConfig::unpack_unchecked(&market_config_data_bytes)?.is_initialized() {
+        msg!("Market config is already initialized");
+        return Err(CodeError::PositionIsAlreadyInitialized.into());
+    }
+
+    let mut market_config_data = MarketConfig {
+        is_initialized: true,
+        market: *market.key,
+        bump: market_config_bump,
+        is_allowed: true,
+        min_spread_in_ticks: 0,
+        max_order_base_lots: 0,
+        min_tick_size_in_quote_lots_per_base_unit: 0,
+        max_tick_size_in_quote_lots_per_base_unit: 0,
+    };
+    market_config_data.set_params(&data.params);
+    market_config_data.pack_into_slice(&mut market_config_data_bytes);
+
+    Ok(())
+}
+
+fn check_accounts(
+    admin: &AccountInfo,
+    market_config: &AccountInfo,
+    system_program: &AccountInfo,
+) -> ProgramResult {
+    if !admin.is_signer || !admin.is_writable {
+        msg!("Admin account should be signer and writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !market_config.is_writable {
+        msg!("Market config account should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if system_program.key != &system_program::id() {
+        msg!("Invalid system program account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
@@ -1,35 +1,31 @@
 use phoenix::{program::{create_new_order_instruction_with_custom_token_accounts, load_with_dispatch, MarketHeader}, state::{OrderPacket, SelfTradeBehavior, Side}};
 use solana_program::{account_info::{AccountInfo, next_account_info}, entrypoint::ProgramResult, msg, program::{invoke, invoke_signed}, program_error::ProgramError, program_pack::{IsInitialized, Pack}, pubkey::Pubkey, rent::Rent, system_instruction::create_account, system_program, sysvar::Sysvar};
-use spl_token::{state::Account, instruction::{initialize_account3, transfer}};
+use spl_token::{state::Account, instruction::initialize_account3};
 use core::mem::size_of;
 
-use crate::{error::CodeError, state::Position};
+use crate::{error::CodeError, events::{Event, FundsDeposited, OrdersPlaced, PositionCreated}, instruction::CreatePosition, pricing::{base_lots_to_atoms, cap_order_sizes, quote_lots_for_base_lots, quote_lots_to_atoms, top_of_ladder_prices, LevelOrder, MarketParams, SpreadMode}, state::{MarketConfig, Position, PositionSeeds, PositionRegistry}};
+use super::{accounts::{check_order_placement_allowed, check_phoenix_market, check_phoenix_vaults, load_config, load_market_config}, funds::transfer_tokens_to_position, params::{check_params_bounds, validate_position_params}, quoting::{apply_risk_limits, get_reference_price, record_seat_funds_as_deposit}};
 
 
 pub fn process_create_position(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
-    spread_margin: u64,
-    side: u8,
-    num_base_lots: u64,
-    client_order_id: u128,
+    data: CreatePosition,
 ) -> ProgramResult {
     msg!("Create position");
 
-    let side_enum = match side {
-        1 => Side::Bid,
-        2 => Side::Ask,
-        _ => {
-            msg!("Invalid side");
-            return Err(ProgramError::InvalidInstructionData);
-        }
-    };
+    if data.bid_num_base_lots == 0 && data.ask_num_base_lots == 0 {
+        msg!("At least one side should have a non-zero size");
+        return Err(ProgramError::InvalidInstructionData);
+    }
 
-    if spread_margin > 100 || spread_margin == 0 {
-        msg!("Invalid spread margin");
+    if data.position_index == Position::LEGACY_POSITION_INDEX {
+        msg!("Position index is reserved for migrated positions");
         return Err(ProgramError::InvalidInstructionData);
     }
 
+    validate_position_params(&data.params)?;
+
     let account_info_iter = &mut accounts.iter();
     let phoenix_program = next_account_info(account_info_iter)?;
     let phoenix_log_authority = next_account_info(account_info_iter)?;
@@ -37,6 +33,7 @@ pub fn process_create_position(
     let trader = next_account_info(account_info_iter)?;
     let seat = next_account_info(account_info_iter)?;
     let position = next_account_info(account_info_iter)?;
+    let position_registry = next_account_info(account_info_iter)?;
     let position_base_token_account = next_account_info(account_info_iter)?;
     let position_quote_token_account = next_account_info(account_info_iter)?;
     let base_vault = next_account_info(account_info_iter)?;
@@ -47,11 +44,15 @@ pub fn process_create_position(
     let trader_quote_token_account = next_account_info(account_info_iter)?;
     let token_program = next_account_info(account_info_iter)?;
     let system_program = next_account_info(account_info_iter)?;
+    let config = next_account_info(account_info_iter)?;
+    let market_config = next_account_info(account_info_iter)?;
+    let oracle = account_info_iter.next();
 
     check_accounts(
         phoenix_program,
         trader,
         position,
+        position_registry,
         position_base_token_account,
         position_quote_token_account,
         base_vault,
@@ -63,8 +64,15 @@ pub fn process_create_position(
         token_program,
         system_program
     )?;
+    check_phoenix_market(market)?;
+    check_phoenix_vaults(market, base_mint, quote_mint, base_vault, quote_vault)?;
 
-    let (position_pubkey, position_bump) = Pubkey::find_program_address(&[Position::SEED.as_bytes(), trader.key.as_ref(), market.key.as_ref()], program_id);
+    let config_data = load_config(program_id, config)?;
+    let market_config_data = load_market_config(program_id, market_config, market.key)?;
+    check_order_placement_allowed(&config_data, &market_config_data)?;
+    check_params_bounds(&config_data, &data.params)?;
+
+    let (position_pubkey, position_bump) = PositionSeeds::find_address(program_id, trader.key, market.key, data.position_index);
     let (position_base_token_account_pubkey, position_base_token_account_bump) = Pubkey::find_program_address(&[Position::BASE_TOKEN_SEED.as_bytes(), position_pubkey.as_ref(), base_mint.key.as_ref()], program_id);
     let (position_quote_token_account_pubkey, position_quote_token_account_bump) = Pubkey::find_program_address(&[Position::QUOTE_TOKEN_SEED.as_bytes(), position_pubkey.as_ref(), quote_mint.key.as_ref()], program_id);
     if position.key != &position_pubkey || position_base_token_account.key != &position_base_token_account_pubkey || position_quote_token_account.key != &position_quote_token_account_pubkey {
@@ -72,9 +80,39 @@ pub fn process_create_position(
         return Err(ProgramError::InvalidAccountData);
     }
 
+    let (position_registry_pubkey, position_registry_bump) = Pubkey::find_program_address(&[PositionRegistry::SEED.as_bytes(), trader.key.as_ref(), market.key.as_ref()], program_id);
+    if position_registry.key != &position_registry_pubkey {
+        msg!("Invalid position registry account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
     let rent = Rent::get()?;
 
-    create_position_account(rent, trader.clone(), position.clone(), position_bump, market.clone(), system_program.clone(), spread_margin, program_id)?;
+    let mut position_data = Position::new(data.position_index, &data.params);
+    position_data.trader = *trader.key;
+    position_data.market = *market.key;
+    position_data.base_mint = *base_mint.key;
+    position_data.quote_mint = *quote_mint.key;
+    position_data.base_token_account = position_base_token_account_pubkey;
+    position_data.quote_token_account = position_quote_token_account_pubkey;
+    position_data.position_bump = position_bump;
+    position_data.base_token_account_bump = position_base_token_account_bump;
+    position_data.quote_token_account_bump = position_quote_token_account_bump;
+
+    let (
+        bid_orders,
+        bid_quote_tokens_to_transfer,
+        ask_orders,
+        ask_base_tokens_to_transfer
+    ) = {
+        let market_account_data = market.data.borrow();
+        let (header_bytes, market_bytes) = market_account_data.split_at(size_of::<MarketHeader>());
+        get_market_data(program_id, header_bytes, market_bytes, &market_config_data, &mut position_data, oracle, data.bid_num_base_lots, data.ask_num_base_lots)?
+    };
+
+    create_position_account(rent, trader.clone(), position.clone(), data.position_index, position_bump, market.clone(), system_program.clone(), &position_data, program_id)?;
+
+    register_position(rent, trader.clone(), position_registry.clone(), position_registry_bump, market.clone(), system_program.clone(), data.position_index, program_id)?;
 
     create_and_initialize_position_token_accounts(
         rent,
@@ -89,17 +127,7 @@ pub fn process_create_position(
         token_program.clone()
     )?;
 
-    let market_account_data = market.data.borrow();
-    let (header_bytes, market_bytes) = market_account_data.split_at(size_of::<MarketHeader>());
-    let (
-        bid_price,
-        bid_quote_tokens_to_transfer,
-        ask_price,
-        ask_base_tokens_to_transfer
-    ) = get_market_data(header_bytes, market_bytes, spread_margin, num_base_lots);
-
     transfer_tokens_to_position(
-        side_enum,
         bid_quote_tokens_to_transfer,
         ask_base_tokens_to_transfer,
         trader.clone(),
@@ -110,14 +138,13 @@ pub fn process_create_position(
         token_program.clone()
     )?;
 
-    place_limit_order_on_phoenix(
-        side_enum,
-        bid_price,
-        ask_price,
-        num_base_lots,
-        client_order_id,
+    place_limit_orders_on_phoenix(
+        &bid_orders,
+        &ask_orders,
+        data.client_order_id,
         trader.clone(),
         position.clone(),
+        data.position_index,
         position_bump,
         market.clone(),
         seat.clone(),
@@ -132,14 +159,32 @@ pub fn process_create_position(
         phoenix_log_authority.clone()
     )?;
 
+    let (base_lots_deposited, quote_lots_deposited) = record_seat_funds_as_deposit(market, position, &mut position_data)?;
+    Position::pack(position_data, &mut position.data.borrow_mut())?;
+
+    Event::PositionCreated(PositionCreated {
+        position: *position.key,
+        trader: *trader.key,
+        market: *market.key,
+        position_index: data.position_index,
+        params: data.params,
+    }).emit();
+    Event::FundsDeposited(FundsDeposited {
+        position: *position.key,
+        base_lots: base_lots_deposited,
+        quote_lots: quote_lots_deposited,
+    }).emit();
+
     Ok(())
 }
 
 
+#[allow(clippy::too_many_arguments)]
 fn check_accounts(
     phoenix_program: &AccountInfo,
     trader: &AccountInfo,
     position: &AccountInfo,
+    position_registry: &AccountInfo,
     position_base_token_account: &AccountInfo,
     position_quote_token_account: &AccountInfo,
     base_vault: &AccountInfo,
@@ -161,8 +206,8 @@ fn check_accounts(
         return Err(ProgramError::InvalidAccountData);
     }
 
-    if !position.is_writable || !position_base_token_account.is_writable || !position_quote_token_account.is_writable {
-        msg!("Position, base token account and quote token account should be writable");
+    if !position.is_writable || !position_registry.is_writable || !position_base_token_account.is_writable || !position_quote_token_account.is_writable {
+        msg!("Position, position registry, base token account and quote token account should be writable");
         return Err(ProgramError::InvalidAccountData);
     }
 
@@ -202,38 +247,69 @@ fn check_accounts(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn create_position_account<'a>(
     rent: Rent,
     trader: AccountInfo<'a>,
     position: AccountInfo<'a>,
+    position_index: u16,
     position_bump: u8,
     market: AccountInfo<'a>,
     system_program: AccountInfo<'a>,
-    spread_margin: u64,
+    position_data: &Position,
     program_id: &Pubkey
 ) -> ProgramResult {
-    let position_size = 8 + std::mem::size_of::<Position>();
+    let position_size = Position::LEN;
     let lamports = rent.minimum_balance(position_size);
     let create_position_ixn = create_account(trader.key, position.key, lamports, position_size.try_into().unwrap(), program_id);
     invoke_signed(
         &create_position_ixn,
         &[trader.clone(), position.clone(), system_program],
-        &[&[Position::SEED.as_bytes(), trader.key.as_ref(), market.key.as_ref(), &[position_bump]]]
+        &[&PositionSeeds::new(trader.key, market.key, position_index, position_bump).signer_seeds()]
     )?;
     let mut position_data_bytes = position.data.borrow_mut();
-    let mut position_data = Position::unpack(&position_data_bytes)?;
-    if position_data.is_initialized() {
+    if Position::unpack_unchecked(&position_data_bytes)?.is_initialized() {
         msg!("Position is already initialized");
         return Err(CodeError::PositionIsAlreadyInitialized.into());
     }
 
-    position_data.is_initialized = true;
-    position_data.spread_margin = spread_margin;
-    Position::pack(position_data, &mut position_data_bytes)?;
+    position_data.pack_into_slice(&mut position_data_bytes);
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn register_position<'a>(
+    rent: Rent,
+    trader: AccountInfo<'a>,
+    position_registry: AccountInfo<'a>,
+    position_registry_bump: u8,
+    market: AccountInfo<'a>,
+    system_program: AccountInfo<'a>,
+    position_index: u16,
+    program_id: &Pubkey
+) -> ProgramResult {
+    if position_registry.data_is_empty() {
+        let position_registry_size = PositionRegistry::LEN;
+        let lamports = rent.minimum_balance(position_registry_size);
+        let create_position_registry_ixn = create_account(trader.key, position_registry.key, lamports, position_registry_size.try_into().unwrap(), program_id);
+        invoke_signed(
+            &create_position_registry_ixn,
+            &[trader.clone(), position_registry.clone(), system_program],
+            &[&[PositionRegistry::SEED.as_bytes(), trader.key.as_ref(), market.key.as_ref(), &[position_registry_bump]]]
+        )?;
+    }
+
+    let mut position_registry_data_bytes = position_registry.data.borrow_mut();
+    let mut position_registry_data = PositionRegistry::unpack_unchecked(&position_registry_data_bytes)?;
+    position_registry_data.is_initialized = true;
+    position_registry_data.add(position_index)?;
+    position_registry_data.pack_into_slice(&mut position_registry_data_bytes);
 
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn create_and_initialize_position_token_accounts<'a>(
     rent: Rent,
     trader: AccountInfo<'a>,
@@ -255,7 +331,7 @@ fn create_and_initialize_position_token_accounts<'a>(
         &[trader.clone(), position_base_token_account.clone(), token_program.clone()],
         &[&[Position::BASE_TOKEN_SEED.as_bytes(), position_pubkey.as_ref(), base_mint.key.as_ref(), &[position_base_token_account_bump]]]
     )?;
-    let initialize_position_base_token_account_ixn = initialize_account3(token_program.key, position_base_token_account.key, base_mint.key, &position_pubkey)?;
+    let initialize_position_base_token_account_ixn = initialize_account3(token_program.key, position_base_token_account.key, base_mint.key, position_pubkey)?;
     invoke(
         &initialize_position_base_token_account_ixn, 
         &[position_base_token_account.clone(), base_mint.clone(), token_program.clone()]
@@ -267,7 +343,7 @@ fn create_and_initialize_position_token_accounts<'a>(
         &[trader.clone(), position_quote_token_account.clone(), token_program.clone()],
         &[&[Position::QUOTE_TOKEN_SEED.as_bytes(), position_pubkey.as_ref(), quote_mint.key.as_ref(), &[position_quote_token_account_bump]]]
     )?;
-    let initialize_position_quote_token_account_ixn = initialize_account3(token_program.key, position_quote_token_account.key, quote_mint.key, &position_pubkey)?;
+    let initialize_position_quote_token_account_ixn = initialize_account3(token_program.key, position_quote_token_account.key, quote_mint.key, position_pubkey)?;
     invoke(
         &initialize_position_quote_token_account_ixn, 
         &[position_quote_token_account.clone(), quote_mint.clone(), token_program.clone()]
@@ -276,76 +352,83 @@ fn create_and_initialize_position_token_accounts<'a>(
     Ok(())
 }
 
-fn get_market_data(header_bytes: &[u8], market_bytes: &[u8], spread_margin: u64, num_base_lots: u64) -> (u64, u64, u64, u64) {
-    let header = bytemuck::try_from_bytes::<MarketHeader>(header_bytes).unwrap();
-    let market_decoded_data = load_with_dispatch(&header.market_size_params, market_bytes).unwrap().inner;
-    let ladder = market_decoded_data.get_ladder(1);
-    let max_bid_price = ladder.bids.get(0).unwrap().price_in_ticks;
-    let min_ask_price = ladder.asks.get(0).unwrap().price_in_ticks;
-    let market_price = (max_bid_price + min_ask_price) / 2;
-    
-    let bid_price = market_price * ((100 - spread_margin) / 100);
-    let bid_quote_tokens_to_transfer = num_base_lots * bid_price;
-    let ask_price = market_price * ((100 + spread_margin) / 100);
-    let base_lots_per_base_unit: u64 = market_decoded_data.get_base_lots_per_base_unit().into();
-    let ask_base_tokens_to_transfer = num_base_lots * base_lots_per_base_unit;
-
-    return (bid_price, bid_quote_tokens_to_transfer, ask_price, ask_base_tokens_to_transfer);
-}
+#[allow(clippy::too_many_arguments)]
+fn get_market_data(
+    program_id: &Pubkey,
+    header_bytes: &[u8],
+    market_bytes: &[u8],
+    market_config_data: &MarketConfig,
+    position_data: &mut Position,
+    oracle: Option<&AccountInfo>,
+    bid_num_base_lots: u64,
+    ask_num_base_lots: u64,
+) -> Result<(Vec<LevelOrder>, u64, Vec<LevelOrder>, u64), ProgramError> {
+    let header = bytemuck::try_from_bytes::<MarketHeader>(header_bytes).map_err(|_| ProgramError::InvalidAccountData)?;
+    if header.base_params.mint_key != position_data.base_mint || header.quote_params.mint_key != position_data.quote_mint {
+        msg!("Base or quote mint doesn't match the market");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let market_decoded_data = load_with_dispatch(&header.market_size_params, market_bytes)?.inner;
+    let market_params = MarketParams::new(header, market_decoded_data);
+    if !market_config_data.is_tick_size_allowed(market_params.tick_size_in_quote_lots_per_base_unit) {
+        msg!("Market tick size is outside the market config bounds");
+        return Err(CodeError::TickSizeOutOfBounds.into());
+    }
+    let mut reference_price = get_reference_price(program_id, market_decoded_data, &market_params, position_data, oracle)?;
+    let market_price = reference_price.price_in_ticks;
+    let spread_mode = SpreadMode::try_from(position_data.spread_mode)?;
+    let spread_margin = position_data.spread_margin;
+    let ladder_config = position_data.ladder_config()?;
+
+    let (top_bid_price, top_ask_price) = top_of_ladder_prices(market_price, spread_mode, spread_margin, market_config_data.min_spread_in_ticks)?;
+
+    let bid_prices = ladder_config.bid_prices(top_bid_price)?;
+    let mut bid_orders = ladder_config.orders(bid_prices, bid_num_base_lots)?;
+    cap_order_sizes(&mut bid_orders, market_config_data.max_order_base_lots);
+    let mut bid_quote_lots = 0u64;
+    for order in bid_orders.iter() {
+        bid_quote_lots = bid_quote_lots
+            .checked_add(quote_lots_for_base_lots(&market_params, order.price_in_ticks, order.num_base_lots)?)
+            .ok_or(CodeError::ArithmeticOverflow)?;
+    }
 
-fn transfer_tokens_to_position<'a>(
-    side_enum: Side,
-    bid_quote_tokens_to_transfer: u64,
-    ask_base_tokens_to_transfer: u64,
-    trader: AccountInfo<'a>,
-    position_base_token_account: AccountInfo<'a>,
-    position_quote_token_account: AccountInfo<'a>,
-    trader_base_token_account: AccountInfo<'a>,
-    trader_quote_token_account: AccountInfo<'a>,
-    token_program: AccountInfo<'a>,
-) -> ProgramResult {
-    match side_enum {
-        Side::Bid => {
-            let transfer_quote_tokens_ixn = transfer(
-                token_program.key,
-                trader_quote_token_account.key,
-                position_quote_token_account.key,
-                trader.key,
-                &[trader.key],
-                bid_quote_tokens_to_transfer
-            )?;
-            invoke(
-                &transfer_quote_tokens_ixn,
-                &[trader_quote_token_account.clone(), position_quote_token_account.clone(), trader.clone(), token_program.clone()]
-            )?;
-        },
-        Side::Ask => {
-            let transfer_base_tokens_ixn = transfer(
-                token_program.key,
-                trader_base_token_account.key,
-                position_base_token_account.key,
-                trader.key,
-                &[trader.key],
-                ask_base_tokens_to_transfer
-            )?;
-            invoke(
-                &transfer_base_tokens_ixn,
-                &[trader_base_token_account.clone(), position_base_token_account.clone(), trader.clone(), token_program.clone()]
-            )?;
-        }
+    let ask_prices = ladder_config.ask_prices(top_ask_price)?;
+    let mut ask_orders = ladder_config.orders(ask_prices, ask_num_base_lots)?;
+    cap_order_sizes(&mut ask_orders, market_config_data.max_order_base_lots);
+    // only the capped ask size is moved to the position
+    let mut ask_base_lots = ask_orders.iter().try_fold(0u64, |total, order| total.checked_add(order.num_base_lots)).ok_or(CodeError::ArithmeticOverflow)?;
+
+    // the deposits make up the whole inventory of the new position: the base moved in for the asks, valued at the mid, and the quote moved in for the bids
+    apply_risk_limits(
+        &mut reference_price,
+        position_data,
+        ask_base_lots,
+        bid_quote_lots,
+        quote_lots_for_base_lots(&market_params, market_price, ask_base_lots)?,
+    );
+    // a suppressed side is neither quoted nor funded
+    if !reference_price.quote_bids {
+        bid_orders.iter_mut().for_each(|order| order.num_base_lots = 0);
+        bid_quote_lots = 0;
+    }
+    if !reference_price.quote_asks {
+        ask_orders.iter_mut().for_each(|order| order.num_base_lots = 0);
+        ask_base_lots = 0;
     }
+    let bid_quote_tokens_to_transfer = quote_lots_to_atoms(&market_params, bid_quote_lots)?;
+    let ask_base_tokens_to_transfer = base_lots_to_atoms(&market_params, ask_base_lots)?;
 
-    Ok(())
+    Ok((bid_orders, bid_quote_tokens_to_transfer, ask_orders, ask_base_tokens_to_transfer))
 }
 
-fn place_limit_order_on_phoenix<'a>(
-    side_enum: Side,
-    bid_price_in_ticks: u64,
-    ask_price_in_ticks: u64,
-    num_base_lots: u64,
+#[allow(clippy::too_many_arguments)]
+fn place_limit_orders_on_phoenix<'a>(
+    bid_orders: &[LevelOrder],
+    ask_orders: &[LevelOrder],
     client_order_id: u128,
     trader: AccountInfo<'a>,
     position: AccountInfo<'a>,
+    position_index: u16,
     position_bump: u8,
     market: AccountInfo<'a>,
     seat: AccountInfo<'a>,
@@ -359,44 +442,55 @@ fn place_limit_order_on_phoenix<'a>(
     phoenix_program: AccountInfo<'a>,
     phoenix_log_authority: AccountInfo<'a>,
 ) -> ProgramResult {
-    let order_packet = OrderPacket::new_limit_order(
-        side_enum,
-        match side_enum {
-            Side::Bid => bid_price_in_ticks,
-            Side::Ask => ask_price_in_ticks,
-        },
-        num_base_lots,
-        SelfTradeBehavior::CancelProvide,
-        None,
-        client_order_id,
-        false
-    );
-    let place_limit_order_ixn = create_new_order_instruction_with_custom_token_accounts(
-        market.key,
-        position.key,
-        position_base_token_account.key,
-        position_quote_token_account.key,
-        base_mint.key,
-        quote_mint.key,
-        &order_packet
-    );
-    invoke_signed(
-        &place_limit_order_ixn,
-        &[
-            phoenix_program.clone(),
-            phoenix_log_authority.clone(),
-            market.clone(),
-            position.clone(),
-            seat.clone(),
-            position_base_token_account.clone(),
-            position_quote_token_account.clone(),
-            base_vault.clone(),
-            quote_vault.clone(),
-            token_program.clone(),
-            phoenix_program.clone(),
-        ],
-        &[&[Position::SEED.as_bytes(), trader.key.as_ref(), market.key.as_ref(), &[position_bump]]],
-    )?;
+    let orders = bid_orders
+        .iter()
+        .map(|order| (Side::Bid, order))
+        .chain(ask_orders.iter().map(|order| (Side::Ask, order)));
+
+    // one order per ladder level
+    for (side, order) in orders {
+        if order.num_base_lots == 0 {
+            continue;
+        }
+
+        let order_packet = OrderPacket::new_limit_order(
+            side,
+            order.price_in_ticks,
+            order.num_base_lots,
+            SelfTradeBehavior::CancelProvide,
+            None,
+            client_order_id,
+            false
+        );
+        let place_limit_order_ixn = create_new_order_instruction_with_custom_token_accounts(
+            market.key,
+            position.key,
+            position_base_token_account.key,
+            position_quote_token_account.key,
+            base_mint.key,
+            quote_mint.key,
+            &order_packet
+        );
+        invoke_signed(
+            &place_limit_order_ixn,
+            &[
+                phoenix_program.clone(),
+                phoenix_log_authority.clone(),
+                market.clone(),
+                position.clone(),
+                seat.clone(),
+                position_base_token_account.clone(),
+                position_quote_token_account.clone(),
+                base_vault.clone(),
+                quote_vault.clone(),
+                token_program.clone(),
+                phoenix_program.clone(),
+            ],
+            &[&PositionSeeds::new(trader.key, market.key, position_index, position_bump).signer_seeds()],
+        )?;
+    }
+
+    Event::OrdersPlaced(OrdersPlaced::from_ladder(*position.key, client_order_id, bid_orders, ask_orders)).emit();
 
     Ok(())
-}
\ No newline at end of file
+}
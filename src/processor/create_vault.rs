@@ -0,0 +1,140 @@
+use phoenix::program::{load_with_dispatch, MarketHeader};
+use solana_program::{account_info::{AccountInfo, next_account_info}, entrypoint::ProgramResult, msg, program::{invoke, invoke_signed}, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey, rent::Rent, system_instruction::create_account, system_program, sysvar::{clock::Clock, Sysvar}};
+use spl_token::{instruction::initialize_mint2, state::{Account, Mint}};
+use core::mem::size_of;
+
+use crate::{accounting::seat_lots_total, error::CodeError, events::{Event, VaultCreated}, fees::{validate_fees, NAV_PER_SHARE_SCALE}, instruction::CreateVault, oracle::PriceSource, state::Position};
+use super::accounts::{check_phoenix_market, check_program_not_paused, load_config, load_position};
+
+
+pub fn process_create_vault(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: CreateVault,
+) -> ProgramResult {
+    msg!("Create vault");
+
+    if validate_fees(data.management_fee_bps, data.performance_fee_bps).is_err() {
+        msg!("Fees are above the allowed maximum");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let trader = next_account_info(account_info_iter)?;
+    let market = next_account_info(account_info_iter)?;
+    let position = next_account_info(account_info_iter)?;
+    let position_base_token_account = next_account_info(account_info_iter)?;
+    let position_quote_token_account = next_account_info(account_info_iter)?;
+    let share_mint = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let config = next_account_info(account_info_iter)?;
+
+    check_accounts(
+        trader,
+        position,
+        share_mint,
+        token_program,
+        system_program,
+    )?;
+    check_phoenix_market(market)?;
+    check_program_not_paused(&load_config(program_id, config)?)?;
+
+    let mut position_data = load_position(program_id, position, trader.key, market.key, data.position_index)?;
+    if position_data.is_vault() {
+        msg!("Position is already a vault");
+        return Err(CodeError::NotAllowedOnVault.into());
+    }
+    // shares are valued at the oracle price, a price the trader can move would let them mint or redeem at a false value
+    if PriceSource::try_from(position_data.price_source)? != PriceSource::Pyth {
+        msg!("Vault should be priced by a pyth oracle");
+        return Err(CodeError::InvalidOracle.into());
+    }
+    if position_base_token_account.key != &position_data.base_token_account || position_quote_token_account.key != &position_data.quote_token_account {
+        msg!("Invalid base token account or quote token account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // shares are priced from the first deposit, a position holding funds would have no shares to back them
+    let (base_lots, quote_lots) = {
+        let market_account_data = market.data.borrow();
+        let (header_bytes, market_bytes) = market_account_data.split_at(size_of::<MarketHeader>());
+        let header = bytemuck::try_from_bytes::<MarketHeader>(header_bytes).map_err(|_| ProgramError::InvalidAccountData)?;
+        let market_decoded_data = load_with_dispatch(&header.market_size_params, market_bytes)?.inner;
+        match market_decoded_data.get_trader_state(position.key) {
+            Some(trader_state) => seat_lots_total(trader_state)?,
+            None => (0, 0),
+        }
+    };
+    let base_tokens = Account::unpack(&position_base_token_account.data.borrow())?.amount;
+    let quote_tokens = Account::unpack(&position_quote_token_account.data.borrow())?.amount;
+    if base_lots != 0 || quote_lots != 0 || base_tokens != 0 || quote_tokens != 0 {
+        msg!("Position should hold no funds, withdraw them first");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let (share_mint_pubkey, share_mint_bump) = Pubkey::find_program_address(&[Position::SHARE_MINT_SEED.as_bytes(), position.key.as_ref()], program_id);
+    if share_mint.key != &share_mint_pubkey {
+        msg!("Invalid share mint account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let lamports = Rent::get()?.minimum_balance(Mint::LEN);
+    let create_share_mint_ixn = create_account(trader.key, share_mint.key, lamports, Mint::LEN.try_into().unwrap(), token_program.key);
+    invoke_signed(
+        &create_share_mint_ixn,
+        &[trader.clone(), share_mint.clone(), system_program.clone()],
+        &[&[Position::SHARE_MINT_SEED.as_bytes(), position.key.as_ref(), &[share_mint_bump]]]
+    )?;
+    let initialize_share_mint_ixn = initialize_mint2(token_program.key, share_mint.key, position.key, None, 0)?;
+    invoke(
+        &initialize_share_mint_ixn,
+        &[share_mint.clone(), token_program.clone()],
+    )?;
+
+    position_data.share_mint = share_mint_pubkey;
+    position_data.share_mint_bump = share_mint_bump;
+    position_data.fee_recipient = data.fee_recipient;
+    position_data.management_fee_bps = data.management_fee_bps;
+    position_data.performance_fee_bps = data.performance_fee_bps;
+    position_data.high_water_mark = NAV_PER_SHARE_SCALE;
+    position_data.last_fee_timestamp = Clock::get()?.unix_timestamp;
+    Position::pack(position_data, &mut position.data.borrow_mut())?;
+
+    Event::VaultCreated(VaultCreated {
+        position: *position.key,
+        share_mint: share_mint_pubkey,
+    }).emit();
+
+    Ok(())
+}
+
+fn check_accounts(
+    trader: &AccountInfo,
+    position: &AccountInfo,
+    share_mint: &AccountInfo,
+    token_program: &AccountInfo,
+    system_program: &AccountInfo,
+) -> ProgramResult {
+    if !trader.is_signer || !trader.is_writable {
+        msg!("Trader account should be signer and writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !position.is_writable || !share_mint.is_writable {
+        msg!("Position and share mint accounts should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if token_program.key != &spl_token::id() {
+        msg!("Invalid token program account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if system_program.key != &system_program::id() {
+        msg!("Invalid system program account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
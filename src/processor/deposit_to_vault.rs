@@ -0,0 +1,231 @@
+use phoenix::program::{load_with_dispatch, MarketHeader};
+use solana_program::{account_info::{AccountInfo, next_account_info}, entrypoint::ProgramResult, msg, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey, sysvar::{clock::Clock, Sysvar}};
+use spl_token::state::{Account, Mint};
+use core::mem::size_of;
+
+use crate::{accounting::{record_deposit, seat_lots_total}, error::CodeError, events::{Event, FeesCollected, VaultDeposit}, fees::accrue_vault_fees, instruction::DepositToVault, pricing::{base_lots_to_atoms, quote_lots_to_atoms, MarketParams}, state::{Position, PositionSeeds}, vault::{shares_for_deposit, value_in_quote_lots}};
+use super::{accounts::{check_fee_recipient_share_token_account, check_phoenix_market, check_phoenix_vaults, check_position_token_accounts, check_program_not_paused, load_config, load_vault}, funds::{deposit_funds_to_phoenix, mint_shares, transfer_tokens_to_position}, quoting::{get_vault_price, record_fills}};
+
+
+pub fn process_deposit_to_vault(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: DepositToVault,
+) -> ProgramResult {
+    msg!("Deposit to vault");
+
+    if data.base_lots_to_deposit == 0 && data.quote_lots_to_deposit == 0 {
+        msg!("At least one of base or quote should be non-zero");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let phoenix_program = next_account_info(account_info_iter)?;
+    let phoenix_log_authority = next_account_info(account_info_iter)?;
+    let market = next_account_info(account_info_iter)?;
+    let depositor = next_account_info(account_info_iter)?;
+    let seat = next_account_info(account_info_iter)?;
+    let position = next_account_info(account_info_iter)?;
+    let position_base_token_account = next_account_info(account_info_iter)?;
+    let position_quote_token_account = next_account_info(account_info_iter)?;
+    let base_vault = next_account_info(account_info_iter)?;
+    let quote_vault = next_account_info(account_info_iter)?;
+    let base_mint = next_account_info(account_info_iter)?;
+    let quote_mint = next_account_info(account_info_iter)?;
+    let depositor_base_token_account = next_account_info(account_info_iter)?;
+    let depositor_quote_token_account = next_account_info(account_info_iter)?;
+    let share_mint = next_account_info(account_info_iter)?;
+    let depositor_share_token_account = next_account_info(account_info_iter)?;
+    let fee_recipient_share_token_account = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let config = next_account_info(account_info_iter)?;
+    let oracle = account_info_iter.next();
+    let fill_history = account_info_iter.next();
+
+    check_accounts(
+        phoenix_program,
+        depositor,
+        position,
+        position_base_token_account,
+        position_quote_token_account,
+        base_mint,
+        quote_mint,
+        depositor_base_token_account,
+        depositor_quote_token_account,
+        share_mint,
+        depositor_share_token_account,
+        token_program,
+    )?;
+    check_phoenix_market(market)?;
+    check_program_not_paused(&load_config(program_id, config)?)?;
+    check_phoenix_vaults(market, base_mint, quote_mint, base_vault, quote_vault)?;
+
+    let mut position_data = load_vault(program_id, position, market.key, share_mint)?;
+    check_position_token_accounts(&position_data, position_base_token_account, position_quote_token_account, base_mint, quote_mint)?;
+    check_fee_recipient_share_token_account(&position_data, fee_recipient_share_token_account)?;
+
+    let share_supply = Mint::unpack(&share_mint.data.borrow())?.supply;
+    let position_base_tokens = Account::unpack(&position_base_token_account.data.borrow())?.amount;
+    let position_quote_tokens = Account::unpack(&position_quote_token_account.data.borrow())?.amount;
+
+    let (base_tokens_to_transfer, quote_tokens_to_transfer, nav, deposit_value) = {
+        let market_account_data = market.data.borrow();
+        let (header_bytes, market_bytes) = market_account_data.split_at(size_of::<MarketHeader>());
+        let header = bytemuck::try_from_bytes::<MarketHeader>(header_bytes).map_err(|_| ProgramError::InvalidAccountData)?;
+        let market_decoded_data = load_with_dispatch(&header.market_size_params, market_bytes)?.inner;
+        let market_params = MarketParams::new(header, market_decoded_data);
+        let (base_lots, quote_lots) = match market_decoded_data.get_trader_state(position.key) {
+            Some(trader_state) => seat_lots_total(trader_state)?,
+            None => (0, 0),
+        };
+        record_fills(program_id, &market_params, position, &mut position_data, fill_history, base_lots, quote_lots)?;
+
+        // the vault holds its seat's free and locked funds plus whatever sits in the position's token accounts
+        let base_lots_held = base_lots
+            .checked_add(position_base_tokens / market_params.base_atoms_per_base_lot.max(1))
+            .ok_or(CodeError::ArithmeticOverflow)?;
+        let quote_lots_held = quote_lots
+            .checked_add(position_quote_tokens / market_params.quote_atoms_per_quote_lot.max(1))
+            .ok_or(CodeError::ArithmeticOverflow)?;
+        let price_in_ticks = get_vault_price(program_id, &market_params, &position_data, oracle)?;
+        (
+            base_lots_to_atoms(&market_params, data.base_lots_to_deposit)?,
+            quote_lots_to_atoms(&market_params, data.quote_lots_to_deposit)?,
+            value_in_quote_lots(&market_params, price_in_ticks, base_lots_held, quote_lots_held)?,
+            value_in_quote_lots(&market_params, price_in_ticks, data.base_lots_to_deposit, data.quote_lots_to_deposit)?,
+        )
+    };
+
+    // fees are minted before pricing the deposit so the depositor doesn't pay for the time before they joined
+    let fee_shares = accrue_vault_fees(&mut position_data, nav, share_supply, Clock::get()?.unix_timestamp)?;
+    let share_supply = share_supply.checked_add(fee_shares).ok_or(CodeError::ArithmeticOverflow)?;
+    let shares = shares_for_deposit(deposit_value, nav, share_supply)?;
+    if shares == 0 {
+        msg!("Deposit is too small to mint a share");
+        return Err(CodeError::InvalidShareAmount.into());
+    }
+    if shares < data.min_shares_out {
+        msg!("Deposit would mint fewer shares than requested");
+        return Err(CodeError::SlippageExceeded.into());
+    }
+
+    transfer_tokens_to_position(
+        quote_tokens_to_transfer,
+        base_tokens_to_transfer,
+        depositor.clone(),
+        position_base_token_account.clone(),
+        position_quote_token_account.clone(),
+        depositor_base_token_account.clone(),
+        depositor_quote_token_account.clone(),
+        token_program.clone()
+    )?;
+
+    let (trader, position_index, position_bump) = (position_data.trader, position_data.position_index, position_data.position_bump);
+    let position_seeds = PositionSeeds::new(&trader, market.key, position_index, position_bump);
+    let position_signer_seeds: &[&[u8]] = &position_seeds.signer_seeds();
+
+    deposit_funds_to_phoenix(
+        data.base_lots_to_deposit,
+        data.quote_lots_to_deposit,
+        phoenix_program,
+        phoenix_log_authority,
+        market,
+        position,
+        seat,
+        position_base_token_account,
+        position_quote_token_account,
+        base_vault,
+        quote_vault,
+        base_mint,
+        quote_mint,
+        token_program,
+        position_signer_seeds,
+    )?;
+
+    mint_shares(fee_shares, position, share_mint, fee_recipient_share_token_account, token_program, position_signer_seeds)?;
+    mint_shares(shares, position, share_mint, depositor_share_token_account, token_program, position_signer_seeds)?;
+
+    // deposited base enters the cost basis at the price the shares were minted at
+    let base_cost_in_quote_lots = deposit_value.saturating_sub(data.quote_lots_to_deposit);
+    record_deposit(&mut position_data, data.base_lots_to_deposit, data.quote_lots_to_deposit, base_cost_in_quote_lots)?;
+
+    if fee_shares > 0 {
+        Event::FeesCollected(FeesCollected {
+            position: *position.key,
+            fee_recipient: position_data.fee_recipient,
+            fee_shares,
+            nav_quote_lots: nav,
+            high_water_mark: position_data.high_water_mark,
+        }).emit();
+    }
+    Event::VaultDeposit(VaultDeposit {
+        position: *position.key,
+        depositor: *depositor.key,
+        base_lots: data.base_lots_to_deposit,
+        quote_lots: data.quote_lots_to_deposit,
+        shares,
+        nav_quote_lots: nav,
+    }).emit();
+
+    Position::pack(position_data, &mut position.data.borrow_mut())?;
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn check_accounts(
+    phoenix_program: &AccountInfo,
+    depositor: &AccountInfo,
+    position: &AccountInfo,
+    position_base_token_account: &AccountInfo,
+    position_quote_token_account: &AccountInfo,
+    base_mint: &AccountInfo,
+    quote_mint: &AccountInfo,
+    depositor_base_token_account: &AccountInfo,
+    depositor_quote_token_account: &AccountInfo,
+    share_mint: &AccountInfo,
+    depositor_share_token_account: &AccountInfo,
+    token_program: &AccountInfo,
+) -> ProgramResult {
+    if phoenix_program.key.to_string() != "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY" {
+        msg!("Invalid phoenix program account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !depositor.is_signer {
+        msg!("Depositor account should be signer");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !position.is_writable || !position_base_token_account.is_writable || !position_quote_token_account.is_writable {
+        msg!("Position, base token account and quote token account should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !share_mint.is_writable || !depositor_share_token_account.is_writable {
+        msg!("Share mint and depositor share token account should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if token_program.key != &spl_token::id() {
+        msg!("Invalid token program account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let depositor_base_token_account_data = Account::unpack(&depositor_base_token_account.data.borrow())?;
+    let depositor_quote_token_account_data = Account::unpack(&depositor_quote_token_account.data.borrow())?;
+    if depositor_base_token_account_data.owner != *depositor.key || depositor_base_token_account_data.mint != *base_mint.key {
+        msg!("Invalid depositor base token account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if depositor_quote_token_account_data.owner != *depositor.key || depositor_quote_token_account_data.mint != *quote_mint.key {
+        msg!("Invalid depositor quote token account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if Account::unpack(&depositor_share_token_account.data.borrow())?.mint != *share_mint.key {
+        msg!("Invalid depositor share token account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
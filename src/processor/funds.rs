@@ -0,0 +1,288 @@
+use phoenix::{program::{create_deposit_funds_instruction_with_custom_token_accounts, create_withdraw_funds_with_custom_amounts_instruction_with_custom_token_accounts, deposit::DepositParams, withdraw::WithdrawParams, CancelOrderParams, ReduceOrderParams}, quantities::WrapperU64, state::{OrderPacket, Side}};
+use phoenix::state::markets::{FIFOOrderId, FIFORestingOrder, Market};
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, msg, program::{invoke, invoke_signed}, program_error::ProgramError, pubkey::Pubkey};
+use spl_token::instruction::{mint_to, transfer};
+
+use crate::{accounting::seat_lots_total, error::CodeError, pricing::{base_lots_for_quote_lots, quote_lots_locked_by_bid, MarketParams}};
+
+
+/// Transfers base and quote tokens from the trader's token accounts to the position's token accounts. Zero amounts are skipped.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn transfer_tokens_to_position<'a>(
+    bid_quote_tokens_to_transfer: u64,
+    ask_base_tokens_to_transfer: u64,
+    trader: AccountInfo<'a>,
+    position_base_token_account: AccountInfo<'a>,
+    position_quote_token_account: AccountInfo<'a>,
+    trader_base_token_account: AccountInfo<'a>,
+    trader_quote_token_account: AccountInfo<'a>,
+    token_program: AccountInfo<'a>,
+) -> ProgramResult {
+    if bid_quote_tokens_to_transfer > 0 {
+        let transfer_quote_tokens_ixn = transfer(
+            token_program.key,
+            trader_quote_token_account.key,
+            position_quote_token_account.key,
+            trader.key,
+            &[trader.key],
+            bid_quote_tokens_to_transfer
+        )?;
+        invoke(
+            &transfer_quote_tokens_ixn,
+            &[trader_quote_token_account.clone(), position_quote_token_account.clone(), trader.clone(), token_program.clone()]
+        )?;
+    }
+
+    if ask_base_tokens_to_transfer > 0 {
+        let transfer_base_tokens_ixn = transfer(
+            token_program.key,
+            trader_base_token_account.key,
+            position_base_token_account.key,
+            trader.key,
+            &[trader.key],
+            ask_base_tokens_to_transfer
+        )?;
+        invoke(
+            &transfer_base_tokens_ixn,
+            &[trader_base_token_account.clone(), position_base_token_account.clone(), trader.clone(), token_program.clone()]
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Deposits base and quote lots from the position's token accounts into the position's seat as free funds.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn deposit_funds_to_phoenix<'a>(
+    base_lots_to_deposit: u64,
+    quote_lots_to_deposit: u64,
+    phoenix_program: &AccountInfo<'a>,
+    phoenix_log_authority: &AccountInfo<'a>,
+    market: &AccountInfo<'a>,
+    position: &AccountInfo<'a>,
+    seat: &AccountInfo<'a>,
+    position_base_token_account: &AccountInfo<'a>,
+    position_quote_token_account: &AccountInfo<'a>,
+    base_vault: &AccountInfo<'a>,
+    quote_vault: &AccountInfo<'a>,
+    base_mint: &AccountInfo<'a>,
+    quote_mint: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    position_signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let deposit_funds_ixn = create_deposit_funds_instruction_with_custom_token_accounts(
+        market.key,
+        position.key,
+        seat.key,
+        position_base_token_account.key,
+        position_quote_token_account.key,
+        base_mint.key,
+        quote_mint.key,
+        &DepositParams {
+            quote_lots_to_deposit,
+            base_lots_to_deposit,
+        },
+    );
+    invoke_signed(
+        &deposit_funds_ixn,
+        &[
+            phoenix_program.clone(),
+            phoenix_log_authority.clone(),
+            market.clone(),
+            position.clone(),
+            seat.clone(),
+            position_base_token_account.clone(),
+            position_quote_token_account.clone(),
+            base_vault.clone(),
+            quote_vault.clone(),
+            token_program.clone(),
+        ],
+        &[position_signer_seeds],
+    )
+}
+
+/// Withdraws base and quote lots from the position's seat to the position's token accounts.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn withdraw_funds_from_phoenix<'a>(
+    base_lots_to_withdraw: u64,
+    quote_lots_to_withdraw: u64,
+    phoenix_program: &AccountInfo<'a>,
+    phoenix_log_authority: &AccountInfo<'a>,
+    market: &AccountInfo<'a>,
+    position: &AccountInfo<'a>,
+    position_base_token_account: &AccountInfo<'a>,
+    position_quote_token_account: &AccountInfo<'a>,
+    base_vault: &AccountInfo<'a>,
+    quote_vault: &AccountInfo<'a>,
+    base_mint: &AccountInfo<'a>,
+    quote_mint: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    position_signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let withdraw_funds_ixn = create_withdraw_funds_with_custom_amounts_instruction_with_custom_token_accounts(
+        market.key,
+        position.key,
+        position_base_token_account.key,
+        position_quote_token_account.key,
+        base_mint.key,
+        quote_mint.key,
+        &WithdrawParams {
+            quote_lots_to_withdraw: Some(quote_lots_to_withdraw),
+            base_lots_to_withdraw: Some(base_lots_to_withdraw),
+        },
+    );
+    invoke_signed(
+        &withdraw_funds_ixn,
+        &[
+            phoenix_program.clone(),
+            phoenix_log_authority.clone(),
+            market.clone(),
+            position.clone(),
+            position_base_token_account.clone(),
+            position_quote_token_account.clone(),
+            base_vault.clone(),
+            quote_vault.clone(),
+            token_program.clone(),
+        ],
+        &[position_signer_seeds],
+    )
+}
+
+/// Transfers base and quote tokens from the position's token accounts to the trader's token accounts. Zero amounts are skipped.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn transfer_tokens_to_trader<'a>(
+    base_tokens_to_transfer: u64,
+    quote_tokens_to_transfer: u64,
+    position: &AccountInfo<'a>,
+    position_base_token_account: &AccountInfo<'a>,
+    position_quote_token_account: &AccountInfo<'a>,
+    trader_base_token_account: &AccountInfo<'a>,
+    trader_quote_token_account: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    position_signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    if base_tokens_to_transfer > 0 {
+        let transfer_base_tokens_ixn = transfer(
+            token_program.key,
+            position_base_token_account.key,
+            trader_base_token_account.key,
+            position.key,
+            &[position.key],
+            base_tokens_to_transfer
+        )?;
+        invoke_signed(
+            &transfer_base_tokens_ixn,
+            &[position_base_token_account.clone(), trader_base_token_account.clone(), position.clone(), token_program.clone()],
+            &[position_signer_seeds]
+        )?;
+    }
+
+    if quote_tokens_to_transfer > 0 {
+        let transfer_quote_tokens_ixn = transfer(
+            token_program.key,
+            position_quote_token_account.key,
+            trader_quote_token_account.key,
+            position.key,
+            &[position.key],
+            quote_tokens_to_transfer
+        )?;
+        invoke_signed(
+            &transfer_quote_tokens_ixn,
+            &[position_quote_token_account.clone(), trader_quote_token_account.clone(), position.clone(), token_program.clone()],
+            &[position_signer_seeds]
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Mints vault shares to a share token account, signed by the position as mint authority. Zero amounts are skipped.
+pub(crate) fn mint_shares<'a>(
+    shares: u64,
+    position: &AccountInfo<'a>,
+    share_mint: &AccountInfo<'a>,
+    share_token_account: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    position_signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    if shares == 0 {
+        return Ok(());
+    }
+    let mint_shares_ixn = mint_to(token_program.key, share_mint.key, share_token_account.key, position.key, &[], shares)?;
+    invoke_signed(
+        &mint_shares_ixn,
+        &[share_mint.clone(), share_token_account.clone(), position.clone(), token_program.clone()],
+        &[position_signer_seeds],
+    )
+}
+
+/// Picks the position's resting orders to reduce so that the seat holds at least the requested free funds.
+/// Asks release base and bids release quote. Orders farthest from the top of the book are reduced first.
+pub(crate) fn get_orders_to_reduce(
+    market_decoded_data: &dyn Market<Pubkey, FIFOOrderId, FIFORestingOrder, OrderPacket>,
+    market_params: &MarketParams,
+    position: &Pubkey,
+    base_lots_to_withdraw: u64,
+    quote_lots_to_withdraw: u64,
+) -> Result<Vec<ReduceOrderParams>, ProgramError> {
+    let trader_state = market_decoded_data.get_trader_state(position).ok_or(ProgramError::InvalidAccountData)?;
+    let (base_lots_total, quote_lots_total) = seat_lots_total(trader_state)?;
+    if base_lots_to_withdraw > base_lots_total || quote_lots_to_withdraw > quote_lots_total {
+        msg!("Position doesn't hold enough funds");
+        return Err(CodeError::InsufficientFunds.into());
+    }
+
+    let mut orders_to_reduce = vec![];
+    let trader_index = match market_decoded_data.get_trader_index(position) {
+        Some(trader_index) => trader_index as u64,
+        None => return Ok(orders_to_reduce),
+    };
+
+    let mut base_lots_needed = base_lots_to_withdraw.saturating_sub(trader_state.base_lots_free.as_u64());
+    let mut quote_lots_needed = quote_lots_to_withdraw.saturating_sub(trader_state.quote_lots_free.as_u64());
+
+    for side in [Side::Ask, Side::Bid] {
+        for (order_id, resting_order) in market_decoded_data.get_book(side).iter().rev() {
+            if resting_order.trader_index != trader_index {
+                continue;
+            }
+            let price_in_ticks = order_id.price_in_ticks.as_u64();
+            let order_size = resting_order.num_base_lots.as_u64();
+
+            let size = match side {
+                Side::Ask => {
+                    if base_lots_needed == 0 {
+                        break;
+                    }
+                    let size = order_size.min(base_lots_needed);
+                    base_lots_needed -= size;
+                    size
+                }
+                Side::Bid => {
+                    if quote_lots_needed == 0 {
+                        break;
+                    }
+                    // smallest reduction that releases the needed quote lots, or the whole order
+                    let mut size = base_lots_for_quote_lots(market_params, price_in_ticks, quote_lots_needed)?;
+                    if quote_lots_locked_by_bid(market_params, price_in_ticks, size)? < quote_lots_needed {
+                        size += 1;
+                    }
+                    let size = size.min(order_size);
+                    quote_lots_needed = quote_lots_needed.saturating_sub(quote_lots_locked_by_bid(market_params, price_in_ticks, size)?);
+                    size
+                }
+            };
+
+            orders_to_reduce.push(ReduceOrderParams {
+                base_params: CancelOrderParams {
+                    side,
+                    price_in_ticks,
+                    order_sequence_number: order_id.order_sequence_number,
+                },
+                size,
+            });
+        }
+    }
+
+    Ok(orders_to_reduce)
+}
@@ -0,0 +1,41 @@
+use phoenix::program::{load_with_dispatch, MarketHeader};
+use solana_program::{account_info::{AccountInfo, next_account_info}, entrypoint::ProgramResult, msg, program::set_return_data, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey};
+use core::mem::size_of;
+
+use crate::{accounting::{apply_fills, seat_lots_total, PositionStats}, pricing::MarketParams};
+use super::accounts::{check_phoenix_market, load_position};
+
+
+pub fn process_get_position_stats(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    position_index: u16,
+) -> ProgramResult {
+    msg!("Get position stats");
+
+    let account_info_iter = &mut accounts.iter();
+    let market = next_account_info(account_info_iter)?;
+    let trader = next_account_info(account_info_iter)?;
+    let position = next_account_info(account_info_iter)?;
+
+    check_phoenix_market(market)?;
+
+    // the position isn't written, fills since it was last touched are applied to the copy only
+    let mut position_data = load_position(program_id, position, trader.key, market.key, position_index)?;
+
+    let market_account_data = market.data.borrow();
+    let (header_bytes, market_bytes) = market_account_data.split_at(size_of::<MarketHeader>());
+    let header = bytemuck::try_from_bytes::<MarketHeader>(header_bytes).map_err(|_| ProgramError::InvalidAccountData)?;
+    let market_decoded_data = load_with_dispatch(&header.market_size_params, market_bytes)?.inner;
+    let market_params = MarketParams::new(header, market_decoded_data);
+    if let Some(trader_state) = market_decoded_data.get_trader_state(position.key) {
+        let (base_lots, quote_lots) = seat_lots_total(trader_state)?;
+        apply_fills(&mut position_data, base_lots, quote_lots)?;
+    }
+
+    let mut stats = [0; PositionStats::LEN];
+    PositionStats::pack(PositionStats::new(&market_params, &position_data)?, &mut stats)?;
+    set_return_data(&stats);
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use solana_program::{account_info::{AccountInfo, next_account_info}, entrypoint::ProgramResult, msg, program::invoke_signed, program_error::ProgramError, program_pack::{IsInitialized, Pack}, pubkey::Pubkey, rent::Rent, system_instruction::create_account, system_program, sysvar::Sysvar};
+
+use crate::{error::CodeError, instruction::InitializeConfig, state::Config};
+use super::params::validate_config_params;
+
+
+pub fn process_initialize_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: InitializeConfig,
+) -> ProgramResult {
+    msg!("Initialize config");
+
+    validate_config_params(&data.params)?;
+
+    let account_info_iter = &mut accounts.iter();
+    let admin = next_account_info(account_info_iter)?;
+    let config = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    check_accounts(
+        admin,
+        config,
+        system_program,
+    )?;
+
+    let (config_pubkey, config_bump) = Pubkey::find_program_address(&[Config::SEED.as_bytes()], program_id);
+    if config.key != &config_pubkey {
+        msg!("Invalid config account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let lamports = Rent::get()?.minimum_balance(Config::LEN);
+    let create_config_ixn = create_account(admin.key, config.key, lamports, Config::LEN.try_into().unwrap(), program_id);
+    invoke_signed(
+        &create_config_ixn,
+        &[admin.clone(), config.clone(), system_program.clone()],
+        &[&[Config::SEED.as_bytes(), &[config_bump]]]
+    )?;
+
+    let mut config_data_bytes = config.data.borrow_mut();
+    if Config::unpack_unchecked(&config_data_bytes)?.is_initialized() {
+        msg!("Config is already initialized");
+        return Err(CodeError::PositionIsAlreadyInitialized.into());
+    }
+
+    let mut config_data = Config {
+        is_initialized: true,
+        admin: *admin.key,
+        bump: config_bump,
+        is_paused: false,
+        min_spread_margin_bps: 0,
+        max_spread_margin_bps: 0,
+        max_num_levels: 0,
+        max_skew_bps: 0,
+    };
+    config_data.set_params(&data.params);
+    config_data.pack_into_slice(&mut config_data_bytes);
+
+    Ok(())
+}
+
+fn check_accounts(
+    admin: &AccountInfo,
+    config: &AccountInfo,
+    system_program: &AccountInfo,
+) -> ProgramResult {
+    if !admin.is_signer || !admin.is_writable {
+        msg!("Admin account should be signer and writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !config.is_writable {
+        msg!("Config account should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if system_program.key != &system_program::id() {
+        msg!("Invalid system program account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
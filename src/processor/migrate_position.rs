@@ -0,0 +1,108 @@
+use phoenix::program::MarketHeader;
+use solana_program::{account_info::{AccountInfo, next_account_info}, entrypoint::ProgramResult, msg, program::invoke, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey, rent::Rent, system_instruction::transfer, system_program, sysvar::Sysvar};
+use core::mem::size_of;
+
+use crate::state::{LegacyPosition, Position, PositionSeeds};
+use super::{accounts::check_phoenix_market, quoting::record_seat_funds_as_deposit};
+
+
+pub fn process_migrate_position(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    position_index: u16,
+) -> ProgramResult {
+    msg!("Migrate position");
+
+    let account_info_iter = &mut accounts.iter();
+    let trader = next_account_info(account_info_iter)?;
+    let market = next_account_info(account_info_iter)?;
+    let position = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    check_accounts(
+        program_id,
+        trader,
+        position,
+        system_program,
+    )?;
+    check_phoenix_market(market)?;
+
+    let (position_pubkey, position_bump) = PositionSeeds::find_address(program_id, trader.key, market.key, position_index);
+    if position.key != &position_pubkey {
+        msg!("Invalid position account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let mut position_data = match position.data_len() {
+        LegacyPosition::LEN if position_index == Position::LEGACY_POSITION_INDEX => Position::from(LegacyPosition::unpack(&position.data.borrow())?),
+        Position::LEN => Position::unpack(&position.data.borrow())?,
+        _ => {
+            msg!("Unknown position layout");
+            return Err(ProgramError::InvalidAccountData);
+        }
+    };
+    if position.data_len() == Position::LEN && position_data.version == Position::VERSION {
+        msg!("Position is already on the latest version");
+        return Ok(());
+    }
+
+    if position_data.version < 2 {
+        let (base_mint, quote_mint) = {
+            let market_account_data = market.data.borrow();
+            let header = bytemuck::try_from_bytes::<MarketHeader>(market_account_data.get(..size_of::<MarketHeader>()).ok_or(ProgramError::InvalidAccountData)?).map_err(|_| ProgramError::InvalidAccountData)?;
+            (header.base_params.mint_key, header.quote_params.mint_key)
+        };
+        let (base_token_account, base_token_account_bump) = Pubkey::find_program_address(&[Position::BASE_TOKEN_SEED.as_bytes(), position_pubkey.as_ref(), base_mint.as_ref()], program_id);
+        let (quote_token_account, quote_token_account_bump) = Pubkey::find_program_address(&[Position::QUOTE_TOKEN_SEED.as_bytes(), position_pubkey.as_ref(), quote_mint.as_ref()], program_id);
+        position_data.trader = *trader.key;
+        position_data.market = *market.key;
+        position_data.base_mint = base_mint;
+        position_data.quote_mint = quote_mint;
+        position_data.base_token_account = base_token_account;
+        position_data.quote_token_account = quote_token_account;
+        position_data.position_bump = position_bump;
+        position_data.base_token_account_bump = base_token_account_bump;
+        position_data.quote_token_account_bump = quote_token_account_bump;
+    }
+    if position_data.version < 3 {
+        // funds held before tracking started count as deposited at the last mid
+        record_seat_funds_as_deposit(market, position, &mut position_data)?;
+    }
+    position_data.version = Position::VERSION;
+
+    let lamports_needed = Rent::get()?.minimum_balance(Position::LEN).saturating_sub(position.lamports());
+    if lamports_needed > 0 {
+        invoke(
+            &transfer(trader.key, position.key, lamports_needed),
+            &[trader.clone(), position.clone(), system_program.clone()],
+        )?;
+    }
+    position.realloc(Position::LEN, true)?;
+    Position::pack(position_data, &mut position.data.borrow_mut())?;
+
+    Ok(())
+}
+
+fn check_accounts(
+    program_id: &Pubkey,
+    trader: &AccountInfo,
+    position: &AccountInfo,
+    system_program: &AccountInfo,
+) -> ProgramResult {
+    if !trader.is_signer || !trader.is_writable {
+        msg!("Trader account should be signer and writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !position.is_writable || position.owner != program_id {
+        msg!("Position account should be writable and owned by the program");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if system_program.key != &system_program::id() {
+        msg!("Invalid system program account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
@@ -1,7 +1,57 @@
 pub mod create_position;
 pub mod cancel_position;
 pub mod place_limit_orders_with_free_funds;
+pub mod requote;
+pub mod set_mock_oracle;
+pub mod add_funds;
+pub mod withdraw_funds;
+pub mod update_position;
+pub mod migrate_position;
+pub mod get_position_stats;
+pub mod create_fill_history;
+pub mod create_vault;
+pub mod deposit_to_vault;
+pub mod redeem_from_vault;
+pub mod set_keeper;
+pub mod cancel_all_orders;
+pub mod configure_crank;
+pub mod crank;
+pub mod pause_position;
+pub mod resume_position;
+pub mod initialize_config;
+pub mod update_config;
+pub mod set_program_pause;
+pub mod set_market_allowed;
+pub mod create_market_config;
+pub mod update_market_config;
+pub(crate) mod accounts;
+pub(crate) mod funds;
+pub(crate) mod params;
+pub(crate) mod quoting;
 
 pub use create_position::process_create_position;
 pub use cancel_position::process_cancel_position;
-pub use place_limit_orders_with_free_funds::process_place_limit_orders_with_free_funds;
\ No newline at end of file
+pub use place_limit_orders_with_free_funds::process_place_limit_orders_with_free_funds;
+pub use requote::process_requote;
+pub use set_mock_oracle::process_set_mock_oracle;
+pub use add_funds::process_add_funds;
+pub use withdraw_funds::process_withdraw_funds;
+pub use update_position::process_update_position;
+pub use migrate_position::process_migrate_position;
+pub use get_position_stats::process_get_position_stats;
+pub use create_fill_history::process_create_fill_history;
+pub use create_vault::process_create_vault;
+pub use deposit_to_vault::process_deposit_to_vault;
+pub use redeem_from_vault::process_redeem_from_vault;
+pub use set_keeper::process_set_keeper;
+pub use cancel_all_orders::process_cancel_all_orders;
+pub use configure_crank::process_configure_crank;
+pub use crank::process_crank;
+pub use pause_position::process_pause_position;
+pub use resume_position::process_resume_position;
+pub use initialize_config::process_initialize_config;
+pub use update_config::process_update_config;
+pub use set_program_pause::process_set_program_pause;
+pub use set_market_allowed::process_set_market_allowed;
+pub use create_market_config::process_create_market_config;
+pub use update_market_config::process_update_market_config;
\ No newline at end of file
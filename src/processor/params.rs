@@ -0,0 +1,94 @@
+use solana_program::{entrypoint::ProgramResult, msg, program_error::ProgramError};
+
+use crate::{error::CodeError, instruction::{ConfigParams, MarketConfigParams, PositionParams}, oracle::PriceSource, pricing::{validate_skew, validate_spread, EmptyBookPolicy, LadderConfig, MidModel, SpreadMode, BPS_DENOMINATOR}, state::Config};
+
+
+/// Checks the strategy parameters of a position before they are written to the position account.
+pub(crate) fn validate_position_params(params: &PositionParams) -> ProgramResult {
+    let spread_mode = SpreadMode::try_from(params.spread_mode)?;
+    if validate_spread(spread_mode, params.spread_margin).is_err() {
+        msg!("Invalid spread margin");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    if LadderConfig::new(params.num_levels, params.level_spacing_mode, params.level_spacing, params.size_profile, params.size_profile_param).is_err() {
+        msg!("Invalid ladder config");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    if validate_skew(params.target_base_ratio_bps, params.skew_bps).is_err() {
+        msg!("Invalid inventory skew");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let price_source = PriceSource::try_from(params.price_source)?;
+    let falls_back_to_oracle = price_source == PriceSource::OrderBook
+        && EmptyBookPolicy::try_from(params.empty_book_policy)? == EmptyBookPolicy::Oracle;
+    if falls_back_to_oracle && PriceSource::try_from(params.fallback_price_source).map_or(true, |source| source == PriceSource::OrderBook) {
+        msg!("Invalid fallback price source");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let uses_oracle = price_source != PriceSource::OrderBook || falls_back_to_oracle;
+    if uses_oracle && (params.max_confidence_bps > BPS_DENOMINATOR || params.max_staleness_slots == 0) {
+        msg!("Invalid oracle config");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    if MidModel::try_from(params.mid_model)? == MidModel::Vwap && params.vwap_depth_base_lots == 0 {
+        msg!("Invalid vwap depth");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    Ok(())
+}
+
+/// Checks the strategy parameters against the bounds of the program config.
+pub(crate) fn check_params_bounds(config_data: &Config, params: &PositionParams) -> ProgramResult {
+    let spread_mode = SpreadMode::try_from(params.spread_mode)?;
+    if spread_mode == SpreadMode::Bps
+        && (params.spread_margin < config_data.min_spread_margin_bps || params.spread_margin > config_data.max_spread_margin_bps)
+    {
+        msg!("Spread margin is outside the config bounds");
+        return Err(CodeError::ParamsOutOfBounds.into());
+    }
+
+    if params.num_levels > config_data.max_num_levels {
+        msg!("Number of levels is above the config maximum");
+        return Err(CodeError::ParamsOutOfBounds.into());
+    }
+
+    if params.skew_bps > config_data.max_skew_bps {
+        msg!("Skew is above the config maximum");
+        return Err(CodeError::ParamsOutOfBounds.into());
+    }
+
+    Ok(())
+}
+
+/// Checks the bounds of the program config before they are written to the config account.
+pub(crate) fn validate_config_params(params: &ConfigParams) -> ProgramResult {
+    if params.min_spread_margin_bps > params.max_spread_margin_bps || params.max_spread_margin_bps > BPS_DENOMINATOR {
+        msg!("Invalid spread margin bounds");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    if params.max_num_levels == 0 {
+        msg!("Invalid number of levels bound");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    Ok(())
+}
+
+/// Checks the limits of a market config before they are written to the market config account.
+pub(crate) fn validate_market_config_params(params: &MarketConfigParams) -> ProgramResult {
+    if params.min_tick_size_in_quote_lots_per_base_unit == 0
+        || params.min_tick_size_in_quote_lots_per_base_unit > params.max_tick_size_in_quote_lots_per_base_unit
+    {
+        msg!("Invalid tick size bounds");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use solana_program::{account_info::{AccountInfo, next_account_info}, entrypoint::ProgramResult, msg, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey};
+
+use crate::{events::{Event, PositionPaused}, state::{Position, PositionSeeds}};
+use super::{accounts::{check_phoenix_market, load_position}, quoting::cancel_all_orders_with_free_funds};
+
+
+pub fn process_pause_position(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    position_index: u16,
+) -> ProgramResult {
+    msg!("Pause position");
+
+    let account_info_iter = &mut accounts.iter();
+    let phoenix_program = next_account_info(account_info_iter)?;
+    let phoenix_log_authority = next_account_info(account_info_iter)?;
+    let market = next_account_info(account_info_iter)?;
+    let trader = next_account_info(account_info_iter)?;
+    let position = next_account_info(account_info_iter)?;
+
+    check_accounts(
+        phoenix_program,
+        trader,
+        position,
+    )?;
+    check_phoenix_market(market)?;
+
+    let mut position_data = load_position(program_id, position, trader.key, market.key, position_index)?;
+    let position_bump = position_data.position_bump;
+
+    cancel_all_orders_with_free_funds(
+        phoenix_program,
+        phoenix_log_authority,
+        market,
+        position,
+        &PositionSeeds::new(trader.key, market.key, position_index, position_bump).signer_seeds(),
+    )?;
+
+    position_data.is_paused = true;
+    Position::pack(position_data, &mut position.data.borrow_mut())?;
+
+    Event::PositionPaused(PositionPaused { position: *position.key }).emit();
+
+    Ok(())
+}
+
+fn check_accounts(
+    phoenix_program: &AccountInfo,
+    trader: &AccountInfo,
+    position: &AccountInfo,
+) -> ProgramResult {
+    if phoenix_program.key.to_string() != "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY" {
+        msg!("Invalid phoenix program account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !trader.is_signer {
+        msg!("Trader account should be signer");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !position.is_writable {
+        msg!("Position account should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,460 @@
+use phoenix::{program::{create_cancel_all_order_with_free_funds_instruction, create_new_order_with_free_funds_instruction, create_reduce_order_with_free_funds_instruction, load_with_dispatch, MarketHeader, ReduceOrderParams}, quantities::{BaseLots, Ticks, WrapperU64}, state::{OrderPacket, SelfTradeBehavior, Side}};
+use phoenix::state::markets::{FIFOOrderId, FIFORestingOrder, Market};
+use solana_program::{account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult, msg, program::invoke_signed, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey, sysvar::Sysvar};
+use core::mem::size_of;
+
+use crate::{accounting::{apply_fills, price_in_ticks, record_deposit, seat_lots_total}, error::CodeError, events::{Event, OrdersCancelled, OrdersPlaced}, oracle::{OraclePrice, PriceSource}, pricing::{base_lots_for_quote_lots, book_mid_price_in_ticks, cap_order_sizes, quote_lots_for_base_lots, skewed_mid_price_in_ticks, top_of_ladder_prices, EmptyBookPolicy, EmptyBookPrice, LevelOrder, MarketParams, MidModel, ReferencePrice, SpreadMode}, state::{FillEntry, FillHistory, MarketConfig, Position}};
+use super::accounts::load_fill_history;
+
+
+/// Computes the ladder a position should post from its free funds in the seat, within the market config's limits.
+pub(crate) fn get_free_funds_orders(
+    program_id: &Pubkey,
+    market: &AccountInfo,
+    market_config_data: &MarketConfig,
+    position: &AccountInfo,
+    position_data: &mut Position,
+    oracle: Option<&AccountInfo>,
+    fill_history: Option<&AccountInfo>,
+) -> Result<(Vec<LevelOrder>, Vec<LevelOrder>), ProgramError> {
+    let spread_mode = SpreadMode::try_from(position_data.spread_mode)?;
+    let spread_margin = position_data.spread_margin;
+    let ladder_config = position_data.ladder_config()?;
+
+    let market_account_data = market.data.borrow();
+    let (header_bytes, market_bytes) = market_account_data.split_at(size_of::<MarketHeader>());
+    let header = bytemuck::try_from_bytes::<MarketHeader>(header_bytes).map_err(|_| ProgramError::InvalidAccountData)?;
+    let market_decoded_data = load_with_dispatch(&header.market_size_params, market_bytes)?.inner;
+    let market_params = MarketParams::new(header, market_decoded_data);
+    if !market_config_data.is_tick_size_allowed(market_params.tick_size_in_quote_lots_per_base_unit) {
+        msg!("Market tick size is outside the market config bounds");
+        return Err(CodeError::TickSizeOutOfBounds.into());
+    }
+
+    let trader_state = market_decoded_data.get_trader_state(position.key).ok_or(ProgramError::InvalidAccountData)?;
+
+    let (base_lots, quote_lots) = seat_lots_total(trader_state)?;
+    record_fills(program_id, &market_params, position, position_data, fill_history, base_lots, quote_lots)?;
+
+    let mut reference_price = get_reference_price(program_id, market_decoded_data, &market_params, position_data, oracle)?;
+    let mid_price = reference_price.price_in_ticks;
+    let base_value_in_quote_lots = quote_lots_for_base_lots(&market_params, mid_price, base_lots)?;
+    apply_risk_limits(&mut reference_price, position_data, base_lots, quote_lots, base_value_in_quote_lots);
+
+    // lean the quotes towards the target inventory, counting funds locked in resting orders too
+    let market_price = skewed_mid_price_in_ticks(
+        mid_price,
+        base_value_in_quote_lots,
+        quote_lots,
+        position_data.target_base_ratio_bps,
+        position_data.skew_bps,
+    )?;
+
+    let (top_bid_price, top_ask_price) = top_of_ladder_prices(market_price, spread_mode, spread_margin, market_config_data.min_spread_in_ticks)?;
+
+    // free quote lots are split across bid levels, then converted to base lots at each level's price
+    let bid_prices = ladder_config.bid_prices(top_bid_price)?;
+    let bid_quote_lots = match reference_price.quote_bids {
+        true => ladder_config.split_size(trader_state.quote_lots_free.as_u64())?,
+        false => vec![0; ladder_config.num_levels as usize],
+    };
+    let mut bid_orders = bid_prices
+        .into_iter()
+        .zip(bid_quote_lots)
+        .map(|(price_in_ticks, quote_lots)| Ok(LevelOrder {
+            price_in_ticks,
+            num_base_lots: base_lots_for_quote_lots(&market_params, price_in_ticks, quote_lots)?,
+        }))
+        .collect::<Result<Vec<LevelOrder>, ProgramError>>()?;
+
+    let ask_prices = ladder_config.ask_prices(top_ask_price)?;
+    let ask_base_lots = match reference_price.quote_asks {
+        true => trader_state.base_lots_free.as_u64(),
+        false => 0,
+    };
+    let mut ask_orders = ladder_config.orders(ask_prices, ask_base_lots)?;
+
+    cap_order_sizes(&mut bid_orders, market_config_data.max_order_base_lots);
+    cap_order_sizes(&mut ask_orders, market_config_data.max_order_base_lots);
+
+    Ok((bid_orders, ask_orders))
+}
+
+/// Stops quoting a side once the position's inventory reached one of its risk limits on that side.
+/// The other side still quotes and only reduces the exposure.
+pub(crate) fn apply_risk_limits(
+    reference_price: &mut ReferencePrice,
+    position_data: &Position,
+    base_lots: u64,
+    quote_lots: u64,
+    base_value_in_quote_lots: u64,
+) {
+    if reference_price.quote_bids && !position_data.can_increase_base(base_lots, base_value_in_quote_lots) {
+        msg!("Base inventory or notional limit reached, bids are suppressed");
+        reference_price.quote_bids = false;
+    }
+    if reference_price.quote_asks && !position_data.can_increase_quote(quote_lots) {
+        msg!("Quote inventory limit reached, asks are suppressed");
+        reference_price.quote_asks = false;
+    }
+}
+
+/// Price the position quotes around, read from the position's price source.
+/// Applies the position's empty book policy when the order book is empty or one-sided,
+/// and stores the mid on the position whenever it comes from a live source.
+pub(crate) fn get_reference_price(
+    program_id: &Pubkey,
+    market_decoded_data: &dyn Market<Pubkey, FIFOOrderId, FIFORestingOrder, OrderPacket>,
+    market_params: &MarketParams,
+    position_data: &mut Position,
+    oracle: Option<&AccountInfo>,
+) -> Result<ReferencePrice, ProgramError> {
+    let price_source = PriceSource::try_from(position_data.price_source)?;
+    if price_source != PriceSource::OrderBook {
+        let price_in_ticks = get_oracle_price(program_id, market_params, position_data, oracle, price_source)?;
+        position_data.last_mid_price_in_ticks = price_in_ticks;
+        return Ok(ReferencePrice::two_sided(price_in_ticks));
+    }
+
+    let mid_model = MidModel::try_from(position_data.mid_model)?;
+    let ladder = market_decoded_data.get_ladder(mid_model.book_levels());
+    if !ladder.bids.is_empty() && !ladder.asks.is_empty() {
+        let price_in_ticks = book_mid_price_in_ticks(&ladder.bids, &ladder.asks, mid_model, position_data.vwap_depth_base_lots)?;
+        position_data.last_mid_price_in_ticks = price_in_ticks;
+        return Ok(ReferencePrice::two_sided(price_in_ticks));
+    }
+
+    msg!("Order book is empty or one-sided");
+    let empty_book_policy = EmptyBookPolicy::try_from(position_data.empty_book_policy)?;
+    let best_bid = ladder.bids.first().map(|order| order.price_in_ticks);
+    let best_ask = ladder.asks.first().map(|order| order.price_in_ticks);
+    match empty_book_policy.empty_book_price(best_bid, best_ask, position_data.last_mid_price_in_ticks)? {
+        EmptyBookPrice::Reference(reference_price) => Ok(reference_price),
+        EmptyBookPrice::Oracle => {
+            let fallback_price_source = PriceSource::try_from(position_data.fallback_price_source)?;
+            let price_in_ticks = get_oracle_price(program_id, market_params, position_data, oracle, fallback_price_source)?;
+            position_data.last_mid_price_in_ticks = price_in_ticks;
+            Ok(ReferencePrice::two_sided(price_in_ticks))
+        }
+    }
+}
+
+/// Price a vault's shares are valued at, always read from its pyth oracle.
+/// The order book and the mock oracle are never used, the trader could move them to mint or redeem shares at a false price.
+pub(crate) fn get_vault_price(
+    program_id: &Pubkey,
+    market_params: &MarketParams,
+    position_data: &Position,
+    oracle: Option<&AccountInfo>,
+) -> Result<u64, ProgramError> {
+    if PriceSource::try_from(position_data.price_source)? != PriceSource::Pyth {
+        msg!("Vault should be priced by a pyth oracle");
+        return Err(CodeError::InvalidOracle.into());
+    }
+    get_oracle_price(program_id, market_params, position_data, oracle, PriceSource::Pyth)
+}
+
+fn get_oracle_price(
+    program_id: &Pubkey,
+    market_params: &MarketParams,
+    position_data: &Position,
+    oracle: Option<&AccountInfo>,
+    price_source: PriceSource,
+) -> Result<u64, ProgramError> {
+    let oracle = match oracle {
+        Some(oracle) if oracle.key == &position_data.oracle => oracle,
+        _ => {
+            msg!("Missing or invalid oracle account");
+            return Err(CodeError::InvalidOracle.into());
+        }
+    };
+    let oracle_price = OraclePrice::load(oracle, price_source, program_id)?;
+    oracle_price.validate(Clock::get()?.slot, position_data.max_staleness_slots, position_data.max_confidence_bps)?;
+    oracle_price.price_in_ticks(market_params)
+}
+
+/// Places one limit order per ladder level, funded by the free funds of the position's seat.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn place_limit_orders_with_free_funds<'a>(
+    phoenix_program: &AccountInfo<'a>,
+    phoenix_log_authority: &AccountInfo<'a>,
+    market: &AccountInfo<'a>,
+    position: &AccountInfo<'a>,
+    seat: &AccountInfo<'a>,
+    bid_orders: &[LevelOrder],
+    ask_orders: &[LevelOrder],
+    client_order_id: u128,
+    position_signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let orders = bid_orders
+        .iter()
+        .map(|order| (Side::Bid, order))
+        .chain(ask_orders.iter().map(|order| (Side::Ask, order)));
+
+    for (side, order) in orders {
+        if order.num_base_lots == 0 {
+            continue;
+        }
+
+        let order_packet = OrderPacket::Limit {
+            side,
+            price_in_ticks: Ticks::new(order.price_in_ticks),
+            num_base_lots: BaseLots::new(order.num_base_lots),
+            self_trade_behavior: SelfTradeBehavior::CancelProvide,
+            match_limit: None,
+            client_order_id,
+            use_only_deposited_funds: true,
+            last_valid_slot: None,
+            last_valid_unix_timestamp_in_seconds: None,
+            fail_silently_on_insufficient_funds: true,
+        };
+        let place_limit_order_ixn = create_new_order_with_free_funds_instruction(
+            market.key,
+            position.key,
+            &order_packet
+        );
+        invoke_signed(
+            &place_limit_order_ixn,
+            &[
+                phoenix_program.clone(),
+                phoenix_log_authority.clone(),
+                market.clone(),
+                position.clone(),
+                seat.clone(),
+                phoenix_program.clone(),
+            ],
+            &[position_signer_seeds],
+        )?;
+    }
+
+    Event::OrdersPlaced(OrdersPlaced::from_ladder(*position.key, client_order_id, bid_orders, ask_orders)).emit();
+
+    Ok(())
+}
+
+/// Cancels every resting order of the position. Released funds stay in the seat as free funds.
+pub(crate) fn cancel_all_orders_with_free_funds<'a>(
+    phoenix_program: &AccountInfo<'a>,
+    phoenix_log_authority: &AccountInfo<'a>,
+    market: &AccountInfo<'a>,
+    position: &AccountInfo<'a>,
+    position_signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let cancel_all_orders_ixn = create_cancel_all_order_with_free_funds_instruction(market.key, position.key);
+    invoke_signed(
+        &cancel_all_orders_ixn,
+        &[phoenix_program.clone(), phoenix_log_authority.clone(), market.clone(), position.clone(), phoenix_program.clone()],
+        &[position_signer_seeds],
+    )?;
+
+    Event::OrdersCancelled(OrdersCancelled { position: *position.key }).emit();
+
+    Ok(())
+}
+
+/// Reduces resting orders of the position. Released funds stay in the seat as free funds.
+pub(crate) fn reduce_orders_with_free_funds<'a>(
+    phoenix_program: &AccountInfo<'a>,
+    phoenix_log_authority: &AccountInfo<'a>,
+    market: &AccountInfo<'a>,
+    position: &AccountInfo<'a>,
+    orders: &[ReduceOrderParams],
+    position_signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    for order in orders {
+        let reduce_order_ixn = create_reduce_order_with_free_funds_instruction(market.key, position.key, order);
+        invoke_signed(
+            &reduce_order_ixn,
+            &[phoenix_program.clone(), phoenix_log_authority.clone(), market.clone(), position.clone(), phoenix_program.clone()],
+            &[position_signer_seeds],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Cancels every resting order of the position and places a new ladder from the released free funds.
+/// Does nothing while the position is paused.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn requote_with_free_funds<'a>(
+    program_id: &Pubkey,
+    phoenix_program: &AccountInfo<'a>,
+    phoenix_log_authority: &AccountInfo<'a>,
+    market: &AccountInfo<'a>,
+    market_config_data: &MarketConfig,
+    position: &AccountInfo<'a>,
+    seat: &AccountInfo<'a>,
+    oracle: Option<&AccountInfo<'a>>,
+    fill_history: Option<&AccountInfo<'a>>,
+    client_order_id: u128,
+    position_signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    if Position::unpack(&position.data.borrow())?.is_paused {
+        msg!("Position is paused, skipping requote");
+        return Ok(());
+    }
+
+    cancel_all_orders_with_free_funds(
+        phoenix_program,
+        phoenix_log_authority,
+        market,
+        position,
+        position_signer_seeds,
+    )?;
+
+    // the mid is computed after the cancel so that the position's own orders don't affect it
+    let mut position_data = Position::unpack(&position.data.borrow())?;
+    let (bid_orders, ask_orders) = get_free_funds_orders(program_id, market, market_config_data, position, &mut position_data, oracle, fill_history)?;
+    Position::pack(position_data, &mut position.data.borrow_mut())?;
+
+    place_limit_orders_with_free_funds(
+        phoenix_program,
+        phoenix_log_authority,
+        market,
+        position,
+        seat,
+        &bid_orders,
+        &ask_orders,
+        client_order_id,
+        position_signer_seeds,
+    )
+}
+
+/// Attributes the change of the seat balances to fills and appends the net fill to the position's fill history.
+/// Once a position keeps a fill history every call has to pass it, so that the snapshot never moves past an unrecorded fill.
+/// A fill history passed for a position not yet marked, created before the flag existed, is used and marks the position.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn record_fills(
+    program_id: &Pubkey,
+    market_params: &MarketParams,
+    position: &AccountInfo,
+    position_data: &mut Position,
+    fill_history: Option<&AccountInfo>,
+    base_lots_total: u64,
+    quote_lots_total: u64,
+) -> ProgramResult {
+    let fill_history = match fill_history {
+        Some(fill_history) => Some((fill_history, load_fill_history(program_id, fill_history, position.key)?)),
+        None if position_data.has_fill_history => {
+            msg!("Missing fill history account");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        None => None,
+    };
+
+    let net_fill = apply_fills(position_data, base_lots_total, quote_lots_total)?;
+    if let Some((fill_history, mut fill_history_data)) = fill_history {
+        position_data.has_fill_history = true;
+        if let Some(net_fill) = net_fill {
+            fill_history_data.push(FillEntry {
+                slot: Clock::get()?.slot,
+                side: match net_fill.side {
+                    Side::Bid => FillEntry::BID,
+                    Side::Ask => FillEntry::ASK,
+                },
+                price_in_ticks: price_in_ticks(market_params, net_fill.base_lots, net_fill.quote_lots)?,
+                base_lots: net_fill.base_lots,
+            });
+            FillHistory::pack(fill_history_data, &mut fill_history.data.borrow_mut())?;
+        }
+    }
+    Ok(())
+}
+
+/// Applies fills since the position was last touched, read from the position's seat balances.
+pub(crate) fn sync_position_fills(
+    program_id: &Pubkey,
+    market: &AccountInfo,
+    position: &AccountInfo,
+    position_data: &mut Position,
+    fill_history: Option<&AccountInfo>,
+) -> ProgramResult {
+    let market_account_data = market.data.borrow();
+    let (header_bytes, market_bytes) = market_account_data.split_at(size_of::<MarketHeader>());
+    let header = bytemuck::try_from_bytes::<MarketHeader>(header_bytes).map_err(|_| ProgramError::InvalidAccountData)?;
+    let market_decoded_data = load_with_dispatch(&header.market_size_params, market_bytes)?.inner;
+    let market_params = MarketParams::new(header, market_decoded_data);
+    let trader_state = market_decoded_data.get_trader_state(position.key).ok_or(ProgramError::InvalidAccountData)?;
+    let (base_lots, quote_lots) = seat_lots_total(trader_state)?;
+    record_fills(program_id, &market_params, position, position_data, fill_history, base_lots, quote_lots)
+}
+
+/// Records the position's seat balances as a deposit, the base entering the cost basis at the last mid price.
+/// Used when the position starts being tracked: on creation and when migrating positions created before tracking.
+/// Returns the recorded base and quote lots.
+pub(crate) fn record_seat_funds_as_deposit(
+    market: &AccountInfo,
+    position: &AccountInfo,
+    position_data: &mut Position,
+) -> Result<(u64, u64), ProgramError> {
+    let market_account_data = market.data.borrow();
+    let (header_bytes, market_bytes) = market_account_data.split_at(size_of::<MarketHeader>());
+    let header = bytemuck::try_from_bytes::<MarketHeader>(header_bytes).map_err(|_| ProgramError::InvalidAccountData)?;
+    let market_decoded_data = load_with_dispatch(&header.market_size_params, market_bytes)?.inner;
+    let market_params = MarketParams::new(header, market_decoded_data);
+    let (base_lots, quote_lots) = match market_decoded_data.get_trader_state(position.key) {
+        Some(trader_state) => seat_lots_total(trader_state)?,
+        None => (0, 0),
+    };
+    let base_cost_in_quote_lots = quote_lots_for_base_lots(&market_params, position_data.last_mid_price_in_ticks, base_lots)?;
+    record_deposit(position_data, base_lots, quote_lots, base_cost_in_quote_lots)?;
+    Ok((base_lots, quote_lots))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::LegacyPosition;
+
+    fn position(max_base_inventory_lots: u64, max_quote_inventory_lots: u64, max_notional_quote_lots: u64) -> Position {
+        let mut position = Position::from(LegacyPosition { is_initialized: true, spread_margin: 1 });
+        position.max_base_inventory_lots = max_base_inventory_lots;
+        position.max_quote_inventory_lots = max_quote_inventory_lots;
+        position.max_notional_quote_lots = max_notional_quote_lots;
+        position
+    }
+
+    fn limited(position: &Position, base_lots: u64, quote_lots: u64, base_value_in_quote_lots: u64) -> ReferencePrice {
+        let mut reference_price = ReferencePrice::two_sided(100);
+        apply_risk_limits(&mut reference_price, position, base_lots, quote_lots, base_value_in_quote_lots);
+        reference_price
+    }
+
+    #[test]
+    fn no_limits_quote_both_sides() {
+        assert_eq!(limited(&position(0, 0, 0), u64::MAX, u64::MAX, u64::MAX), ReferencePrice::two_sided(100));
+    }
+
+    #[test]
+    fn base_inventory_limit_only_suppresses_bids() {
+        let position = position(10, 0, 0);
+        assert_eq!(limited(&position, 9, 1_000, 1_000), ReferencePrice::two_sided(100));
+        assert_eq!(limited(&position, 10, 1_000, 1_000), ReferencePrice { price_in_ticks: 100, quote_bids: false, quote_asks: true });
+    }
+
+    #[test]
+    fn notional_limit_only_suppresses_bids() {
+        let position = position(0, 0, 500);
+        assert_eq!(limited(&position, 1_000, 1_000, 499), ReferencePrice::two_sided(100));
+        assert_eq!(limited(&position, 1_000, 1_000, 500), ReferencePrice { price_in_ticks: 100, quote_bids: false, quote_asks: true });
+    }
+
+    #[test]
+    fn quote_inventory_limit_only_suppresses_asks() {
+        let position = position(0, 1_000, 0);
+        assert_eq!(limited(&position, 1_000, 999, 1_000), ReferencePrice::two_sided(100));
+        assert_eq!(limited(&position, 1_000, 1_000, 1_000), ReferencePrice { price_in_ticks: 100, quote_bids: true, quote_asks: false });
+    }
+
+    #[test]
+    fn limits_on_both_sides_suppress_both() {
+        assert_eq!(limited(&position(10, 1_000, 0), 10, 1_000, 0), ReferencePrice { price_in_ticks: 100, quote_bids: false, quote_asks: false });
+    }
+
+    #[test]
+    fn limits_never_reenable_a_side() {
+        let mut reference_price = ReferencePrice { price_in_ticks: 100, quote_bids: false, quote_asks: false };
+        apply_risk_limits(&mut reference_price, &position(0, 0, 0), 0, 0, 0);
+        assert_eq!(reference_price, ReferencePrice { price_in_ticks: 100, quote_bids: false, quote_asks: false });
+    }
+}
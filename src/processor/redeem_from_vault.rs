@@ -0,0 +1,252 @@
+use phoenix::program::{load_with_dispatch, MarketHeader};
+use solana_program::{account_info::{AccountInfo, next_account_info}, entrypoint::ProgramResult, msg, program::invoke, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey, sysvar::{clock::Clock, Sysvar}};
+use spl_token::{instruction::burn, state::{Account, Mint}};
+use core::mem::size_of;
+
+use crate::{accounting::{record_withdrawal, seat_lots_total}, error::CodeError, events::{Event, FeesCollected, VaultRedeem}, fees::accrue_vault_fees, instruction::RedeemFromVault, pricing::{base_lots_to_atoms, quote_lots_to_atoms, MarketParams}, state::{Position, PositionSeeds}, vault::{amount_for_shares, value_in_quote_lots}};
+use super::{accounts::{check_fee_recipient_share_token_account, check_phoenix_market, check_phoenix_vaults, check_position_token_accounts, load_vault}, funds::{get_orders_to_reduce, mint_shares, transfer_tokens_to_trader, withdraw_funds_from_phoenix}, quoting::{get_vault_price, record_fills, reduce_orders_with_free_funds}};
+
+
+pub fn process_redeem_from_vault(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: RedeemFromVault,
+) -> ProgramResult {
+    msg!("Redeem from vault");
+
+    if data.shares == 0 {
+        msg!("Shares to redeem should be non-zero");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let phoenix_program = next_account_info(account_info_iter)?;
+    let phoenix_log_authority = next_account_info(account_info_iter)?;
+    let market = next_account_info(account_info_iter)?;
+    let owner = next_account_info(account_info_iter)?;
+    let position = next_account_info(account_info_iter)?;
+    let position_base_token_account = next_account_info(account_info_iter)?;
+    let position_quote_token_account = next_account_info(account_info_iter)?;
+    let base_vault = next_account_info(account_info_iter)?;
+    let quote_vault = next_account_info(account_info_iter)?;
+    let base_mint = next_account_info(account_info_iter)?;
+    let quote_mint = next_account_info(account_info_iter)?;
+    let owner_base_token_account = next_account_info(account_info_iter)?;
+    let owner_quote_token_account = next_account_info(account_info_iter)?;
+    let share_mint = next_account_info(account_info_iter)?;
+    let owner_share_token_account = next_account_info(account_info_iter)?;
+    let fee_recipient_share_token_account = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let oracle = account_info_iter.next();
+    let fill_history = account_info_iter.next();
+
+    check_accounts(
+        phoenix_program,
+        owner,
+        position,
+        position_base_token_account,
+        position_quote_token_account,
+        base_mint,
+        quote_mint,
+        owner_base_token_account,
+        owner_quote_token_account,
+        share_mint,
+        owner_share_token_account,
+        token_program,
+    )?;
+    check_phoenix_market(market)?;
+    check_phoenix_vaults(market, base_mint, quote_mint, base_vault, quote_vault)?;
+
+    let mut position_data = load_vault(program_id, position, market.key, share_mint)?;
+    check_position_token_accounts(&position_data, position_base_token_account, position_quote_token_account, base_mint, quote_mint)?;
+    check_fee_recipient_share_token_account(&position_data, fee_recipient_share_token_account)?;
+
+    let share_supply = Mint::unpack(&share_mint.data.borrow())?.supply;
+    let position_base_tokens = Account::unpack(&position_base_token_account.data.borrow())?.amount;
+    let position_quote_tokens = Account::unpack(&position_quote_token_account.data.borrow())?.amount;
+
+    let now = Clock::get()?.unix_timestamp;
+    let (
+        nav,
+        fee_shares,
+        orders_to_reduce,
+        base_lots_out,
+        quote_lots_out,
+        base_lots_from_seat,
+        quote_lots_from_seat,
+        base_tokens_to_transfer,
+        quote_tokens_to_transfer,
+    ) = {
+        let market_account_data = market.data.borrow();
+        let (header_bytes, market_bytes) = market_account_data.split_at(size_of::<MarketHeader>());
+        let header = bytemuck::try_from_bytes::<MarketHeader>(header_bytes).map_err(|_| ProgramError::InvalidAccountData)?;
+        let market_decoded_data = load_with_dispatch(&header.market_size_params, market_bytes)?.inner;
+        let market_params = MarketParams::new(header, market_decoded_data);
+        let (base_lots, quote_lots) = match market_decoded_data.get_trader_state(position.key) {
+            Some(trader_state) => seat_lots_total(trader_state)?,
+            None => (0, 0),
+        };
+        record_fills(program_id, &market_params, position, &mut position_data, fill_history, base_lots, quote_lots)?;
+
+        // redemptions are paid in kind from the token accounts first, then from the seat
+        let position_base_lots = position_base_tokens / market_params.base_atoms_per_base_lot.max(1);
+        let position_quote_lots = position_quote_tokens / market_params.quote_atoms_per_quote_lot.max(1);
+        let base_lots_held = base_lots.checked_add(position_base_lots).ok_or(CodeError::ArithmeticOverflow)?;
+        let quote_lots_held = quote_lots.checked_add(position_quote_lots).ok_or(CodeError::ArithmeticOverflow)?;
+
+        // fees are minted before the redemption so the owner pays their part up to now
+        let nav = if position_data.management_fee_bps > 0 || position_data.performance_fee_bps > 0 {
+            let price_in_ticks = get_vault_price(program_id, &market_params, &position_data, oracle)?;
+            value_in_quote_lots(&market_params, price_in_ticks, base_lots_held, quote_lots_held)?
+        } else {
+            0
+        };
+        let fee_shares = accrue_vault_fees(&mut position_data, nav, share_supply, now)?;
+        let share_supply = share_supply.checked_add(fee_shares).ok_or(CodeError::ArithmeticOverflow)?;
+
+        let base_lots_out = amount_for_shares(base_lots_held, data.shares, share_supply)?;
+        let quote_lots_out = amount_for_shares(quote_lots_held, data.shares, share_supply)?;
+        let base_lots_from_seat = base_lots_out.saturating_sub(position_base_lots);
+        let quote_lots_from_seat = quote_lots_out.saturating_sub(position_quote_lots);
+        (
+            nav,
+            fee_shares,
+            get_orders_to_reduce(market_decoded_data, &market_params, position.key, base_lots_from_seat, quote_lots_from_seat)?,
+            base_lots_out,
+            quote_lots_out,
+            base_lots_from_seat,
+            quote_lots_from_seat,
+            base_lots_to_atoms(&market_params, base_lots_out)?,
+            quote_lots_to_atoms(&market_params, quote_lots_out)?,
+        )
+    };
+
+    if base_lots_out < data.min_base_lots_out || quote_lots_out < data.min_quote_lots_out {
+        msg!("Redemption would pay less than requested");
+        return Err(CodeError::SlippageExceeded.into());
+    }
+
+    let (trader, position_index, position_bump) = (position_data.trader, position_data.position_index, position_data.position_bump);
+    let position_seeds = PositionSeeds::new(&trader, market.key, position_index, position_bump);
+    let position_signer_seeds: &[&[u8]] = &position_seeds.signer_seeds();
+
+    mint_shares(fee_shares, position, share_mint, fee_recipient_share_token_account, token_program, position_signer_seeds)?;
+    if fee_shares > 0 {
+        Event::FeesCollected(FeesCollected {
+            position: *position.key,
+            fee_recipient: position_data.fee_recipient,
+            fee_shares,
+            nav_quote_lots: nav,
+            high_water_mark: position_data.high_water_mark,
+        }).emit();
+    }
+
+    let burn_shares_ixn = burn(token_program.key, owner_share_token_account.key, share_mint.key, owner.key, &[], data.shares)?;
+    invoke(
+        &burn_shares_ixn,
+        &[owner_share_token_account.clone(), share_mint.clone(), owner.clone(), token_program.clone()],
+    )?;
+
+    if base_lots_from_seat > 0 || quote_lots_from_seat > 0 {
+        reduce_orders_with_free_funds(
+            phoenix_program,
+            phoenix_log_authority,
+            market,
+            position,
+            &orders_to_reduce,
+            position_signer_seeds,
+        )?;
+
+        withdraw_funds_from_phoenix(
+            base_lots_from_seat,
+            quote_lots_from_seat,
+            phoenix_program,
+            phoenix_log_authority,
+            market,
+            position,
+            position_base_token_account,
+            position_quote_token_account,
+            base_vault,
+            quote_vault,
+            base_mint,
+            quote_mint,
+            token_program,
+            position_signer_seeds,
+        )?;
+
+        record_withdrawal(&mut position_data, base_lots_from_seat, quote_lots_from_seat)?;
+    }
+    Position::pack(position_data, &mut position.data.borrow_mut())?;
+
+    transfer_tokens_to_trader(
+        base_tokens_to_transfer,
+        quote_tokens_to_transfer,
+        position,
+        position_base_token_account,
+        position_quote_token_account,
+        owner_base_token_account,
+        owner_quote_token_account,
+        token_program,
+        position_signer_seeds,
+    )?;
+
+    Event::VaultRedeem(VaultRedeem {
+        position: *position.key,
+        owner: *owner.key,
+        shares: data.shares,
+        base_lots: base_lots_out,
+        quote_lots: quote_lots_out,
+    }).emit();
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn check_accounts(
+    phoenix_program: &AccountInfo,
+    owner: &AccountInfo,
+    position: &AccountInfo,
+    position_base_token_account: &AccountInfo,
+    position_quote_token_account: &AccountInfo,
+    base_mint: &AccountInfo,
+    quote_mint: &AccountInfo,
+    owner_base_token_account: &AccountInfo,
+    owner_quote_token_account: &AccountInfo,
+    share_mint: &AccountInfo,
+    owner_share_token_account: &AccountInfo,
+    token_program: &AccountInfo,
+) -> ProgramResult {
+    if phoenix_program.key.to_string() != "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY" {
+        msg!("Invalid phoenix program account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !owner.is_signer {
+        msg!("Owner account should be signer");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !position.is_writable || !position_base_token_account.is_writable || !position_quote_token_account.is_writable {
+        msg!("Position, base token account and quote token account should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !share_mint.is_writable || !owner_share_token_account.is_writable {
+        msg!("Share mint and owner share token account should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if token_program.key != &spl_token::id() {
+        msg!("Invalid token program account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let owner_base_token_account_data = Account::unpack(&owner_base_token_account.data.borrow())?;
+    let owner_quote_token_account_data = Account::unpack(&owner_quote_token_account.data.borrow())?;
+    if owner_base_token_account_data.mint != *base_mint.key || owner_quote_token_account_data.mint != *quote_mint.key {
+        msg!("Invalid owner base or quote token account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
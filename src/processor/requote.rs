@@ -0,0 +1,72 @@
+use solana_program::{account_info::{AccountInfo, next_account_info}, entrypoint::ProgramResult, msg, program_error::ProgramError, pubkey::Pubkey};
+
+use crate::state::{Position, PositionSeeds};
+use super::{accounts::{check_order_placement_allowed, check_phoenix_market, load_config, load_market_config, load_position_for_authority}, quoting::requote_with_free_funds};
+
+
+pub fn process_requote(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    position_index: u16,
+    client_order_id: u128
+) -> ProgramResult {
+    msg!("Requote");
+
+    let account_info_iter = &mut accounts.iter();
+    let phoenix_program = next_account_info(account_info_iter)?;
+    let phoenix_log_authority = next_account_info(account_info_iter)?;
+    let market = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+    let seat = next_account_info(account_info_iter)?;
+    let position = next_account_info(account_info_iter)?;
+    let config = next_account_info(account_info_iter)?;
+    let market_config = next_account_info(account_info_iter)?;
+    let oracle = account_info_iter.next();
+    let fill_history = account_info_iter.next();
+
+    check_accounts(
+        phoenix_program,
+        position,
+    )?;
+    check_phoenix_market(market)?;
+
+    let config_data = load_config(program_id, config)?;
+    let market_config_data = load_market_config(program_id, market_config, market.key)?;
+    check_order_placement_allowed(&config_data, &market_config_data)?;
+
+    let position_data = load_position_for_authority(program_id, position, authority, market.key, position_index, Position::KEEPER_REQUOTE)?;
+    let (trader, position_bump) = (position_data.trader, position_data.position_bump);
+
+    requote_with_free_funds(
+        program_id,
+        phoenix_program,
+        phoenix_log_authority,
+        market,
+        &market_config_data,
+        position,
+        seat,
+        oracle,
+        fill_history,
+        client_order_id,
+        &PositionSeeds::new(&trader, market.key, position_index, position_bump).signer_seeds(),
+    )?;
+
+    Ok(())
+}
+
+fn check_accounts(
+    phoenix_program: &AccountInfo,
+    position: &AccountInfo,
+) -> ProgramResult {
+    if phoenix_program.key.to_string() != "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY" {
+        msg!("Invalid phoenix program account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !position.is_writable {
+        msg!("Position account should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use solana_program::{account_info::{AccountInfo, next_account_info}, entrypoint::ProgramResult, msg, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey};
+
+use crate::{events::{Event, PositionResumed}, state::{Position, PositionSeeds}};
+use super::{accounts::{check_order_placement_allowed, check_phoenix_market, load_config, load_market_config, load_position}, quoting::requote_with_free_funds};
+
+
+pub fn process_resume_position(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    position_index: u16,
+    client_order_id: u128,
+) -> ProgramResult {
+    msg!("Resume position");
+
+    let account_info_iter = &mut accounts.iter();
+    let phoenix_program = next_account_info(account_info_iter)?;
+    let phoenix_log_authority = next_account_info(account_info_iter)?;
+    let market = next_account_info(account_info_iter)?;
+    let trader = next_account_info(account_info_iter)?;
+    let seat = next_account_info(account_info_iter)?;
+    let position = next_account_info(account_info_iter)?;
+    let config = next_account_info(account_info_iter)?;
+    let market_config = next_account_info(account_info_iter)?;
+    let oracle = account_info_iter.next();
+    let fill_history = account_info_iter.next();
+
+    check_accounts(
+        phoenix_program,
+        trader,
+        position,
+    )?;
+    check_phoenix_market(market)?;
+
+    let config_data = load_config(program_id, config)?;
+    let market_config_data = load_market_config(program_id, market_config, market.key)?;
+    check_order_placement_allowed(&config_data, &market_config_data)?;
+
+    let mut position_data = load_position(program_id, position, trader.key, market.key, position_index)?;
+    let position_bump = position_data.position_bump;
+
+    position_data.is_paused = false;
+    Position::pack(position_data, &mut position.data.borrow_mut())?;
+
+    Event::PositionResumed(PositionResumed { position: *position.key }).emit();
+
+    requote_with_free_funds(
+        program_id,
+        phoenix_program,
+        phoenix_log_authority,
+        market,
+        &market_config_data,
+        position,
+        seat,
+        oracle,
+        fill_history,
+        client_order_id,
+        &PositionSeeds::new(trader.key, market.key, position_index, position_bump).signer_seeds(),
+    )?;
+
+    Ok(())
+}
+
+fn check_accounts(
+    phoenix_program: &AccountInfo,
+    trader: &AccountInfo,
+    position: &AccountInfo,
+) -> ProgramResult {
+    if phoenix_program.key.to_string() != "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY" {
+        msg!("Invalid phoenix program account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !trader.is_signer {
+        msg!("Trader account should be signer");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !position.is_writable {
+        msg!("Position account should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use solana_program::{account_info::{AccountInfo, next_account_info}, entrypoint::ProgramResult, msg, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey};
+
+use crate::{events::{Event, KeeperUpdated}, instruction::SetKeeper, state::Position};
+use super::accounts::{check_program_not_paused, load_config, load_position};
+
+
+pub fn process_set_keeper(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: SetKeeper,
+) -> ProgramResult {
+    msg!("Set keeper");
+
+    if data.permissions & !Position::KEEPER_PERMISSIONS != 0 {
+        msg!("Unknown keeper permissions");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let trader = next_account_info(account_info_iter)?;
+    let market = next_account_info(account_info_iter)?;
+    let position = next_account_info(account_info_iter)?;
+    let config = next_account_info(account_info_iter)?;
+
+    check_accounts(
+        trader,
+        position,
+    )?;
+
+    check_program_not_paused(&load_config(program_id, config)?)?;
+
+    let mut position_data = load_position(program_id, position, trader.key, market.key, data.position_index)?;
+
+    // a removed keeper keeps no permissions
+    position_data.keeper = data.keeper;
+    position_data.keeper_permissions = if data.keeper == Pubkey::default() { 0 } else { data.permissions };
+    let keeper_permissions = position_data.keeper_permissions;
+    Position::pack(position_data, &mut position.data.borrow_mut())?;
+
+    Event::KeeperUpdated(KeeperUpdated {
+        position: *position.key,
+        keeper: data.keeper,
+        permissions: keeper_permissions,
+    }).emit();
+
+    Ok(())
+}
+
+fn check_accounts(
+    trader: &AccountInfo,
+    position: &AccountInfo,
+) -> ProgramResult {
+    if !trader.is_signer {
+        msg!("Trader account should be signer");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !position.is_writable {
+        msg!("Position account should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use solana_program::{account_info::{AccountInfo, next_account_info}, entrypoint::ProgramResult, msg, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey};
+
+use crate::{instruction::SetMarketAllowed, state::MarketConfig};
+use super::accounts::{load_config_for_admin, load_market_config};
+
+
+pub fn process_set_market_allowed(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: SetMarketAllowed,
+) -> ProgramResult {
+    msg!("Set market allowed");
+
+    let account_info_iter = &mut accounts.iter();
+    let admin = next_account_info(account_info_iter)?;
+    let config = next_account_info(account_info_iter)?;
+    let market_config = next_account_info(account_info_iter)?;
+
+    if !market_config.is_writable {
+        msg!("Market config account should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    load_config_for_admin(program_id, config, admin)?;
+    let mut market_config_data = load_market_config(program_id, market_config, &data.market)?;
+    market_config_data.is_allowed = data.allowed;
+    MarketConfig::pack(market_config_data, &mut market_config.data.borrow_mut())?;
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use solana_program::{account_info::{AccountInfo, next_account_info}, clock::Clock, entrypoint::ProgramResult, msg, program::invoke_signed, program_error::ProgramError, program_pack::{IsInitialized, Pack}, pubkey::Pubkey, rent::Rent, system_instruction::create_account, system_program, sysvar::Sysvar};
+
+use crate::state::MockOracle;
+
+
+pub fn process_set_mock_oracle(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    price: i64,
+    conf: u64,
+    expo: i32,
+) -> ProgramResult {
+    msg!("Set mock oracle");
+
+    let account_info_iter = &mut accounts.iter();
+    let authority = next_account_info(account_info_iter)?;
+    let mock_oracle = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    check_accounts(
+        authority,
+        mock_oracle,
+        system_program,
+    )?;
+
+    let (mock_oracle_pubkey, mock_oracle_bump) = Pubkey::find_program_address(&[MockOracle::SEED.as_bytes(), authority.key.as_ref()], program_id);
+    if mock_oracle.key != &mock_oracle_pubkey {
+        msg!("Invalid mock oracle account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if mock_oracle.data_is_empty() {
+        let rent = Rent::get()?;
+        let create_mock_oracle_ixn = create_account(authority.key, mock_oracle.key, rent.minimum_balance(MockOracle::LEN), MockOracle::LEN as u64, program_id);
+        invoke_signed(
+            &create_mock_oracle_ixn,
+            &[authority.clone(), mock_oracle.clone(), system_program.clone()],
+            &[&[MockOracle::SEED.as_bytes(), authority.key.as_ref(), &[mock_oracle_bump]]]
+        )?;
+    }
+
+    let mut mock_oracle_data_bytes = mock_oracle.data.borrow_mut();
+    let mut mock_oracle_data = MockOracle::unpack_unchecked(&mock_oracle_data_bytes)?;
+    if mock_oracle_data.is_initialized() && mock_oracle_data.authority != *authority.key {
+        msg!("Invalid mock oracle authority");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    mock_oracle_data.is_initialized = true;
+    mock_oracle_data.authority = *authority.key;
+    mock_oracle_data.price = price;
+    mock_oracle_data.conf = conf;
+    mock_oracle_data.expo = expo;
+    mock_oracle_data.publish_slot = Clock::get()?.slot;
+    MockOracle::pack(mock_oracle_data, &mut mock_oracle_data_bytes)?;
+
+    Ok(())
+}
+
+fn check_accounts(
+    authority: &AccountInfo,
+    mock_oracle: &AccountInfo,
+    system_program: &AccountInfo,
+) -> ProgramResult {
+    if !authority.is_signer || !authority.is_writable {
+        msg!("Authority account should be signer and writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !mock_oracle.is_writable {
+        msg!("Mock oracle account should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if system_program.key != &system_program::id() {
+        msg!("Invalid system program account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use solana_program::{account_info::{AccountInfo, next_account_info}, entrypoint::ProgramResult, msg, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey};
+
+use crate::state::Config;
+use super::accounts::load_config_for_admin;
+
+
+pub fn process_set_program_pause(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    is_paused: bool,
+) -> ProgramResult {
+    msg!("Set program pause");
+
+    let account_info_iter = &mut accounts.iter();
+    let admin = next_account_info(account_info_iter)?;
+    let config = next_account_info(account_info_iter)?;
+
+    if !config.is_writable {
+        msg!("Config account should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let mut config_data = load_config_for_admin(program_id, config, admin)?;
+    config_data.is_paused = is_paused;
+    Config::pack(config_data, &mut config.data.borrow_mut())?;
+
+    Ok(())
+}
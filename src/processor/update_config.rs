@@ -0,0 +1,31 @@
+use solana_program::{account_info::{AccountInfo, next_account_info}, entrypoint::ProgramResult, msg, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey};
+
+use crate::{instruction::UpdateConfig, state::Config};
+use super::{accounts::load_config_for_admin, params::validate_config_params};
+
+
+pub fn process_update_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: UpdateConfig,
+) -> ProgramResult {
+    msg!("Update config");
+
+    validate_config_params(&data.params)?;
+
+    let account_info_iter = &mut accounts.iter();
+    let admin = next_account_info(account_info_iter)?;
+    let config = next_account_info(account_info_iter)?;
+
+    if !config.is_writable {
+        msg!("Config account should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let mut config_data = load_config_for_admin(program_id, config, admin)?;
+    config_data.admin = data.admin;
+    config_data.set_params(&data.params);
+    Config::pack(config_data, &mut config.data.borrow_mut())?;
+
+    Ok(())
+}
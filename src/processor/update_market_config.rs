@@ -0,0 +1,38 @@
+use solana_program::{account_info::{AccountInfo, next_account_info}, entrypoint::ProgramResult, msg, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey};
+
+use crate::{instruction::UpdateMarketConfig, state::MarketConfig};
+use super::{accounts::load_config_for_admin, params::validate_market_config_params};
+
+
+pub fn process_update_market_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: UpdateMarketConfig,
+) -> ProgramResult {
+    msg!("Update market config");
+
+    validate_market_config_params(&data.params)?;
+
+    let account_info_iter = &mut accounts.iter();
+    let admin = next_account_info(account_info_iter)?;
+    let config = next_account_info(account_info_iter)?;
+    let market_config = next_account_info(account_info_iter)?;
+
+    if !market_config.is_writable || market_config.owner != program_id {
+        msg!("Market config account should be writable and owned by the program");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    load_config_for_admin(program_id, config, admin)?;
+    let mut market_config_data = MarketConfig::unpack(&market_config.data.borrow())?;
+    let market_config_pubkey = Pubkey::create_program_address(&[MarketConfig::SEED.as_bytes(), market_config_data.market.as_ref(), &[market_config_data.bump]], program_id)?;
+    if market_config.key != &market_config_pubkey {
+        msg!("Invalid market config account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    market_config_data.set_params(&data.params);
+    MarketConfig::pack(market_config_data, &mut market_config.data.borrow_mut())?;
+
+    Ok(())
+}
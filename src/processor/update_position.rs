@@ -0,0 +1,103 @@
+use solana_program::{account_info::{AccountInfo, next_account_info}, entrypoint::ProgramResult, msg, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey};
+
+use crate::{error::CodeError, events::{Event, ParamsUpdated}, instruction::UpdatePosition, state::{Position, PositionSeeds}};
+use super::{accounts::{check_order_placement_allowed, check_phoenix_market, check_program_not_paused, load_config, load_market_config, load_position}, params::{check_params_bounds, validate_position_params}, quoting::{requote_with_free_funds, sync_position_fills}};
+
+
+pub fn process_update_position(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: UpdatePosition,
+) -> ProgramResult {
+    msg!("Update position");
+
+    validate_position_params(&data.params)?;
+
+    let account_info_iter = &mut accounts.iter();
+    let phoenix_program = next_account_info(account_info_iter)?;
+    let phoenix_log_authority = next_account_info(account_info_iter)?;
+    let market = next_account_info(account_info_iter)?;
+    let trader = next_account_info(account_info_iter)?;
+    let seat = next_account_info(account_info_iter)?;
+    let position = next_account_info(account_info_iter)?;
+    let config = next_account_info(account_info_iter)?;
+    let market_config = next_account_info(account_info_iter)?;
+    let oracle = account_info_iter.next();
+    let fill_history = account_info_iter.next();
+
+    check_accounts(
+        phoenix_program,
+        trader,
+        position,
+    )?;
+    check_phoenix_market(market)?;
+
+    let config_data = load_config(program_id, config)?;
+    let market_config_data = load_market_config(program_id, market_config, market.key)?;
+    check_program_not_paused(&config_data)?;
+    check_params_bounds(&config_data, &data.params)?;
+    if data.requote {
+        check_order_placement_allowed(&config_data, &market_config_data)?;
+    }
+
+    let mut position_data = load_position(program_id, position, trader.key, market.key, data.position_index)?;
+    let position_bump = position_data.position_bump;
+    if position_data.is_vault() && (
+        data.params.price_source != position_data.price_source
+        || data.params.fallback_price_source != position_data.fallback_price_source
+        || data.params.oracle != position_data.oracle
+    ) {
+        msg!("Vault price source and oracle can't be changed");
+        return Err(CodeError::NotAllowedOnVault.into());
+    }
+
+    sync_position_fills(program_id, market, position, &mut position_data, fill_history)?;
+    position_data.set_params(&data.params);
+    Position::pack(position_data, &mut position.data.borrow_mut())?;
+
+    Event::ParamsUpdated(ParamsUpdated {
+        position: *position.key,
+        params: data.params,
+    }).emit();
+
+    if data.requote {
+        requote_with_free_funds(
+            program_id,
+            phoenix_program,
+            phoenix_log_authority,
+            market,
+            &market_config_data,
+            position,
+            seat,
+            oracle,
+            fill_history,
+            data.client_order_id,
+            &PositionSeeds::new(trader.key, market.key, data.position_index, position_bump).signer_seeds(),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn check_accounts(
+    phoenix_program: &AccountInfo,
+    trader: &AccountInfo,
+    position: &AccountInfo,
+) -> ProgramResult {
+    if phoenix_program.key.to_string() != "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY" {
+        msg!("Invalid phoenix program account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !trader.is_signer {
+        msg!("Trader account should be signer");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !position.is_writable {
+        msg!("Position account should be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
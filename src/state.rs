@@ -1,16 +1,215 @@
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
-use solana_program::{program_error::ProgramError, program_pack::{IsInitialized, Pack, Sealed}};
+use solana_program::{program_error::ProgramError, program_pack::{IsInitialized, Pack, Sealed}, pubkey::Pubkey};
+
+use crate::{error::CodeError, instruction::{ConfigParams, MarketConfigParams, PositionParams}, oracle::PriceSource, pricing::{EmptyBookPolicy, LadderConfig, MidModel, SizeProfile, SpreadMode}};
 
 
 pub struct Position {
-    pub is_initialized: bool,
-    pub spread_margin: u64, // percentage of spread to put limit orders at from market price
+    pub is_initialized: bool, // stored as the account discriminator
+    pub version: u8, // layout version, fields added in later versions are zero in older accounts
+    pub position_index: u16, // index of the position among the trader's positions on the market
+    pub spread_mode: u8, // 1 for basis points, 2 for ticks
+    pub spread_margin: u64, // distance of limit orders from market price, in basis points or ticks depending on spread_mode
+    pub num_levels: u8, // number of limit orders per side
+    pub level_spacing_mode: u8, // 1 for basis points, 2 for ticks
+    pub level_spacing: u64, // distance between consecutive levels, in basis points or ticks depending on level_spacing_mode
+    pub size_profile: u8, // 1 for flat, 2 for linear, 3 for geometric
+    pub size_profile_param: u64, // size step (linear) or ratio (geometric) between levels, in basis points
+    pub target_base_ratio_bps: u64, // share of the inventory value the position aims to hold in base, in basis points
+    pub skew_bps: u64, // shift of the quotes' reference price when inventory is fully one-sided, in basis points of the mid. 0 disables skewing
+    pub price_source: u8, // 1 for order book, 2 for pyth, 3 for mock oracle
+    pub oracle: Pubkey, // oracle account, unused when price_source is order book
+    pub max_confidence_bps: u64, // widest accepted oracle confidence interval, in basis points of the price
+    pub max_staleness_slots: u64, // oldest accepted oracle price, in slots
+    pub mid_model: u8, // 1 for top of book, 2 for microprice, 3 for vwap. Used when price_source is order book
+    pub vwap_depth_base_lots: u64, // depth per side averaged by the vwap mid model
+    pub empty_book_policy: u8, // 1 to fail, 2 to use the last mid, 3 to use the oracle, 4 to quote only the populated side
+    pub fallback_price_source: u8, // 2 for pyth, 3 for mock oracle. Read by the oracle empty book policy when price_source is order book
+    pub last_mid_price_in_ticks: u64, // last mid price read from the order book or the oracle
+    // version 2
+    pub trader: Pubkey, // owner of the position
+    pub market: Pubkey, // phoenix market the position quotes on
+    pub base_mint: Pubkey, // base mint of the market
+    pub quote_mint: Pubkey, // quote mint of the market
+    pub base_token_account: Pubkey, // base token account of the position
+    pub quote_token_account: Pubkey, // quote token account of the position
+    pub position_bump: u8, // bump of the position address
+    pub base_token_account_bump: u8, // bump of the base token account address
+    pub quote_token_account_bump: u8, // bump of the quote token account address
+    // version 3
+    pub total_base_deposited_lots: u64, // base lots deposited into the seat over the life of the position
+    pub total_quote_deposited_lots: u64, // quote lots deposited into the seat over the life of the position
+    pub total_base_withdrawn_lots: u64, // base lots withdrawn from the seat over the life of the position
+    pub total_quote_withdrawn_lots: u64, // quote lots withdrawn from the seat over the life of the position
+    pub base_lots_bought: u64, // base lots bought through fills
+    pub base_lots_sold: u64, // base lots sold through fills
+    pub quote_lots_spent: u64, // quote lots paid for the base lots bought
+    pub quote_lots_received: u64, // quote lots received for the base lots sold
+    pub cost_basis_quote_lots: u64, // cost of the base lots held, at average cost. Deposited base is valued at the last mid price
+    pub realized_pnl_quote_lots: i64, // profit or loss realized by selling base above or below its average cost, in quote lots
+    pub last_base_lots_total: u64, // free and locked base lots of the seat when the position was last touched
+    pub last_quote_lots_total: u64, // free and locked quote lots of the seat when the position was last touched
+    // version 4
+    pub share_mint: Pubkey, // share mint of the vault, default when the position isn't a vault
+    pub share_mint_bump: u8, // bump of the share mint address
+    // version 5
+    pub fee_recipient: Pubkey, // owner of the share token account vault fees are minted to
+    pub management_fee_bps: u64, // yearly management fee, in basis points of the vault's value
+    pub performance_fee_bps: u64, // fee on gains above the high-water mark, in basis points
+    pub high_water_mark: u64, // highest nav per share fees were charged at, scaled by fees::NAV_PER_SHARE_SCALE
+    pub last_fee_timestamp: i64, // unix timestamp fees were last accrued at
+    // version 6
+    pub keeper: Pubkey, // account allowed to manage quotes on behalf of the trader, default when there is no keeper
+    pub keeper_permissions: u8, // bitmask of Position::KEEPER_* actions the keeper may take
+    // version 7
+    pub crank_interval_slots: u64, // slots between two public cranks, 0 when the position isn't open to the crank
+    pub crank_tip_lamports: u64, // lamports paid to the caller of each crank
+    pub crank_tip_budget_lamports: u64, // lamports left on the position account to pay crank tips
+    pub last_crank_slot: u64, // slot of the last public crank
+    // version 8
+    pub is_paused: bool, // resting orders are cancelled and requotes do nothing until resumed
+    // version 9
+    pub max_base_inventory_lots: u64, // base lots held from which bids are no longer posted, 0 for no limit
+    pub max_quote_inventory_lots: u64, // quote lots held from which asks are no longer posted, 0 for no limit
+    pub max_notional_quote_lots: u64, // value of the base held at mid from which bids are no longer posted, 0 for no limit
+    // version 10
+    pub has_fill_history: bool, // fills are appended to the fill history, which every instruction syncing fills then requires
 }
 
 impl Position {
     pub const SEED: &'static str = "position";
     pub const BASE_TOKEN_SEED: &'static str = "base";
     pub const QUOTE_TOKEN_SEED: &'static str = "quote";
+    pub const SHARE_MINT_SEED: &'static str = "shares";
+    pub const DISCRIMINATOR: [u8; 8] = *b"position";
+    pub const VERSION: u8 = 10;
+    pub const LEGACY_POSITION_INDEX: u16 = u16::MAX; // index of a migrated first release position, whose address has no index seed
+    pub const KEEPER_REQUOTE: u8 = 1 << 0; // place orders with free funds and requote
+    pub const KEEPER_CANCEL_ORDERS: u8 = 1 << 1; // cancel resting orders, funds stay in the seat
+    pub const KEEPER_PERMISSIONS: u8 = Position::KEEPER_REQUOTE | Position::KEEPER_CANCEL_ORDERS;
+    pub const RESERVED_LEN: usize = Position::LEN - (8 + 1 + 2 + 1 + 8 + 1 + 1 + 8 + 1 + 8 + 8 + 8 + 1 + 32 + 8 + 8 + 1 + 8 + 1 + 1 + 8 + 32 + 32 + 32 + 32 + 32 + 32 + 1 + 1 + 1 + 8 * 12 + 32 + 1 + 32 + 8 + 8 + 8 + 8 + 32 + 1 + 8 + 8 + 8 + 8 + 1 + 8 * 3 + 1);
+
+    pub fn new(position_index: u16, params: &PositionParams) -> Self {
+        Position {
+            is_initialized: true,
+            version: Position::VERSION,
+            position_index,
+            spread_mode: params.spread_mode,
+            spread_margin: params.spread_margin,
+            num_levels: params.num_levels,
+            level_spacing_mode: params.level_spacing_mode,
+            level_spacing: params.level_spacing,
+            size_profile: params.size_profile,
+            size_profile_param: params.size_profile_param,
+            target_base_ratio_bps: params.target_base_ratio_bps,
+            skew_bps: params.skew_bps,
+            price_source: params.price_source,
+            oracle: params.oracle,
+            max_confidence_bps: params.max_confidence_bps,
+            max_staleness_slots: params.max_staleness_slots,
+            mid_model: params.mid_model,
+            vwap_depth_base_lots: params.vwap_depth_base_lots,
+            empty_book_policy: params.empty_book_policy,
+            fallback_price_source: params.fallback_price_source,
+            last_mid_price_in_ticks: 0,
+            trader: Pubkey::default(),
+            market: Pubkey::default(),
+            base_mint: Pubkey::default(),
+            quote_mint: Pubkey::default(),
+            base_token_account: Pubkey::default(),
+            quote_token_account: Pubkey::default(),
+            position_bump: 0,
+            base_token_account_bump: 0,
+            quote_token_account_bump: 0,
+            total_base_deposited_lots: 0,
+            total_quote_deposited_lots: 0,
+            total_base_withdrawn_lots: 0,
+            total_quote_withdrawn_lots: 0,
+            base_lots_bought: 0,
+            base_lots_sold: 0,
+            quote_lots_spent: 0,
+            quote_lots_received: 0,
+            cost_basis_quote_lots: 0,
+            realized_pnl_quote_lots: 0,
+            last_base_lots_total: 0,
+            last_quote_lots_total: 0,
+            share_mint: Pubkey::default(),
+            share_mint_bump: 0,
+            fee_recipient: Pubkey::default(),
+            management_fee_bps: 0,
+            performance_fee_bps: 0,
+            high_water_mark: 0,
+            last_fee_timestamp: 0,
+            keeper: Pubkey::default(),
+            keeper_permissions: 0,
+            crank_interval_slots: 0,
+            crank_tip_lamports: 0,
+            crank_tip_budget_lamports: 0,
+            last_crank_slot: 0,
+            is_paused: false,
+            max_base_inventory_lots: params.max_base_inventory_lots,
+            max_quote_inventory_lots: params.max_quote_inventory_lots,
+            max_notional_quote_lots: params.max_notional_quote_lots,
+            has_fill_history: false,
+        }
+    }
+
+    /// Overwrites the strategy parameters, keeping the stored state.
+    pub fn set_params(&mut self, params: &PositionParams) {
+        self.spread_mode = params.spread_mode;
+        self.spread_margin = params.spread_margin;
+        self.num_levels = params.num_levels;
+        self.level_spacing_mode = params.level_spacing_mode;
+        self.level_spacing = params.level_spacing;
+        self.size_profile = params.size_profile;
+        self.size_profile_param = params.size_profile_param;
+        self.target_base_ratio_bps = params.target_base_ratio_bps;
+        self.skew_bps = params.skew_bps;
+        self.price_source = params.price_source;
+        self.oracle = params.oracle;
+        self.max_confidence_bps = params.max_confidence_bps;
+        self.max_staleness_slots = params.max_staleness_slots;
+        self.mid_model = params.mid_model;
+        self.vwap_depth_base_lots = params.vwap_depth_base_lots;
+        self.empty_book_policy = params.empty_book_policy;
+        self.fallback_price_source = params.fallback_price_source;
+        self.max_base_inventory_lots = params.max_base_inventory_lots;
+        self.max_quote_inventory_lots = params.max_quote_inventory_lots;
+        self.max_notional_quote_lots = params.max_notional_quote_lots;
+    }
+
+    /// Whether the position misses data that only MigratePosition can fill.
+    /// Fields added by the other versions default to zero and need no migration.
+    pub fn needs_migration(&self) -> bool {
+        self.version < 3
+    }
+
+    pub fn is_vault(&self) -> bool {
+        self.share_mint != Pubkey::default()
+    }
+
+    /// Whether `authority` may take the action: the trader always can, the keeper only with the permission.
+    pub fn is_authorized(&self, authority: &Pubkey, permission: u8) -> bool {
+        if *authority == self.trader {
+            return true;
+        }
+        self.keeper != Pubkey::default() && *authority == self.keeper && self.keeper_permissions & permission == permission
+    }
+
+    /// Whether bids may be posted: buying adds to the base inventory and to its notional.
+    pub fn can_increase_base(&self, base_lots: u64, base_value_in_quote_lots: u64) -> bool {
+        (self.max_base_inventory_lots == 0 || base_lots < self.max_base_inventory_lots)
+            && (self.max_notional_quote_lots == 0 || base_value_in_quote_lots < self.max_notional_quote_lots)
+    }
+
+    /// Whether asks may be posted: selling adds to the quote inventory.
+    pub fn can_increase_quote(&self, quote_lots: u64) -> bool {
+        self.max_quote_inventory_lots == 0 || quote_lots < self.max_quote_inventory_lots
+    }
+
+    pub fn ladder_config(&self) -> Result<LadderConfig, ProgramError> {
+        LadderConfig::new(self.num_levels, self.level_spacing_mode, self.level_spacing, self.size_profile, self.size_profile_param)
+    }
 }
 
 impl Sealed for Position {}
@@ -22,16 +221,349 @@ impl IsInitialized for Position {
 }
 
 impl Pack for Position {
-    const LEN: usize = 1 + 8;
+    const LEN: usize = 1024;
 
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
         let src = array_ref![src, 0, Position::LEN];
         let (
-            is_initialized,
+            discriminator,
+            version,
+            position_index,
+            spread_mode,
             spread_margin,
-        ) = array_refs![src, 1, 8];
+            num_levels,
+            level_spacing_mode,
+            level_spacing,
+            size_profile,
+            size_profile_param,
+            target_base_ratio_bps,
+            skew_bps,
+            price_source,
+            oracle,
+            max_confidence_bps,
+            max_staleness_slots,
+            mid_model,
+            vwap_depth_base_lots,
+            empty_book_policy,
+            fallback_price_source,
+            last_mid_price_in_ticks,
+            trader,
+            market,
+            base_mint,
+            quote_mint,
+            base_token_account,
+            quote_token_account,
+            position_bump,
+            base_token_account_bump,
+            quote_token_account_bump,
+            total_base_deposited_lots,
+            total_quote_deposited_lots,
+            total_base_withdrawn_lots,
+            total_quote_withdrawn_lots,
+            base_lots_bought,
+            base_lots_sold,
+            quote_lots_spent,
+            quote_lots_received,
+            cost_basis_quote_lots,
+            realized_pnl_quote_lots,
+            last_base_lots_total,
+            last_quote_lots_total,
+            share_mint,
+            share_mint_bump,
+            fee_recipient,
+            management_fee_bps,
+            performance_fee_bps,
+            high_water_mark,
+            last_fee_timestamp,
+            keeper,
+            keeper_permissions,
+            crank_interval_slots,
+            crank_tip_lamports,
+            crank_tip_budget_lamports,
+            last_crank_slot,
+            is_paused,
+            max_base_inventory_lots,
+            max_quote_inventory_lots,
+            max_notional_quote_lots,
+            has_fill_history,
+            _reserved,
+        ) = array_refs![src, 8, 1, 2, 1, 8, 1, 1, 8, 1, 8, 8, 8, 1, 32, 8, 8, 1, 8, 1, 1, 8, 32, 32, 32, 32, 32, 32, 1, 1, 1, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 32, 1, 32, 8, 8, 8, 8, 32, 1, 8, 8, 8, 8, 1, 8, 8, 8, 1, Position::RESERVED_LEN];
+
+        let is_initialized = match *discriminator {
+            Position::DISCRIMINATOR => true,
+            [0, 0, 0, 0, 0, 0, 0, 0] => false,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        if is_initialized && (version[0] == 0 || version[0] > Position::VERSION) {
+            return Err(ProgramError::InvalidAccountData);
+        }
 
         Ok(Position {
+            is_initialized,
+            version: version[0],
+            position_index: u16::from_le_bytes(*position_index),
+            spread_mode: spread_mode[0],
+            spread_margin: u64::from_le_bytes(*spread_margin),
+            num_levels: num_levels[0],
+            level_spacing_mode: level_spacing_mode[0],
+            level_spacing: u64::from_le_bytes(*level_spacing),
+            size_profile: size_profile[0],
+            size_profile_param: u64::from_le_bytes(*size_profile_param),
+            target_base_ratio_bps: u64::from_le_bytes(*target_base_ratio_bps),
+            skew_bps: u64::from_le_bytes(*skew_bps),
+            price_source: price_source[0],
+            oracle: Pubkey::new_from_array(*oracle),
+            max_confidence_bps: u64::from_le_bytes(*max_confidence_bps),
+            max_staleness_slots: u64::from_le_bytes(*max_staleness_slots),
+            mid_model: mid_model[0],
+            vwap_depth_base_lots: u64::from_le_bytes(*vwap_depth_base_lots),
+            empty_book_policy: empty_book_policy[0],
+            fallback_price_source: fallback_price_source[0],
+            last_mid_price_in_ticks: u64::from_le_bytes(*last_mid_price_in_ticks),
+            trader: Pubkey::new_from_array(*trader),
+            market: Pubkey::new_from_array(*market),
+            base_mint: Pubkey::new_from_array(*base_mint),
+            quote_mint: Pubkey::new_from_array(*quote_mint),
+            base_token_account: Pubkey::new_from_array(*base_token_account),
+            quote_token_account: Pubkey::new_from_array(*quote_token_account),
+            position_bump: position_bump[0],
+            base_token_account_bump: base_token_account_bump[0],
+            quote_token_account_bump: quote_token_account_bump[0],
+            total_base_deposited_lots: u64::from_le_bytes(*total_base_deposited_lots),
+            total_quote_deposited_lots: u64::from_le_bytes(*total_quote_deposited_lots),
+            total_base_withdrawn_lots: u64::from_le_bytes(*total_base_withdrawn_lots),
+            total_quote_withdrawn_lots: u64::from_le_bytes(*total_quote_withdrawn_lots),
+            base_lots_bought: u64::from_le_bytes(*base_lots_bought),
+            base_lots_sold: u64::from_le_bytes(*base_lots_sold),
+            quote_lots_spent: u64::from_le_bytes(*quote_lots_spent),
+            quote_lots_received: u64::from_le_bytes(*quote_lots_received),
+            cost_basis_quote_lots: u64::from_le_bytes(*cost_basis_quote_lots),
+            realized_pnl_quote_lots: i64::from_le_bytes(*realized_pnl_quote_lots),
+            last_base_lots_total: u64::from_le_bytes(*last_base_lots_total),
+            last_quote_lots_total: u64::from_le_bytes(*last_quote_lots_total),
+            share_mint: Pubkey::new_from_array(*share_mint),
+            share_mint_bump: share_mint_bump[0],
+            fee_recipient: Pubkey::new_from_array(*fee_recipient),
+            management_fee_bps: u64::from_le_bytes(*management_fee_bps),
+            performance_fee_bps: u64::from_le_bytes(*performance_fee_bps),
+            high_water_mark: u64::from_le_bytes(*high_water_mark),
+            last_fee_timestamp: i64::from_le_bytes(*last_fee_timestamp),
+            keeper: Pubkey::new_from_array(*keeper),
+            keeper_permissions: keeper_permissions[0],
+            crank_interval_slots: u64::from_le_bytes(*crank_interval_slots),
+            crank_tip_lamports: u64::from_le_bytes(*crank_tip_lamports),
+            crank_tip_budget_lamports: u64::from_le_bytes(*crank_tip_budget_lamports),
+            last_crank_slot: u64::from_le_bytes(*last_crank_slot),
+            is_paused: is_paused[0] != 0,
+            max_base_inventory_lots: u64::from_le_bytes(*max_base_inventory_lots),
+            max_quote_inventory_lots: u64::from_le_bytes(*max_quote_inventory_lots),
+            max_notional_quote_lots: u64::from_le_bytes(*max_notional_quote_lots),
+            has_fill_history: has_fill_history[0] != 0,
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, Position::LEN];
+        let (
+            discriminator,
+            version,
+            position_index,
+            spread_mode,
+            spread_margin,
+            num_levels,
+            level_spacing_mode,
+            level_spacing,
+            size_profile,
+            size_profile_param,
+            target_base_ratio_bps,
+            skew_bps,
+            price_source,
+            oracle,
+            max_confidence_bps,
+            max_staleness_slots,
+            mid_model,
+            vwap_depth_base_lots,
+            empty_book_policy,
+            fallback_price_source,
+            last_mid_price_in_ticks,
+            trader,
+            market,
+            base_mint,
+            quote_mint,
+            base_token_account,
+            quote_token_account,
+            position_bump,
+            base_token_account_bump,
+            quote_token_account_bump,
+            total_base_deposited_lots,
+            total_quote_deposited_lots,
+            total_base_withdrawn_lots,
+            total_quote_withdrawn_lots,
+            base_lots_bought,
+            base_lots_sold,
+            quote_lots_spent,
+            quote_lots_received,
+            cost_basis_quote_lots,
+            realized_pnl_quote_lots,
+            last_base_lots_total,
+            last_quote_lots_total,
+            share_mint,
+            share_mint_bump,
+            fee_recipient,
+            management_fee_bps,
+            performance_fee_bps,
+            high_water_mark,
+            last_fee_timestamp,
+            keeper,
+            keeper_permissions,
+            crank_interval_slots,
+            crank_tip_lamports,
+            crank_tip_budget_lamports,
+            last_crank_slot,
+            is_paused,
+            max_base_inventory_lots,
+            max_quote_inventory_lots,
+            max_notional_quote_lots,
+            has_fill_history,
+            reserved,
+        ) = mut_array_refs![dst, 8, 1, 2, 1, 8, 1, 1, 8, 1, 8, 8, 8, 1, 32, 8, 8, 1, 8, 1, 1, 8, 32, 32, 32, 32, 32, 32, 1, 1, 1, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 32, 1, 32, 8, 8, 8, 8, 32, 1, 8, 8, 8, 8, 1, 8, 8, 8, 1, Position::RESERVED_LEN];
+        match self.is_initialized {
+            true => *discriminator = Position::DISCRIMINATOR,
+            false => *discriminator = [0; 8],
+        };
+        version[0] = self.version;
+        *position_index = self.position_index.to_le_bytes();
+        spread_mode[0] = self.spread_mode;
+        *spread_margin = self.spread_margin.to_le_bytes();
+        num_levels[0] = self.num_levels;
+        level_spacing_mode[0] = self.level_spacing_mode;
+        *level_spacing = self.level_spacing.to_le_bytes();
+        size_profile[0] = self.size_profile;
+        *size_profile_param = self.size_profile_param.to_le_bytes();
+        *target_base_ratio_bps = self.target_base_ratio_bps.to_le_bytes();
+        *skew_bps = self.skew_bps.to_le_bytes();
+        price_source[0] = self.price_source;
+        *oracle = self.oracle.to_bytes();
+        *max_confidence_bps = self.max_confidence_bps.to_le_bytes();
+        *max_staleness_slots = self.max_staleness_slots.to_le_bytes();
+        mid_model[0] = self.mid_model;
+        *vwap_depth_base_lots = self.vwap_depth_base_lots.to_le_bytes();
+        empty_book_policy[0] = self.empty_book_policy;
+        fallback_price_source[0] = self.fallback_price_source;
+        *last_mid_price_in_ticks = self.last_mid_price_in_ticks.to_le_bytes();
+        *trader = self.trader.to_bytes();
+        *market = self.market.to_bytes();
+        *base_mint = self.base_mint.to_bytes();
+        *quote_mint = self.quote_mint.to_bytes();
+        *base_token_account = self.base_token_account.to_bytes();
+        *quote_token_account = self.quote_token_account.to_bytes();
+        position_bump[0] = self.position_bump;
+        base_token_account_bump[0] = self.base_token_account_bump;
+        quote_token_account_bump[0] = self.quote_token_account_bump;
+        *total_base_deposited_lots = self.total_base_deposited_lots.to_le_bytes();
+        *total_quote_deposited_lots = self.total_quote_deposited_lots.to_le_bytes();
+        *total_base_withdrawn_lots = self.total_base_withdrawn_lots.to_le_bytes();
+        *total_quote_withdrawn_lots = self.total_quote_withdrawn_lots.to_le_bytes();
+        *base_lots_bought = self.base_lots_bought.to_le_bytes();
+        *base_lots_sold = self.base_lots_sold.to_le_bytes();
+        *quote_lots_spent = self.quote_lots_spent.to_le_bytes();
+        *quote_lots_received = self.quote_lots_received.to_le_bytes();
+        *cost_basis_quote_lots = self.cost_basis_quote_lots.to_le_bytes();
+        *realized_pnl_quote_lots = self.realized_pnl_quote_lots.to_le_bytes();
+        *last_base_lots_total = self.last_base_lots_total.to_le_bytes();
+        *last_quote_lots_total = self.last_quote_lots_total.to_le_bytes();
+        *share_mint = self.share_mint.to_bytes();
+        share_mint_bump[0] = self.share_mint_bump;
+        *fee_recipient = self.fee_recipient.to_bytes();
+        *management_fee_bps = self.management_fee_bps.to_le_bytes();
+        *performance_fee_bps = self.performance_fee_bps.to_le_bytes();
+        *high_water_mark = self.high_water_mark.to_le_bytes();
+        *last_fee_timestamp = self.last_fee_timestamp.to_le_bytes();
+        *keeper = self.keeper.to_bytes();
+        keeper_permissions[0] = self.keeper_permissions;
+        *crank_interval_slots = self.crank_interval_slots.to_le_bytes();
+        *crank_tip_lamports = self.crank_tip_lamports.to_le_bytes();
+        *crank_tip_budget_lamports = self.crank_tip_budget_lamports.to_le_bytes();
+        *last_crank_slot = self.last_crank_slot.to_le_bytes();
+        is_paused[0] = self.is_paused as u8;
+        *max_base_inventory_lots = self.max_base_inventory_lots.to_le_bytes();
+        *max_quote_inventory_lots = self.max_quote_inventory_lots.to_le_bytes();
+        *max_notional_quote_lots = self.max_notional_quote_lots.to_le_bytes();
+        has_fill_history[0] = self.has_fill_history as u8;
+        *reserved = [0; Position::RESERVED_LEN];
+    }
+}
+
+
+/// Seeds of a position address, owned so that the signer seeds can be borrowed from them.
+/// Positions migrated from the first release have no position index seed.
+pub struct PositionSeeds {
+    trader: [u8; 32],
+    market: [u8; 32],
+    position_index: [u8; 2],
+    bump: [u8; 1],
+}
+
+impl PositionSeeds {
+    pub fn new(trader: &Pubkey, market: &Pubkey, position_index: u16, bump: u8) -> Self {
+        PositionSeeds {
+            trader: trader.to_bytes(),
+            market: market.to_bytes(),
+            position_index: position_index.to_le_bytes(),
+            bump: [bump],
+        }
+    }
+
+    /// Address and bump of the position of `trader` on `market` at `position_index`.
+    pub fn find_address(program_id: &Pubkey, trader: &Pubkey, market: &Pubkey, position_index: u16) -> (Pubkey, u8) {
+        let position_seeds = PositionSeeds::new(trader, market, position_index, 0);
+        Pubkey::find_program_address(&position_seeds.seeds(), program_id)
+    }
+
+    pub fn signer_seeds(&self) -> Vec<&[u8]> {
+        let mut seeds = self.seeds();
+        seeds.push(&self.bump);
+        seeds
+    }
+
+    fn seeds(&self) -> Vec<&[u8]> {
+        let mut seeds: Vec<&[u8]> = vec![Position::SEED.as_bytes(), &self.trader, &self.market];
+        if self.position_index != Position::LEGACY_POSITION_INDEX.to_le_bytes() {
+            seeds.push(&self.position_index);
+        }
+        seeds
+    }
+}
+
+
+/// Position layout of the first release, kept to migrate old accounts.
+/// Those accounts have no position index in their address seeds and migrate to `Position::LEGACY_POSITION_INDEX`.
+pub struct LegacyPosition {
+    pub is_initialized: bool,
+    pub spread_margin: u64, // percentage of the mid price to put limit orders at
+}
+
+impl Sealed for LegacyPosition {}
+
+impl IsInitialized for LegacyPosition {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for LegacyPosition {
+    // allocated as 8 + size_of::<LegacyPosition>(), only the first 9 bytes hold data
+    const LEN: usize = 24;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, LegacyPosition::LEN];
+        let (
+            is_initialized,
+            spread_margin,
+            _padding,
+        ) = array_refs![src, 1, 8, 15];
+
+        Ok(LegacyPosition {
             is_initialized: match is_initialized {
                 [0] => false,
                 [1] => true,
@@ -42,15 +574,708 @@ impl Pack for Position {
     }
 
     fn pack_into_slice(&self, dst: &mut [u8]) {
-        let dst = array_mut_ref![dst, 0, Position::LEN];
+        let dst = array_mut_ref![dst, 0, LegacyPosition::LEN];
         let (
             is_initialized,
             spread_margin,
-        ) = mut_array_refs![dst, 1, 8];
+            padding,
+        ) = mut_array_refs![dst, 1, 8, 15];
         match self.is_initialized {
             true => is_initialized[0] = 1,
             false => is_initialized[0] = 0,
         };
         *spread_margin = self.spread_margin.to_le_bytes();
+        *padding = [0; 15];
+    }
+}
+
+
+impl From<LegacyPosition> for Position {
+    /// Keeps the spread of a first release position and fills the strategy fields it didn't have with a single
+    /// flat level quoted around the top of book. The version is left at 1 so that migration fills the rest.
+    fn from(legacy: LegacyPosition) -> Self {
+        Position {
+            is_initialized: legacy.is_initialized,
+            version: 1,
+            position_index: Position::LEGACY_POSITION_INDEX,
+            spread_mode: SpreadMode::Bps as u8,
+            spread_margin: legacy.spread_margin.saturating_mul(100),
+            num_levels: 1,
+            level_spacing_mode: SpreadMode::Bps as u8,
+            level_spacing: 0,
+            size_profile: SizeProfile::Flat as u8,
+            size_profile_param: 0,
+            target_base_ratio_bps: 0,
+            skew_bps: 0,
+            price_source: PriceSource::OrderBook as u8,
+            oracle: Pubkey::default(),
+            max_confidence_bps: 0,
+            max_staleness_slots: 0,
+            mid_model: MidModel::TopOfBook as u8,
+            vwap_depth_base_lots: 0,
+            empty_book_policy: EmptyBookPolicy::Fail as u8,
+            fallback_price_source: 0,
+            last_mid_price_in_ticks: 0,
+            trader: Pubkey::default(),
+            market: Pubkey::default(),
+            base_mint: Pubkey::default(),
+            quote_mint: Pubkey::default(),
+            base_token_account: Pubkey::default(),
+            quote_token_account: Pubkey::default(),
+            position_bump: 0,
+            base_token_account_bump: 0,
+            quote_token_account_bump: 0,
+            total_base_deposited_lots: 0,
+            total_quote_deposited_lots: 0,
+            total_base_withdrawn_lots: 0,
+            total_quote_withdrawn_lots: 0,
+            base_lots_bought: 0,
+            base_lots_sold: 0,
+            quote_lots_spent: 0,
+            quote_lots_received: 0,
+            cost_basis_quote_lots: 0,
+            realized_pnl_quote_lots: 0,
+            last_base_lots_total: 0,
+            last_quote_lots_total: 0,
+            share_mint: Pubkey::default(),
+            share_mint_bump: 0,
+            fee_recipient: Pubkey::default(),
+            management_fee_bps: 0,
+            performance_fee_bps: 0,
+            high_water_mark: 0,
+            last_fee_timestamp: 0,
+            keeper: Pubkey::default(),
+            keeper_permissions: 0,
+            crank_interval_slots: 0,
+            crank_tip_lamports: 0,
+            crank_tip_budget_lamports: 0,
+            last_crank_slot: 0,
+            is_paused: false,
+            max_base_inventory_lots: 0,
+            max_quote_inventory_lots: 0,
+            max_notional_quote_lots: 0,
+            has_fill_history: false,
+        }
+    }
+}
+
+
+/// Program-owned price account with the same fields as a pyth price, writable by its authority.
+pub struct MockOracle {
+    pub is_initialized: bool,
+    pub authority: Pubkey,
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub publish_slot: u64,
+}
+
+impl MockOracle {
+    pub const SEED: &'static str = "mock_oracle";
+}
+
+impl Sealed for MockOracle {}
+
+impl IsInitialized for MockOracle {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for MockOracle {
+    const LEN: usize = 1 + 32 + 8 + 8 + 4 + 8;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, MockOracle::LEN];
+        let (
+            is_initialized,
+            authority,
+            price,
+            conf,
+            expo,
+            publish_slot,
+        ) = array_refs![src, 1, 32, 8, 8, 4, 8];
+
+        Ok(MockOracle {
+            is_initialized: match is_initialized {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            authority: Pubkey::new_from_array(*authority),
+            price: i64::from_le_bytes(*price),
+            conf: u64::from_le_bytes(*conf),
+            expo: i32::from_le_bytes(*expo),
+            publish_slot: u64::from_le_bytes(*publish_slot),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, MockOracle::LEN];
+        let (
+            is_initialized,
+            authority,
+            price,
+            conf,
+            expo,
+            publish_slot,
+        ) = mut_array_refs![dst, 1, 32, 8, 8, 4, 8];
+        match self.is_initialized {
+            true => is_initialized[0] = 1,
+            false => is_initialized[0] = 0,
+        };
+        *authority = self.authority.to_bytes();
+        *price = self.price.to_le_bytes();
+        *conf = self.conf.to_le_bytes();
+        *expo = self.expo.to_le_bytes();
+        *publish_slot = self.publish_slot.to_le_bytes();
+    }
+}
+
+
+/// Open position indices of a trader on a market.
+pub struct PositionRegistry {
+    pub is_initialized: bool,
+    pub num_positions: u8, // number of used entries in position_indices
+    pub position_indices: [u16; PositionRegistry::MAX_POSITIONS], // open position indices, unused entries are zero
+}
+
+impl PositionRegistry {
+    pub const SEED: &'static str = "registry";
+    pub const MAX_POSITIONS: usize = 16;
+
+    pub fn contains(&self, position_index: u16) -> bool {
+        self.position_indices[..self.num_positions as usize].contains(&position_index)
+    }
+
+    pub fn add(&mut self, position_index: u16) -> Result<(), ProgramError> {
+        if self.contains(position_index) {
+            return Err(CodeError::PositionIsAlreadyInitialized.into());
+        }
+        if self.num_positions as usize >= PositionRegistry::MAX_POSITIONS {
+            return Err(CodeError::PositionRegistryFull.into());
+        }
+        self.position_indices[self.num_positions as usize] = position_index;
+        self.num_positions += 1;
+        Ok(())
+    }
+
+    pub fn remove(&mut self, position_index: u16) -> Result<(), ProgramError> {
+        let num_positions = self.num_positions as usize;
+        let i = self.position_indices[..num_positions]
+            .iter()
+            .position(|index| *index == position_index)
+            .ok_or(CodeError::PositionNotInitialized)?;
+        self.position_indices[i] = self.position_indices[num_positions - 1];
+        self.position_indices[num_positions - 1] = 0;
+        self.num_positions -= 1;
+        Ok(())
+    }
+}
+
+impl Sealed for PositionRegistry {}
+
+impl IsInitialized for PositionRegistry {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for PositionRegistry {
+    const LEN: usize = 1 + 1 + 2 * PositionRegistry::MAX_POSITIONS;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, PositionRegistry::LEN];
+        let (
+            is_initialized,
+            num_positions,
+            position_indices_bytes,
+        ) = array_refs![src, 1, 1, 2 * PositionRegistry::MAX_POSITIONS];
+
+        let num_positions = num_positions[0];
+        if num_positions as usize > PositionRegistry::MAX_POSITIONS {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let mut position_indices = [0u16; PositionRegistry::MAX_POSITIONS];
+        for (index, bytes) in position_indices.iter_mut().zip(position_indices_bytes.chunks_exact(2)) {
+            *index = u16::from_le_bytes([bytes[0], bytes[1]]);
+        }
+
+        Ok(PositionRegistry {
+            is_initialized: match is_initialized {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            num_positions,
+            position_indices,
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, PositionRegistry::LEN];
+        let (
+            is_initialized,
+            num_positions,
+            position_indices_bytes,
+        ) = mut_array_refs![dst, 1, 1, 2 * PositionRegistry::MAX_POSITIONS];
+        match self.is_initialized {
+            true => is_initialized[0] = 1,
+            false => is_initialized[0] = 0,
+        };
+        num_positions[0] = self.num_positions;
+        for (index, bytes) in self.position_indices.iter().zip(position_indices_bytes.chunks_exact_mut(2)) {
+            bytes.copy_from_slice(&index.to_le_bytes());
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FillEntry {
+    pub slot: u64, // slot the fill was observed in, not the slot it happened in
+    pub side: u8, // 0 when the position bought base (bid filled), 1 when it sold base (ask filled)
+    pub price_in_ticks: u64, // average price of the filled quantity
+    pub base_lots: u64, // filled base lots
+}
+
+impl FillEntry {
+    pub const BID: u8 = 0;
+    pub const ASK: u8 = 1;
+    pub const LEN: usize = 8 + 1 + 8 + 8;
+
+    fn unpack_from_slice(src: &[u8; FillEntry::LEN]) -> Self {
+        let (slot, side, price_in_ticks, base_lots) = array_refs![src, 8, 1, 8, 8];
+        FillEntry {
+            slot: u64::from_le_bytes(*slot),
+            side: side[0],
+            price_in_ticks: u64::from_le_bytes(*price_in_ticks),
+            base_lots: u64::from_le_bytes(*base_lots),
+        }
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8; FillEntry::LEN]) {
+        let (slot, side, price_in_ticks, base_lots) = mut_array_refs![dst, 8, 1, 8, 8];
+        *slot = self.slot.to_le_bytes();
+        side[0] = self.side;
+        *price_in_ticks = self.price_in_ticks.to_le_bytes();
+        *base_lots = self.base_lots.to_le_bytes();
+    }
+}
+
+/// Ring buffer of the fills observed on a position, oldest entries are overwritten once full.
+pub struct FillHistory {
+    pub is_initialized: bool,
+    pub position: Pubkey, // position the fills belong to
+    pub bump: u8, // bump of the fill history address
+    pub next_index: u16, // entry the next fill is written to
+    pub num_fills: u64, // fills appended over the life of the account, including overwritten ones
+    pub fills: [FillEntry; FillHistory::CAPACITY],
+}
+
+impl FillHistory {
+    pub const SEED: &'static str = "fills";
+    pub const CAPACITY: usize = 32;
+
+    pub fn push(&mut self, fill: FillEntry) {
+        self.fills[self.next_index as usize] = fill;
+        self.next_index = ((self.next_index as usize + 1) % FillHistory::CAPACITY) as u16;
+        self.num_fills = self.num_fills.saturating_add(1);
+    }
+}
+
+impl Sealed for FillHistory {}
+
+impl IsInitialized for FillHistory {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for FillHistory {
+    const LEN: usize = 1 + 32 + 1 + 2 + 8 + FillEntry::LEN * FillHistory::CAPACITY;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, FillHistory::LEN];
+        let (
+            is_initialized,
+            position,
+            bump,
+            next_index,
+            num_fills,
+            fills_bytes,
+        ) = array_refs![src, 1, 32, 1, 2, 8, FillEntry::LEN * FillHistory::CAPACITY];
+
+        let next_index = u16::from_le_bytes(*next_index);
+        if next_index as usize >= FillHistory::CAPACITY {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let mut fills = [FillEntry::default(); FillHistory::CAPACITY];
+        for (fill, bytes) in fills.iter_mut().zip(fills_bytes.chunks_exact(FillEntry::LEN)) {
+            *fill = FillEntry::unpack_from_slice(array_ref![bytes, 0, FillEntry::LEN]);
+        }
+
+        Ok(FillHistory {
+            is_initialized: match is_initialized {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            position: Pubkey::new_from_array(*position),
+            bump: bump[0],
+            next_index,
+            num_fills: u64::from_le_bytes(*num_fills),
+            fills,
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, FillHistory::LEN];
+        let (
+            is_initialized,
+            position,
+            bump,
+            next_index,
+            num_fills,
+            fills_bytes,
+        ) = mut_array_refs![dst, 1, 32, 1, 2, 8, FillEntry::LEN * FillHistory::CAPACITY];
+        match self.is_initialized {
+            true => is_initialized[0] = 1,
+            false => is_initialized[0] = 0,
+        };
+        *position = self.position.to_bytes();
+        bump[0] = self.bump;
+        *next_index = self.next_index.to_le_bytes();
+        *num_fills = self.num_fills.to_le_bytes();
+        for (fill, bytes) in self.fills.iter().zip(fills_bytes.chunks_exact_mut(FillEntry::LEN)) {
+            fill.pack_into_slice(array_mut_ref![bytes, 0, FillEntry::LEN]);
+        }
+    }
+}
+
+
+pub struct Config {
+    pub is_initialized: bool,
+    pub admin: Pubkey, // can update the config, pause the program and manage market configs
+    pub bump: u8, // bump of the config address
+    pub is_paused: bool, // halts every instruction changing positions, cancels, withdrawals, redemptions, pauses and migrations keep working
+    pub min_spread_margin_bps: u64, // narrowest spread margin accepted in basis points spread mode
+    pub max_spread_margin_bps: u64, // widest spread margin accepted in basis points spread mode
+    pub max_num_levels: u8, // most ladder levels per side
+    pub max_skew_bps: u64, // largest inventory skew
+}
+
+impl Config {
+    pub const SEED: &'static str = "config";
+
+    /// Overwrites the parameter bounds, keeping the admin and pause flag.
+    pub fn set_params(&mut self, params: &ConfigParams) {
+        self.min_spread_margin_bps = params.min_spread_margin_bps;
+        self.max_spread_margin_bps = params.max_spread_margin_bps;
+        self.max_num_levels = params.max_num_levels;
+        self.max_skew_bps = params.max_skew_bps;
+    }
+}
+
+impl Sealed for Config {}
+
+impl IsInitialized for Config {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for Config {
+    const LEN: usize = 1 + 32 + 1 + 1 + 8 + 8 + 1 + 8;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, Config::LEN];
+        let (
+            is_initialized,
+            admin,
+            bump,
+            is_paused,
+            min_spread_margin_bps,
+            max_spread_margin_bps,
+            max_num_levels,
+            max_skew_bps,
+        ) = array_refs![src, 1, 32, 1, 1, 8, 8, 1, 8];
+
+        Ok(Config {
+            is_initialized: match is_initialized {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            admin: Pubkey::new_from_array(*admin),
+            bump: bump[0],
+            is_paused: is_paused[0] != 0,
+            min_spread_margin_bps: u64::from_le_bytes(*min_spread_margin_bps),
+            max_spread_margin_bps: u64::from_le_bytes(*max_spread_margin_bps),
+            max_num_levels: max_num_levels[0],
+            max_skew_bps: u64::from_le_bytes(*max_skew_bps),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, Config::LEN];
+        let (
+            is_initialized,
+            admin,
+            bump,
+            is_paused,
+            min_spread_margin_bps,
+            max_spread_margin_bps,
+            max_num_levels,
+            max_skew_bps,
+        ) = mut_array_refs![dst, 1, 32, 1, 1, 8, 8, 1, 8];
+        match self.is_initialized {
+            true => is_initialized[0] = 1,
+            false => is_initialized[0] = 0,
+        };
+        *admin = self.admin.to_bytes();
+        bump[0] = self.bump;
+        is_paused[0] = self.is_paused as u8;
+        *min_spread_margin_bps = self.min_spread_margin_bps.to_le_bytes();
+        *max_spread_margin_bps = self.max_spread_margin_bps.to_le_bytes();
+        max_num_levels[0] = self.max_num_levels;
+        *max_skew_bps = self.max_skew_bps.to_le_bytes();
+    }
+}
+
+
+pub struct MarketConfig {
+    pub is_initialized: bool,
+    pub market: Pubkey, // phoenix market the config applies to
+    pub bump: u8, // bump of the market config address
+    pub is_allowed: bool, // positions only place orders on allowed markets
+    pub min_spread_in_ticks: u64, // narrowest distance of the top levels from the reference price
+    pub max_order_base_lots: u64, // largest order per ladder level, 0 for no limit
+    pub min_tick_size_in_quote_lots_per_base_unit: u64, // smallest tick size the market may report
+    pub max_tick_size_in_quote_lots_per_base_unit: u64, // largest tick size the market may report
+}
+
+impl MarketConfig {
+    pub const SEED: &'static str = "market_config";
+
+    /// Overwrites the market limits, keeping the allowlist flag.
+    pub fn set_params(&mut self, params: &MarketConfigParams) {
+        self.min_spread_in_ticks = params.min_spread_in_ticks;
+        self.max_order_base_lots = params.max_order_base_lots;
+        self.min_tick_size_in_quote_lots_per_base_unit = params.min_tick_size_in_quote_lots_per_base_unit;
+        self.max_tick_size_in_quote_lots_per_base_unit = params.max_tick_size_in_quote_lots_per_base_unit;
+    }
+
+    /// Guards against quoting on a market whose tick size isn't the one the limits were set for.
+    pub fn is_tick_size_allowed(&self, tick_size_in_quote_lots_per_base_unit: u64) -> bool {
+        tick_size_in_quote_lots_per_base_unit >= self.min_tick_size_in_quote_lots_per_base_unit
+            && tick_size_in_quote_lots_per_base_unit <= self.max_tick_size_in_quote_lots_per_base_unit
+    }
+}
+
+impl Sealed for MarketConfig {}
+
+impl IsInitialized for MarketConfig {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for MarketConfig {
+    const LEN: usize = 1 + 32 + 1 + 1 + 8 + 8 + 8 + 8;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, MarketConfig::LEN];
+        let (
+            is_initialized,
+            market,
+            bump,
+            is_allowed,
+            min_spread_in_ticks,
+            max_order_base_lots,
+            min_tick_size_in_quote_lots_per_base_unit,
+            max_tick_size_in_quote_lots_per_base_unit,
+        ) = array_refs![src, 1, 32, 1, 1, 8, 8, 8, 8];
+
+        Ok(MarketConfig {
+            is_initialized: match is_initialized {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            market: Pubkey::new_from_array(*market),
+            bump: bump[0],
+            is_allowed: is_allowed[0] != 0,
+            min_spread_in_ticks: u64::from_le_bytes(*min_spread_in_ticks),
+            max_order_base_lots: u64::from_le_bytes(*max_order_base_lots),
+            min_tick_size_in_quote_lots_per_base_unit: u64::from_le_bytes(*min_tick_size_in_quote_lots_per_base_unit),
+            max_tick_size_in_quote_lots_per_base_unit: u64::from_le_bytes(*max_tick_size_in_quote_lots_per_base_unit),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, MarketConfig::LEN];
+        let (
+            is_initialized,
+            market,
+            bump,
+            is_allowed,
+            min_spread_in_ticks,
+            max_order_base_lots,
+            min_tick_size_in_quote_lots_per_base_unit,
+            max_tick_size_in_quote_lots_per_base_unit,
+        ) = mut_array_refs![dst, 1, 32, 1, 1, 8, 8, 8, 8];
+        match self.is_initialized {
+            true => is_initialized[0] = 1,
+            false => is_initialized[0] = 0,
+        };
+        *market = self.market.to_bytes();
+        bump[0] = self.bump;
+        is_allowed[0] = self.is_allowed as u8;
+        *min_spread_in_ticks = self.min_spread_in_ticks.to_le_bytes();
+        *max_order_base_lots = self.max_order_base_lots.to_le_bytes();
+        *min_tick_size_in_quote_lots_per_base_unit = self.min_tick_size_in_quote_lots_per_base_unit.to_le_bytes();
+        *max_tick_size_in_quote_lots_per_base_unit = self.max_tick_size_in_quote_lots_per_base_unit.to_le_bytes();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_release_positions_unpack_and_convert() {
+        let mut data = [0u8; LegacyPosition::LEN];
+        data[0] = 1;
+        data[1..9].copy_from_slice(&2u64.to_le_bytes());
+
+        let position = Position::from(LegacyPosition::unpack(&data).unwrap());
+        assert!(position.is_initialized);
+        assert_eq!(position.position_index, Position::LEGACY_POSITION_INDEX);
+        assert_eq!(position.spread_margin, 200);
+        assert!(position.ladder_config().is_ok());
+        assert!(position.needs_migration());
+    }
+
+    #[test]
+    fn legacy_position_seeds_have_no_index() {
+        let trader = Pubkey::new_unique();
+        let market = Pubkey::new_unique();
+
+        let legacy_seeds = PositionSeeds::new(&trader, &market, Position::LEGACY_POSITION_INDEX, 7);
+        assert_eq!(legacy_seeds.signer_seeds(), vec![Position::SEED.as_bytes(), trader.as_ref(), market.as_ref(), &[7]]);
+
+        let seeds = PositionSeeds::new(&trader, &market, 3, 7);
+        assert_eq!(seeds.signer_seeds(), vec![Position::SEED.as_bytes(), trader.as_ref(), market.as_ref(), &3u16.to_le_bytes(), &[7]]);
+    }
+
+    #[test]
+    fn only_missing_data_needs_migration() {
+        let mut position = Position::from(LegacyPosition { is_initialized: true, spread_margin: 1 });
+        position.version = 2;
+        assert!(position.needs_migration());
+        position.version = 3;
+        assert!(!position.needs_migration());
+    }
+
+    #[test]
+    fn fill_history_flag_survives_pack() {
+        let mut position = Position::from(LegacyPosition { is_initialized: true, spread_margin: 1 });
+        position.has_fill_history = true;
+        let mut data = [0u8; Position::LEN];
+        Position::pack(position, &mut data).unwrap();
+        assert!(Position::unpack(&data).unwrap().has_fill_history);
+    }
+
+    fn fill_history() -> FillHistory {
+        FillHistory {
+            is_initialized: true,
+            position: Pubkey::new_unique(),
+            bump: 255,
+            next_index: 0,
+            num_fills: 0,
+            fills: [FillEntry::default(); FillHistory::CAPACITY],
+        }
+    }
+
+    fn fill(slot: u64) -> FillEntry {
+        FillEntry { slot, side: FillEntry::BID, price_in_ticks: 100, base_lots: 1 }
+    }
+
+    #[test]
+    fn fill_history_wraps_around_at_capacity() {
+        let mut fill_history = fill_history();
+        for slot in 0..FillHistory::CAPACITY as u64 - 1 {
+            fill_history.push(fill(slot));
+        }
+        assert_eq!(fill_history.next_index as usize, FillHistory::CAPACITY - 1);
+
+        fill_history.push(fill(FillHistory::CAPACITY as u64 - 1));
+        assert_eq!(fill_history.next_index, 0);
+
+        // the oldest entries are overwritten first
+        for slot in FillHistory::CAPACITY as u64..FillHistory::CAPACITY as u64 + 3 {
+            fill_history.push(fill(slot));
+        }
+        assert_eq!(fill_history.next_index, 3);
+        assert_eq!(fill_history.num_fills, FillHistory::CAPACITY as u64 + 3);
+        assert_eq!(fill_history.fills[0], fill(FillHistory::CAPACITY as u64));
+        assert_eq!(fill_history.fills[2], fill(FillHistory::CAPACITY as u64 + 2));
+        assert_eq!(fill_history.fills[3], fill(3));
+
+        let mut data = vec![0u8; FillHistory::LEN];
+        FillHistory::pack(fill_history, &mut data).unwrap();
+        let unpacked = FillHistory::unpack(&data).unwrap();
+        assert_eq!((unpacked.next_index, unpacked.num_fills), (3, FillHistory::CAPACITY as u64 + 3));
+        assert_eq!(unpacked.fills[0], fill(FillHistory::CAPACITY as u64));
+    }
+
+    #[test]
+    fn fill_history_count_saturates() {
+        let mut fill_history = fill_history();
+        fill_history.num_fills = u64::MAX - 1;
+        fill_history.push(fill(1));
+        fill_history.push(fill(2));
+        assert_eq!(fill_history.num_fills, u64::MAX);
+        assert_eq!(fill_history.next_index, 2);
+    }
+
+    #[test]
+    fn fill_history_with_out_of_range_index_is_rejected() {
+        let mut data = vec![0u8; FillHistory::LEN];
+        FillHistory::pack(fill_history(), &mut data).unwrap();
+        data[34..36].copy_from_slice(&(FillHistory::CAPACITY as u16).to_le_bytes());
+        assert_eq!(FillHistory::unpack(&data).err(), Some(ProgramError::InvalidAccountData));
+    }
+
+    #[test]
+    fn new_positions_keep_their_limits() {
+        let mut params = PositionParams {
+            spread_mode: 1,
+            spread_margin: 10,
+            num_levels: 1,
+            level_spacing_mode: 1,
+            level_spacing: 0,
+            size_profile: 1,
+            size_profile_param: 0,
+            target_base_ratio_bps: 0,
+            skew_bps: 0,
+            price_source: 1,
+            oracle: Pubkey::default(),
+            max_confidence_bps: 0,
+            max_staleness_slots: 0,
+            mid_model: 1,
+            vwap_depth_base_lots: 0,
+            empty_book_policy: 1,
+            fallback_price_source: 0,
+            max_base_inventory_lots: 10,
+            max_quote_inventory_lots: 20,
+            max_notional_quote_lots: 30,
+        };
+        let position = Position::new(0, &params);
+        assert_eq!((position.max_base_inventory_lots, position.max_quote_inventory_lots, position.max_notional_quote_lots), (10, 20, 30));
+
+        params.max_base_inventory_lots = 0;
+        assert_eq!(Position::new(0, &params).max_base_inventory_lots, 0);
     }
 }
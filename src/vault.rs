@@ -0,0 +1,130 @@
+use solana_program::program_error::ProgramError;
+
+use crate::{error::CodeError, pricing::MarketParams};
+
+
+/// Value of `base_lots` and `quote_lots` in quote lots, base valued at `price_in_ticks`, rounded down.
+pub fn value_in_quote_lots(market_params: &MarketParams, price_in_ticks: u64, base_lots: u64, quote_lots: u64) -> Result<u64, ProgramError> {
+    if market_params.base_lots_per_base_unit == 0 {
+        return Err(CodeError::ArithmeticOverflow.into());
+    }
+    let base_value = (price_in_ticks as u128)
+        .checked_mul(market_params.tick_size_in_quote_lots_per_base_unit as u128)
+        .and_then(|value| value.checked_mul(base_lots as u128))
+        .ok_or(CodeError::ArithmeticOverflow)?
+        / market_params.base_lots_per_base_unit as u128;
+    let value = base_value
+        .checked_add(quote_lots as u128)
+        .ok_or(CodeError::ArithmeticOverflow)?;
+    u64::try_from(value).map_err(|_| CodeError::ArithmeticOverflow.into())
+}
+
+/// Shares minted for a deposit worth `deposit_value` into a vault worth `nav` with `share_supply` shares outstanding, rounded down.
+/// The first deposit mints one share per quote lot of value. A vault with shares outstanding and nothing left takes no deposits,
+/// its worthless shares would claim part of them.
+pub fn shares_for_deposit(deposit_value: u64, nav: u64, share_supply: u64) -> Result<u64, ProgramError> {
+    if share_supply == 0 {
+        return Ok(deposit_value);
+    }
+    if nav == 0 {
+        return Err(CodeError::InvalidShareAmount.into());
+    }
+    pro_rata(deposit_value, share_supply, nav)
+}
+
+/// Part of `amount` owned by `shares` out of `share_supply`, rounded down.
+pub fn amount_for_shares(amount: u64, shares: u64, share_supply: u64) -> Result<u64, ProgramError> {
+    if shares > share_supply {
+        return Err(CodeError::InvalidShareAmount.into());
+    }
+    pro_rata(amount, shares, share_supply)
+}
+
+fn pro_rata(amount: u64, numerator: u64, denominator: u64) -> Result<u64, ProgramError> {
+    if denominator == 0 {
+        return Err(CodeError::ArithmeticOverflow.into());
+    }
+    let value = (amount as u128)
+        .checked_mul(numerator as u128)
+        .ok_or(CodeError::ArithmeticOverflow)?
+        / denominator as u128;
+    u64::try_from(value).map_err(|_| CodeError::ArithmeticOverflow.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market_params() -> MarketParams {
+        MarketParams {
+            base_lots_per_base_unit: 1_000,
+            tick_size_in_quote_lots_per_base_unit: 10,
+            base_atoms_per_base_lot: 1_000_000,
+            quote_atoms_per_quote_lot: 1,
+            tick_size_in_quote_atoms_per_base_unit: 10,
+            quote_decimals: 6,
+            raw_base_units_per_base_unit: 1,
+        }
+    }
+
+    #[test]
+    fn base_is_valued_at_the_price_rounded_down() {
+        // 500 base lots = 0.5 base units at 1500 quote lots per base unit
+        assert_eq!(value_in_quote_lots(&market_params(), 150, 500, 0), Ok(750));
+        assert_eq!(value_in_quote_lots(&market_params(), 150, 500, 250), Ok(1_000));
+        // 1 base lot is worth 1.5 quote lots
+        assert_eq!(value_in_quote_lots(&market_params(), 150, 1, 0), Ok(1));
+        assert_eq!(value_in_quote_lots(&market_params(), 0, 500, 250), Ok(250));
+    }
+
+    #[test]
+    fn oversized_values_overflow() {
+        assert_eq!(value_in_quote_lots(&market_params(), u64::MAX, u64::MAX, 0), Err(CodeError::ArithmeticOverflow.into()));
+        assert_eq!(value_in_quote_lots(&market_params(), 0, 0, u64::MAX), Ok(u64::MAX));
+        assert_eq!(value_in_quote_lots(&market_params(), 100, 1_000, u64::MAX), Err(CodeError::ArithmeticOverflow.into()));
+        let market_params = MarketParams { base_lots_per_base_unit: 0, ..market_params() };
+        assert_eq!(value_in_quote_lots(&market_params, 150, 500, 0), Err(CodeError::ArithmeticOverflow.into()));
+    }
+
+    #[test]
+    fn first_deposit_mints_one_share_per_quote_lot() {
+        assert_eq!(shares_for_deposit(1_000, 0, 0), Ok(1_000));
+        // funds sent to an empty vault go to its first depositor
+        assert_eq!(shares_for_deposit(1_000, 5_000, 0), Ok(1_000));
+    }
+
+    #[test]
+    fn deposits_mint_shares_pro_rata_to_nav() {
+        // 2 quote lots per share
+        assert_eq!(shares_for_deposit(1_000, 10_000, 5_000), Ok(500));
+        // 0.5 quote lots per share
+        assert_eq!(shares_for_deposit(1_000, 10_000, 20_000), Ok(2_000));
+    }
+
+    #[test]
+    fn deposits_round_down_in_the_vault_favour() {
+        // 1001 / 2 = 500.5 shares
+        assert_eq!(shares_for_deposit(1_001, 10_000, 5_000), Ok(500));
+        assert_eq!(shares_for_deposit(1, 10_000, 5_000), Ok(0));
+    }
+
+    #[test]
+    fn deposits_into_a_worthless_vault_are_rejected() {
+        assert_eq!(shares_for_deposit(1_000, 0, 5_000), Err(CodeError::InvalidShareAmount.into()));
+    }
+
+    #[test]
+    fn redemptions_pay_pro_rata_rounded_down() {
+        assert_eq!(amount_for_shares(10_000, 500, 5_000), Ok(1_000));
+        // 1000 / 3 = 333.33
+        assert_eq!(amount_for_shares(1_000, 1, 3), Ok(333));
+        assert_eq!(amount_for_shares(1_000, 3, 3), Ok(1_000));
+        assert_eq!(amount_for_shares(0, 3, 3), Ok(0));
+    }
+
+    #[test]
+    fn redeeming_more_than_the_supply_is_rejected() {
+        assert_eq!(amount_for_shares(1_000, 4, 3), Err(CodeError::InvalidShareAmount.into()));
+        assert_eq!(amount_for_shares(1_000, 1, 0), Err(CodeError::InvalidShareAmount.into()));
+    }
+}